@@ -1,19 +1,264 @@
-use std::{path::PathBuf, process::Command};
+use std::{cell::RefCell, env, fs, io::{self, IsTerminal, Read, Write}, path::{Path, PathBuf}, process::Command, str::FromStr, thread, time::{Duration, SystemTime}};
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
-use crate::compiler;
+use kairo::compiler::{self, codegen::rust::{CodegenOptions, Overflow}, config::{CliOverrides, Config}, interp::{repl::ReplSession, Limits}, semantics::{diagnostics::{self, ColorChoice, Diagnostic, Diagnostics, RenderOptions, Reporter, Severity}, SemanticOptions}, EmitKind, OutputOptions};
 
 /// Kairo命令行接口 - 运行和构建.kr文件
 /// 
 /// 使用clap库提供现代化的命令行参数解析
 #[derive(Parser, Debug)]
 #[command(name = "kairo", version, about = "Kairo language toolchain (MVP)")]
-struct Cli {
+pub struct Cli {
     /// 子命令
     #[command(subcommand)]
     command: Commands,
+    /// 出错时显示完整的错误链（也可设置环境变量KAIRO_FULL_ERROR=1）
+    #[arg(long, global = true)]
+    full_errors: bool,
+    /// 错误信息中标记出错位置使用的字符，默认为 `^`（也可设置环境变量KAIRO_CARET_CHAR）
+    #[arg(long, global = true, value_parser = diagnostics::parse_caret_char)]
+    caret_char: Option<char>,
+    /// 诊断信息的输出格式：human（默认，带颜色的文本）或json（供编辑器等工具解析的JSON数组）
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "human")]
+    diagnostics: DiagnosticFormat,
+    /// 把本次命令的全部诊断信息以JSON数组写入指定文件（格式与--diagnostics=json相同，与其是否指定无关；
+    /// 会自动创建所在目录）
+    #[arg(long, global = true, value_name = "FILE")]
+    diagnostics_out: Option<PathBuf>,
+    /// 不在标准错误输出诊断信息和错误，只写入--diagnostics-out指定的文件
+    #[arg(long, global = true, requires = "diagnostics_out")]
+    no_stderr_diagnostics: bool,
+    /// 诊断信息是否带颜色：auto（标准错误是终端时）、always或never，覆盖环境变量NO_COLOR
+    /// （未指定时只要没有设置NO_COLOR就带颜色）
+    #[arg(long, global = true, value_name = "WHEN")]
+    color: Option<ColorChoice>,
+}
+
+impl Cli {
+    /// 是否显示完整的错误链
+    /// 
+    /// # 返回值
+    /// * `bool` - 指定了--full-errors，或环境变量KAIRO_FULL_ERROR为非空且不为0时返回true
+    pub fn full_errors(&self) -> bool {
+        self.full_errors || env::var("KAIRO_FULL_ERROR").is_ok_and(|v| !v.is_empty() && v != "0")
+    }
+
+    /// 命令行中指定的全局设置（各子命令再补充自己的参数，如--out-dir）
+    fn global_overrides(&self) -> CliOverrides {
+        CliOverrides { color: self.color, caret_char: self.caret_char, full_errors: self.full_errors, ..Default::default() }
+    }
+
+    /// 是否以JSON格式输出诊断信息（此时错误已包含在JSON输出中，调用方不应再次输出）
+    pub fn json_diagnostics(&self) -> bool {
+        self.diagnostics == DiagnosticFormat::Json
+    }
+
+    /// 是否在标准错误输出诊断信息（指定了--no-stderr-diagnostics时为false，调用方不应输出错误）
+    pub fn stderr_diagnostics(&self) -> bool {
+        !self.no_stderr_diagnostics
+    }
+
+    /// 以文本输出诊断信息时的显示选项
+    /// 
+    /// # 返回值
+    /// * `RenderOptions` - 颜色和标记字符依次取命令行参数（--color、--caret-char）、
+    ///   环境变量（NO_COLOR、KAIRO_CARET_CHAR）和默认值（带颜色、`^`），见`Config`
+    pub fn render_options(&self) -> RenderOptions {
+        let config = Config::resolve("", &self.global_overrides()).expect("empty source has no pragma");
+        RenderOptions { color: config.color.value, caret_char: config.caret_char.value }
+    }
+}
+
+/// 本次命令的诊断信息输出方式（由全局参数决定，传给各子命令）
+/// 
+/// # 字段
+/// * `json` - 是否以JSON格式输出到标准错误（--diagnostics=json）
+/// * `stderr` - 是否在标准错误输出（--no-stderr-diagnostics时为false）
+/// * `file` - 同时以JSON写入的文件（--diagnostics-out）
+/// * `render` - 以文本输出时的显示选项
+/// * `reporter` - 传给编译函数的警告报告目标：需要JSON时保存起来，以文本输出时同时按`render`立即输出
+/// * `failures` - 处理多个文件时，各个失败文件的错误（见`for_each_file`）
+struct DiagnosticOutput {
+    json: bool,
+    stderr: bool,
+    file: Option<PathBuf>,
+    render: RenderOptions,
+    reporter: Reporter,
+    failures: RefCell<Vec<Diagnostic>>,
+}
+
+impl DiagnosticOutput {
+    /// 根据命令行参数创建
+    fn new(cli: &Cli) -> Self {
+        let json = cli.json_diagnostics();
+        let stderr = cli.stderr_diagnostics();
+        let file = cli.diagnostics_out.clone();
+        let render = cli.render_options();
+        let echo = (!json && stderr).then_some(render);
+        let reporter = Reporter::new(echo, json || file.is_some());
+        DiagnosticOutput { json, stderr, file, render, reporter, failures: RefCell::new(Vec::new()) }
+    }
+
+    /// 是否需要保存诊断信息（以JSON输出到标准错误或写入文件）
+    fn collecting(&self) -> bool {
+        self.json || self.file.is_some()
+    }
+
+    /// 是否以文本在标准错误输出诊断信息
+    fn human(&self) -> bool {
+        !self.json && self.stderr
+    }
+
+    /// 记录处理多个文件时某个文件的错误（见`for_each_file`）
+    fn record_failure(&self, e: &anyhow::Error, file: &Path) {
+        self.failures.borrow_mut().extend(error_diagnostics(e, &file.display().to_string()));
+    }
+
+    /// 命令（或监视模式下的一轮）结束时，以JSON输出全部诊断信息
+    /// 
+    /// # 参数
+    /// * `result` - 命令的执行结果
+    /// 
+    /// # 返回值
+    /// * `Result<()>` - 无法写入--diagnostics-out指定的文件时返回错误
+    /// 
+    /// # 说明
+    /// 包括已报告的警告和导致失败的错误，按出错位置排序；没有诊断信息时为 `[]`。
+    /// 错误本身不是诊断信息时（如无法读取文件、rustc编译失败），作为一条没有位置信息的错误输出；
+    /// 处理多个文件时使用各文件的错误，不包括最后的汇总错误。
+    /// --diagnostics=json时输出到标准错误（除非指定了--no-stderr-diagnostics），
+    /// 指定了--diagnostics-out时写入该文件（覆盖原有内容）
+    fn finish(&self, result: &Result<()>) -> Result<()> {
+        if !self.collecting() {
+            return Ok(());
+        }
+        let mut all = self.reporter.take();
+        let failures = self.failures.take();
+        if !failures.is_empty() {
+            all.extend(failures);
+        } else if let Err(e) = result {
+            all.extend(error_diagnostics(e, ""));
+        }
+        // 按出错位置排序（位置相同时保持报告顺序），与各分析阶段的检查顺序无关
+        all.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        let json = diagnostics::to_json(&all);
+
+        if self.json && self.stderr {
+            eprintln!("{json}");
+        }
+        if let Some(path) = &self.file {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).with_context(|| format!("create dir: {}", parent.display()))?;
+            }
+            fs::write(path, format!("{json}\n")).with_context(|| format!("write file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// 取出错误中的诊断信息
+/// 
+/// # 参数
+/// * `e` - 错误
+/// * `file` - 错误不是`Diagnostics`时，所生成诊断的文件名
+/// 
+/// # 返回值
+/// * `Vec<Diagnostic>` - 错误链中的`Diagnostics`；没有时为一条只有完整错误信息的诊断
+fn error_diagnostics(e: &anyhow::Error, file: &str) -> Vec<Diagnostic> {
+    match e.chain().find_map(|cause| cause.downcast_ref::<Diagnostics>()) {
+        Some(errors) => errors.0.clone(),
+        None => vec![Diagnostic::plain(Severity::Error, file, None, &format!("{e:#}"))],
+    }
+}
+
+/// 以文本输出错误的根本原因（通常是编译器构造的友好消息）
+/// 
+/// # 参数
+/// * `e` - 错误
+/// * `options` - 显示选项（根本原因是`Diagnostics`时用于渲染诊断块）
+pub fn print_error(e: &anyhow::Error, options: &RenderOptions) {
+    let root = e.chain().last().unwrap_or(&**e);
+    match root.downcast_ref::<Diagnostics>() {
+        Some(errors) => eprintln!("{}", errors.render(options)),
+        None => eprintln!("{root}"),
+    }
+}
+
+/// 诊断信息的输出格式
+/// 
+/// # 变体
+/// * `Human` - 带源码行和修复建议的彩色文本，逐条输出到标准错误
+/// * `Json` - 命令结束时把全部诊断信息作为一个JSON数组输出到标准错误（格式见`Diagnostic::to_json`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiagnosticFormat {
+    /// 彩色文本
+    Human,
+    /// JSON数组
+    Json,
+}
+
+impl FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(DiagnosticFormat::Human),
+            "json" => Ok(DiagnosticFormat::Json),
+            _ => Err(format!("unknown diagnostics format `{s}` (expected: human, json)")),
+        }
+    }
+}
+
+/// 语义检查的命令行参数（run、build、check、repl和emit-rust共用）
+#[derive(Args, Debug)]
+struct SemanticFlags {
+    /// 宽松模式：重新赋值不可变变量只报告警告
+    #[arg(long)]
+    lenient: bool,
+    /// 对没有任何效果的语句（如 `x = x`）报告警告
+    #[arg(long)]
+    warn_no_effect: bool,
+    /// 对与顶层常量同名（遮蔽了常量）的函数参数报告警告
+    #[arg(long)]
+    warn_shadow: bool,
+    /// 把所有警告当作错误：有警告时以非0状态退出
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+/// 解释器执行上限的命令行参数（run --interpret和repl共用，见`Limits`）
+#[derive(Args, Debug)]
+struct LimitFlags {
+    /// 解释器中函数调用的最大嵌套深度（默认1000）
+    #[arg(long, value_name = "N")]
+    max_call_depth: Option<usize>,
+    /// 解释器最多执行的语句条数（默认10000000；repl中对每次输入分别计算）
+    #[arg(long, value_name = "N")]
+    max_steps: Option<u64>,
+}
+
+impl LimitFlags {
+    /// 是否指定了任何上限
+    fn is_set(&self) -> bool {
+        self.max_call_depth.is_some() || self.max_steps.is_some()
+    }
+}
+
+impl From<SemanticFlags> for SemanticOptions {
+    fn from(flags: SemanticFlags) -> Self {
+        let SemanticFlags { lenient, warn_no_effect, warn_shadow, deny_warnings } = flags;
+        SemanticOptions { lenient, warn_no_effect, warn_shadow, deny_warnings, ..Default::default() }
+    }
+}
+
+/// 解析命令行参数
+/// 
+/// # 返回值
+/// * `Cli` - 解析后的命令行参数（参数无效时clap会打印帮助并退出）
+pub fn parse_args() -> Cli {
+    Cli::parse()
 }
 
 /// 支持的命令类型
@@ -21,56 +266,478 @@ struct Cli {
 enum Commands {
     /// 直接运行.kr文件（编译为临时可执行文件然后执行）
     Run {
-        /// .kr源文件路径
-        file: PathBuf,
+        /// .kr源文件路径或通配符模式（如 `src/**/*.kr`，`-`表示从标准输入读取）；指定多个文件时依次运行，某个文件失败时继续运行其余文件
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<PathBuf>,
+        /// 不展开FILE中的通配符（`*`、`**`和`?`），按原样作为文件路径
+        #[arg(long)]
+        no_glob: bool,
+        /// 用解释器直接执行，不生成Rust代码也不调用rustc（启动更快）
+        #[arg(long)]
+        interpret: bool,
+        /// 监视源文件，每次保存后重新编译并运行（按Ctrl-C退出）
+        #[arg(long)]
+        watch: bool,
+        /// 构建产物和中间文件的输出目录（默认target/kairo_out）
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// 语义检查的参数
+        #[command(flatten)]
+        semantic: SemanticFlags,
+        /// 解释器的执行上限（只用于--interpret）
+        #[command(flatten)]
+        limits: LimitFlags,
+        /// 整数加、减、乘和取负溢出时的处理方式：check（默认，报告运行时错误；--interpret只支持check）、wrap（回绕）或saturate（饱和），覆盖文件中的编译指示
+        #[arg(long, value_name = "MODE")]
+        overflow: Option<Overflow>,
+        /// 传给程序的命令行参数（写在 `--` 之后，如 `kairo run file.kr -- foo bar`；解释器目前不使用）
+        #[arg(last = true, value_name = "ARGS")]
+        args: Vec<String>,
     },
     /// 将.kr文件构建为可执行文件
     Build {
-        /// .kr源文件路径
+        /// .kr源文件路径或通配符模式（如 `src/**/*.kr`，`-`表示从标准输入读取）；指定多个文件时各自构建，某个文件失败时继续构建其余文件
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<PathBuf>,
+        /// 不展开FILE中的通配符（`*`、`**`和`?`），按原样作为文件路径
+        #[arg(long)]
+        no_glob: bool,
+        /// 使用优化构建（覆盖文件中的编译指示）
+        #[arg(long)]
+        release: bool,
+        /// 整数加、减、乘和取负溢出时的处理方式：check（默认，报告运行时错误）、wrap（回绕）或saturate（饱和），覆盖文件中的编译指示
+        #[arg(long, value_name = "MODE")]
+        overflow: Option<Overflow>,
+        /// 要生成的构建产物，逗号分隔（exe, rust, ast, dot）
+        #[arg(long, value_delimiter = ',', default_value = "exe")]
+        emit: Vec<EmitKind>,
+        /// 可执行文件的输出路径（默认为输出目录下的<文件名>，会自动创建所在目录）
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 构建产物和中间文件的输出目录（默认target/kairo_out）
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// 把生成的Rust代码写入指定目录（默认为输出目录），并保留该文件
+        #[arg(long, value_name = "DIR")]
+        emit_rust_to: Option<PathBuf>,
+        /// 编译成功后保留作为rustc输入的中间.rs文件（默认删除）
+        #[arg(long)]
+        keep_temps: bool,
+        /// 通过生成的kairo_print函数输出（便于嵌入时重定向输出）
+        #[arg(long)]
+        print_helper: bool,
+        /// 生成代码的入口函数名（默认main；其他名称生成pub fn，仅用于--emit rust）
+        #[arg(long, value_parser = parse_entry_name)]
+        entry: Option<String>,
+        /// 语义检查的参数
+        #[command(flatten)]
+        semantic: SemanticFlags,
+        /// 用rustfmt格式化生成的Rust代码（未安装rustfmt时输出警告并保留原样）
+        #[arg(long)]
+        rustfmt: bool,
+        /// 在生成的Rust代码中用注释说明每条语句为何这样转换
+        #[arg(long)]
+        explain_codegen: bool,
+        /// 只打印将要执行的rustc命令，不编译可执行文件（仍会生成.rs文件）
+        #[arg(long)]
+        print_rustc_command: bool,
+        /// 监视源文件，每次保存后重新构建（按Ctrl-C退出）
+        #[arg(long)]
+        watch: bool,
+    },
+    /// 只解析并执行语义检查，不生成代码也不调用rustc（有错误时以非0状态退出；只有警告时通过）
+    Check {
+        /// .kr源文件路径（`-`表示从标准输入读取）
+        file: PathBuf,
+        /// 语义检查的参数
+        #[command(flatten)]
+        semantic: SemanticFlags,
+        /// 检查通过后输出降级（常量折叠等）之后、即将交给代码生成的抽象语法树
+        #[arg(long)]
+        dump_lowered: bool,
+    },
+    /// 把.kr文件格式化为规范的写法（直接改写文件；从标准输入读取时输出到标准输出）
+    Fmt {
+        /// .kr源文件路径（`-`表示从标准输入读取）
         file: PathBuf,
-        /// 使用优化构建
+        /// 只检查是否已经格式化，不改写文件（未格式化时以非0状态退出）
+        #[arg(long)]
+        check: bool,
+    },
+    /// 交互式解释器：逐行输入并立即执行（输入 `:quit` 退出）
+    Repl {
+        /// 语义检查的参数
+        #[command(flatten)]
+        semantic: SemanticFlags,
+        /// 解释器的执行上限
+        #[command(flatten)]
+        limits: LimitFlags,
+    },
+    /// 打印合并后的全部生效设置及每项设置的来源（默认值、环境变量、编译指示或命令行参数）
+    DumpConfig {
+        /// .kr源文件路径（读取其中的编译指示；`-`表示从标准输入读取）
+        file: Option<PathBuf>,
+        /// 使用优化构建（覆盖文件中的编译指示）
         #[arg(long)]
         release: bool,
+        /// 构建产物和中间文件的输出目录（默认target/kairo_out）
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+        /// 整数加、减、乘和取负溢出时的处理方式：check、wrap或saturate
+        #[arg(long, value_name = "MODE")]
+        overflow: Option<Overflow>,
+    },
+    /// 只生成Rust代码并输出，不调用rustc
+    EmitRust {
+        /// .kr源文件路径（`-`表示从标准输入读取）
+        file: PathBuf,
+        /// 写入指定文件而不是标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 通过生成的kairo_print函数输出（便于嵌入时重定向输出）
+        #[arg(long)]
+        print_helper: bool,
+        /// 生成代码的入口函数名（默认main；其他名称生成pub fn）
+        #[arg(long, value_parser = parse_entry_name)]
+        entry: Option<String>,
+        /// 整数加、减、乘和取负溢出时的处理方式：check（默认，报告运行时错误）、wrap（回绕）或saturate（饱和），覆盖文件中的编译指示
+        #[arg(long, value_name = "MODE")]
+        overflow: Option<Overflow>,
+        /// 语义检查的参数
+        #[command(flatten)]
+        semantic: SemanticFlags,
+        /// 用rustfmt格式化生成的Rust代码（未安装rustfmt时输出警告并保留原样）
+        #[arg(long)]
+        rustfmt: bool,
+        /// 在生成的Rust代码中用注释说明每条语句为何这样转换
+        #[arg(long)]
+        explain_codegen: bool,
     },
 }
 
 /// 运行CLI程序
 /// 
+/// # 参数
+/// * `cli` - 解析后的命令行参数
+/// 
 /// # 返回值
 /// * `Result<()>` - 成功返回Ok(())，失败返回错误信息
 /// 
 /// # 功能
-/// 1. 解析命令行参数
-/// 2. 根据子命令执行相应操作
-pub fn run() -> Result<()> {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::Run { file } => run_file(file),
-        Commands::Build { file, release } => build_file(file, release).map(|_| ()),
+/// 根据子命令执行相应操作
+pub fn run(cli: Cli) -> Result<()> {
+    let out = DiagnosticOutput::new(&cli);
+    let global = cli.global_overrides();
+    let result = match cli.command {
+        Commands::Run { files, no_glob, interpret, watch: watch_file, out_dir, semantic, limits, overflow, args } => {
+            if limits.is_set() && !interpret {
+                return Err(anyhow!("--max-call-depth and --max-steps only apply to the interpreter; add --interpret"));
+            }
+            let LimitFlags { max_call_depth, max_steps } = limits;
+            let overrides = CliOverrides { out_dir, overflow, max_call_depth, max_steps, ..global };
+            let output = OutputOptions::default();
+            let semantic_options = SemanticOptions::from(semantic);
+            let run = |file: &Path| run_file(file.to_path_buf(), interpret, &args, &overrides, &output, &semantic_options, &out.reporter);
+            match files.as_slice() {
+                [file] if watch_file => watch(file, &out, || run(file)),
+                _ if watch_file => Err(anyhow!("--watch can only watch a single file")),
+                _ => for_each_file(&files, !no_glob, "ran", &out, run),
+            }
+        }
+        Commands::Build {
+            files,
+            no_glob,
+            release,
+            overflow,
+            mut emit,
+            output,
+            out_dir,
+            emit_rust_to,
+            keep_temps,
+            print_helper,
+            entry,
+            semantic,
+            rustfmt,
+            explain_codegen,
+            print_rustc_command,
+            watch: watch_file,
+        } => {
+            if output.is_some() && !emit.contains(&EmitKind::Exe) {
+                return Err(anyhow!("--output sets the executable path; add `exe` to --emit to use it"));
+            }
+            if output.is_some() && files.len() > 1 {
+                return Err(anyhow!("--output sets the path of a single executable; it cannot be used when building several files"));
+            }
+            let semantic_options = SemanticOptions::from(semantic);
+            let overrides = CliOverrides { out_dir, release: release.then_some(true), overflow, ..global };
+            let codegen_options = CodegenOptions { print_helper, entry, rustfmt, explain: explain_codegen, ..Default::default() };
+            // 指定了Rust代码的输出目录时，生成的Rust代码就是构建产物之一
+            if emit_rust_to.is_some() && !emit.contains(&EmitKind::Rust) {
+                emit.push(EmitKind::Rust);
+            }
+            let output = OutputOptions { exe: output, rust_dir: emit_rust_to, keep_temps };
+            let build = |file: &Path| {
+                let (source, name) = read_input(file)?;
+                if print_rustc_command {
+                    print_rustc_invocation(&source, &name, &overrides, &emit, &output, &semantic_options, &codegen_options, &out.reporter)
+                } else {
+                    build_file(&source, &name, &overrides, &emit, &output, &semantic_options, &codegen_options, &out.reporter).map(|_| ())
+                }
+            };
+            match files.as_slice() {
+                [file] if watch_file => watch(file, &out, || build(file)),
+                _ if watch_file => Err(anyhow!("--watch can only watch a single file")),
+                _ => for_each_file(&files, !no_glob, "built", &out, build),
+            }
+        }
+        Commands::Check { file, semantic, dump_lowered } => check_file(file, &semantic.into(), dump_lowered, &out),
+        Commands::Fmt { file, check } => fmt_file(file, check),
+        Commands::Repl { semantic, limits: LimitFlags { max_call_depth, max_steps } } => {
+            let limits = Config::resolve("", &CliOverrides { max_call_depth, max_steps, ..global })?.limits();
+            repl(semantic.into(), limits, &out.render)
+        }
+        Commands::DumpConfig { file, release, out_dir, overflow } => {
+            dump_config(file, &CliOverrides { out_dir, release: release.then_some(true), overflow, ..global })
+        }
+        Commands::EmitRust { file, output, print_helper, entry, overflow, semantic, rustfmt, explain_codegen } => {
+            let semantic_options = SemanticOptions::from(semantic);
+            let overrides = CliOverrides { overflow, ..global };
+            let codegen_options = CodegenOptions { print_helper, entry, rustfmt, explain: explain_codegen, ..Default::default() };
+            emit_rust_file(file, output, &overrides, &semantic_options, &codegen_options, &out.reporter)
+        }
+    };
+    out.finish(&result)?;
+    result
+}
+
+/// 依次处理多个源文件，某个文件失败时继续处理其余文件
+/// 
+/// # 参数
+/// * `files` - .kr源文件路径或通配符模式
+/// * `glob` - 是否展开通配符（见`expand_globs`；命令行参数--no-glob时为false）
+/// * `verb` - 汇总信息中的动词（如 `built`）
+/// * `out` - 诊断信息的输出方式
+/// * `process` - 处理单个文件的操作
+/// 
+/// # 返回值
+/// * `Result<()>` - 全部成功时返回Ok(())，否则返回列出失败文件的汇总错误
+///   （需要JSON时各文件的错误记录在`out`中，见`DiagnosticOutput::finish`）
+/// 
+/// # 说明
+/// 先展开通配符，展开后只有一个文件时直接返回其结果，输出与只处理一个文件时完全相同；
+/// 有多个文件时，每个失败的文件输出其错误和 `failed: 文件名`，最后输出成功的文件数，
+/// 如 `2 of 3 files built`。标准输入（`-`）不能与其他文件一起使用
+fn for_each_file(files: &[PathBuf], glob: bool, verb: &str, out: &DiagnosticOutput, mut process: impl FnMut(&Path) -> Result<()>) -> Result<()> {
+    let files = if glob { expand_globs(files)? } else { files.to_vec() };
+    if let [file] = files.as_slice() {
+        return process(file);
+    }
+    if files.iter().any(|file| file == Path::new("-")) {
+        return Err(anyhow!("`-` (stdin) cannot be combined with other files"));
+    }
+
+    let mut failed = Vec::new();
+    for file in &files {
+        let Err(e) = process(file) else {
+            continue;
+        };
+        if out.collecting() {
+            out.record_failure(&e, file);
+        }
+        if out.human() {
+            print_error(&e, &out.render);
+            eprintln!("failed: {}", file.display());
+        }
+        failed.push(file.display().to_string());
+    }
+
+    let summary = format!("{} of {} files {verb}", files.len() - failed.len(), files.len());
+    if failed.is_empty() {
+        println!("{summary}");
+        Ok(())
+    } else {
+        Err(anyhow!("{summary}; failed: {}", failed.join(", ")))
+    }
+}
+
+/// 展开文件参数中的通配符（用于不展开通配符的shell，如Windows的cmd）
+/// 
+/// # 参数
+/// * `files` - 命令行给出的文件路径或通配符模式
+/// 
+/// # 返回值
+/// * `Result<Vec<PathBuf>>` - 展开后的文件路径（每个模式匹配到的文件按路径排序）；
+///   某个模式没有匹配任何文件时返回错误
+/// 
+/// # 规则
+/// - 不含 `*` 和 `?` 的参数（包括 `-`）按原样保留，不检查文件是否存在
+/// - `*` 匹配文件名中任意个字符，`?` 匹配一个字符，`**` 单独作为一段时匹配任意层目录（包括零层）
+/// - 通配符不匹配以 `.` 开头的文件和目录，除非该段模式本身以 `.` 开头
+/// - 只匹配文件；`**` 不进入符号链接指向的目录
+fn expand_globs(files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for file in files {
+        if !file.to_str().is_some_and(has_wildcard) {
+            expanded.push(file.clone());
+            continue;
+        }
+        let mut base = PathBuf::new();
+        let mut parts = Vec::new();
+        for component in file.components() {
+            let text = component.as_os_str().to_string_lossy();
+            if parts.is_empty() && !has_wildcard(&text) {
+                base.push(component);
+            } else {
+                parts.push(text.into_owned());
+            }
+        }
+        let mut matched = Vec::new();
+        glob_in(&base, &parts, &mut matched);
+        if matched.is_empty() {
+            return Err(anyhow!("no files match `{}`; pass --no-glob to use it as a literal path", file.display()));
+        }
+        matched.sort();
+        matched.dedup();
+        expanded.extend(matched);
+    }
+    Ok(expanded)
+}
+
+/// 是否含有通配符
+fn has_wildcard(text: &str) -> bool {
+    text.contains(['*', '?'])
+}
+
+/// 在目录下查找与剩余各段模式匹配的文件
+/// 
+/// # 参数
+/// * `dir` - 当前目录（空路径表示当前工作目录，匹配结果保持相对路径）
+/// * `parts` - 剩余的各段模式
+/// * `matched` - 收集匹配到的文件
+fn glob_in(dir: &Path, parts: &[String], matched: &mut Vec<PathBuf>) {
+    let Some((part, rest)) = parts.split_first() else {
+        if dir.is_file() {
+            matched.push(dir.to_path_buf());
+        }
+        return;
+    };
+    if !has_wildcard(part) {
+        glob_in(&dir.join(part), rest, matched);
+        return;
+    }
+    if part == "**" {
+        glob_in(dir, rest, matched);
+    }
+    let read_from = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = fs::read_dir(read_from) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.starts_with('.') && !part.starts_with('.') {
+            continue;
+        }
+        let path = dir.join(name);
+        if part == "**" {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                glob_in(&path, parts, matched);
+            }
+        } else if wildcard_match(part, name) {
+            glob_in(&path, rest, matched);
+        }
+    }
+}
+
+/// 判断文件名是否与一段通配符模式匹配（`*` 匹配任意个字符，`?` 匹配一个字符）
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 `*` 的位置，以及它开始匹配的文件名位置（匹配失败时让它多匹配一个字符）
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
     }
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 /// 运行.kr文件
 /// 
 /// # 参数
-/// * `file` - .kr源文件路径
+/// * `file` - .kr源文件路径（`-`表示从标准输入读取）
+/// * `interpret` - 是否用解释器直接执行
+/// * `args` - 传给编译后程序的命令行参数
+/// * `overrides` - 命令行参数中指定的设置（整数溢出的处理方式、解释器的执行上限）
+/// * `output` - 构建产物的输出位置选项
+/// * `semantic_options` - 语义检查选项
+/// * `reporter` - 语义警告的报告目标
 /// 
 /// # 返回值
 /// * `Result<()>` - 成功返回Ok(())，失败返回错误信息
 /// 
 /// # 功能
-/// 1. 验证文件扩展名
+/// 1. 读取源代码（验证文件扩展名）
 /// 2. 编译为可执行文件
-/// 3. 执行编译后的程序
-fn run_file(file: PathBuf) -> Result<()> {
-    ensure_kr_ext(&file)?;
+/// 3. 以`args`为命令行参数执行编译后的程序
+/// 
+/// 使用解释器时跳过第2步，直接在当前进程中执行抽象语法树；
+/// 解释器只支持报告整数溢出，命令行参数或编译指示指定了其他处理方式时返回错误
+fn run_file(
+    file: PathBuf,
+    interpret: bool,
+    args: &[String],
+    overrides: &CliOverrides,
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    reporter: &Reporter,
+) -> Result<()> {
+    let (source, name) = read_input(&file)?;
+
+    if interpret {
+        let overflow = Config::resolve(&source, overrides)?.overflow;
+        if overflow.value != Overflow::Check {
+            return Err(anyhow!(
+                "the interpreter always reports integer overflow; overflow={} ({}) needs a compiled run",
+                overflow.value,
+                overflow.source
+            ));
+        }
+        return compiler::interpret_source(&source, &name, overrides, semantic_options, reporter, io::stdout().lock())
+            .with_context(|| format!("failed to interpret {:?}", name));
+    }
 
-    // 编译为可执行文件（调试模式）
-    let exe_path = compiler::compile_file_to_exe(&file, /*release=*/ false)
-        .with_context(|| format!("failed to compile {:?}", file))?;
+    // 编译为可执行文件（默认调试模式，可由编译指示修改）
+    let exe_path = compiler::compile_source(
+        &source,
+        &name,
+        overrides,
+        &[EmitKind::Exe],
+        output,
+        semantic_options,
+        &CodegenOptions::default(),
+        reporter,
+    )
+    .with_context(|| format!("failed to compile {:?}", name))?
+    .remove(0);
 
     // 执行编译后的二进制文件
     let status = Command::new(&exe_path)
+        .args(args)
         .status()
         .with_context(|| format!("failed to run {:?}", exe_path))?;
 
@@ -80,29 +747,393 @@ fn run_file(file: PathBuf) -> Result<()> {
     Ok(())
 }
 
-/// 构建.kr文件为可执行文件
+/// 构建.kr文件为可执行文件或其他构建产物
 /// 
 /// # 参数
-/// * `file` - .kr源文件路径
-/// * `release` - 是否使用发布模式（优化）
+/// * `source` - 源代码字符串
+/// * `name` - 源文件路径或显示名称（见`read_input`）
+/// * `overrides` - 命令行参数中指定的设置（发布模式、整数溢出的处理方式）
+/// * `emit` - 要生成的构建产物列表
+/// * `output` - 构建产物的输出位置选项
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `reporter` - 语义警告的报告目标
 /// 
 /// # 返回值
-/// * `Result<PathBuf>` - 成功返回可执行文件路径，失败返回错误信息
+/// * `Result<Vec<PathBuf>>` - 成功返回各构建产物路径，失败返回错误信息
 /// 
 /// # 功能
-/// 1. 验证文件扩展名
-/// 2. 编译为可执行文件
-/// 3. 显示输出路径
-fn build_file(file: PathBuf, release: bool) -> Result<PathBuf> {
-    ensure_kr_ext(&file)?;
-
-    // 编译为可执行文件
-    let exe_path = compiler::compile_file_to_exe(&file, release)
-        .with_context(|| format!("failed to compile {:?}", file))?;
+/// 1. 编译并生成请求的构建产物
+/// 2. 显示输出路径
+#[allow(clippy::too_many_arguments)]
+fn build_file(
+    source: &str,
+    name: &Path,
+    overrides: &CliOverrides,
+    emit: &[EmitKind],
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<Vec<PathBuf>> {
+    // 编译并生成构建产物（未在命令行指定的设置由编译指示决定）
+    let outputs = compiler::compile_source(source, name, overrides, emit, output, semantic_options, codegen_options, reporter)
+        .with_context(|| format!("failed to compile {:?}", name))?;
 
     // 为用户方便显示输出路径
-    println!("Built: {}", exe_path.display());
-    Ok(exe_path)
+    for path in &outputs {
+        println!("Built: {}", path.display());
+    }
+    Ok(outputs)
+}
+
+/// 检查.kr文件（只执行解析和语义检查）
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径（`-`表示从标准输入读取）
+/// * `semantic_options` - 语义检查选项
+/// * `dump_lowered` - 检查通过后是否输出降级之后的抽象语法树（见`compiler::lower`）
+/// * `out` - 诊断信息的输出方式
+/// 
+/// # 返回值
+/// * `Result<()>` - 没有错误时报告警告（如果有）后返回Ok(())；有错误时输出全部诊断信息后返回错误
+///   （需要JSON时不输出，诊断信息作为`Diagnostics`错误返回，见`DiagnosticOutput::finish`）
+/// 
+/// # 功能
+/// 不生成代码、不调用rustc，因此没有安装Rust工具链时也可以使用。
+/// 与run和build一样，只有警告时检查通过；指定了--deny-warnings时警告已改为错误（见`check_semantics`）
+fn check_file(file: PathBuf, semantic_options: &SemanticOptions, dump_lowered: bool, out: &DiagnosticOutput) -> Result<()> {
+    let (source, name) = read_input(&file)?;
+    compiler::check_pragma(&source, &name)?;
+
+    let analysis = compiler::analyze(&source, &name, semantic_options);
+    if analysis.diagnostics.iter().all(|d| d.severity == Severity::Warning) {
+        for warning in &analysis.diagnostics {
+            out.reporter.report(warning);
+        }
+        if dump_lowered && let (Some(mut program), Some(mut semantic)) = (analysis.program, analysis.semantic) {
+            compiler::lower::lower(&mut program, &mut semantic);
+            println!("{program:#?}");
+        }
+        if !out.json {
+            println!("{}", diagnostics::render_success("No errors found", &out.render));
+        }
+        return Ok(());
+    }
+    if out.collecting() {
+        // 全部诊断信息由调用方统一以JSON输出
+        return Err(Diagnostics(analysis.diagnostics).into());
+    }
+
+    for diagnostic in &analysis.diagnostics {
+        eprintln!("{}", diagnostic.render(&out.render));
+    }
+    Err(anyhow!("check failed for {}", name.display()))
+}
+
+/// 格式化.kr文件
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径（`-`表示从标准输入读取）
+/// * `check` - 只检查是否已经格式化
+/// 
+/// # 返回值
+/// * `Result<()>` - 成功返回Ok(())；有语法错误，或指定了`check`而文件未格式化时返回错误信息
+/// 
+/// # 功能
+/// 只有内容发生变化时才改写文件
+fn fmt_file(file: PathBuf, check: bool) -> Result<()> {
+    let (source, name) = read_input(&file)?;
+    let formatted = compiler::formatter::format_source(&source, &name)?;
+
+    if check {
+        if formatted != source {
+            return Err(anyhow!("{} is not formatted; run `kairo fmt` to fix it", name.display()));
+        }
+        return Ok(());
+    }
+    if file == Path::new("-") {
+        print!("{formatted}");
+    } else if formatted != source {
+        fs::write(&name, formatted).with_context(|| format!("failed to write {}", name.display()))?;
+    }
+    Ok(())
+}
+
+/// 生成.kr文件对应的Rust代码并输出（不调用rustc）
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径（`-`表示从标准输入读取）
+/// * `output` - 输出文件路径，None表示输出到标准输出
+/// * `overrides` - 命令行参数中指定的设置（整数溢出的处理方式）
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `reporter` - 语义警告的报告目标
+/// 
+/// # 返回值
+/// * `Result<()>` - 成功返回Ok(())，失败返回错误信息
+/// 
+/// # 功能
+/// 语义检查失败时只输出错误信息，不会输出任何代码
+fn emit_rust_file(
+    file: PathBuf,
+    output: Option<PathBuf>,
+    overrides: &CliOverrides,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<()> {
+    let (source, name) = read_input(&file)?;
+
+    let rust_code = compiler::compile_source_to_rust(&source, &name, overrides, semantic_options, codegen_options, reporter)
+        .with_context(|| format!("failed to compile {:?}", name))?;
+
+    match output {
+        Some(path) => fs::write(&path, &rust_code).with_context(|| format!("write file: {}", path.display())),
+        None => {
+            print!("{rust_code}");
+            Ok(())
+        }
+    }
+}
+
+/// 监视模式下检查源文件是否修改的间隔
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 检测到修改后，等待源文件不再变化的时间（合并编辑器保存时的多次写入）
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// 监视源文件，每次修改后重新执行一轮操作
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径（不能是`-`）
+/// * `out` - 诊断信息的输出方式（JSON输出模式下每轮输出一个JSON数组）
+/// * `cycle` - 每轮执行的操作（如编译并运行）
+/// 
+/// # 返回值
+/// * `Result<()>` - 只有无法监视时才返回错误；每轮操作的错误只输出，不结束监视
+/// 
+/// # 功能
+/// 每轮开始前清空终端（输出不是终端时不清空），使屏幕上只有本轮的诊断信息。
+/// 通过定期比较文件的修改时间和大小检测修改，不依赖平台的文件系统通知
+fn watch(file: &Path, out: &DiagnosticOutput, mut cycle: impl FnMut() -> Result<()>) -> Result<()> {
+    if file == Path::new("-") {
+        return Err(anyhow!("--watch cannot be used when reading from stdin"));
+    }
+    ensure_kr_ext(file)?;
+
+    loop {
+        if io::stdout().is_terminal() {
+            print!("\x1b[2J\x1b[H");
+            io::stdout().flush().context("failed to flush stdout")?;
+        }
+        let result = cycle();
+        out.finish(&result)?;
+        if let Err(e) = &result
+            && out.human()
+        {
+            print_error(e, &out.render);
+        }
+        eprintln!("watching {} for changes (Ctrl-C to stop)", file.display());
+        wait_for_change(file);
+    }
+}
+
+/// 获取源文件的修改时间和大小（文件暂时不存在时为None）
+fn file_stamp(file: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// 阻塞直到源文件被修改，并且在`WATCH_DEBOUNCE`内不再变化
+/// 
+/// # 说明
+/// 编辑器保存时可能先删除再重新创建文件，文件暂时不存在时继续等待
+fn wait_for_change(file: &Path) {
+    let last = file_stamp(file);
+    loop {
+        thread::sleep(WATCH_POLL_INTERVAL);
+        let mut current = file_stamp(file);
+        if current.is_none() || current == last {
+            continue;
+        }
+        loop {
+            thread::sleep(WATCH_DEBOUNCE);
+            let settled = file_stamp(file);
+            if settled == current {
+                return;
+            }
+            current = settled;
+        }
+    }
+}
+
+/// 运行交互式解释器
+/// 
+/// # 参数
+/// * `semantic_options` - 语义检查选项
+/// * `limits` - 解释器的执行上限
+/// * `render` - 警告和错误的显示选项
+/// 
+/// # 返回值
+/// * `Result<()>` - 输入 `:quit` 或遇到输入结束（EOF）时返回Ok(())
+/// 
+/// # 功能
+/// 每行输入立即由解释器执行，不调用rustc；表达式和有返回值的函数调用会打印其值。
+/// 函数定义的函数体从下一行开始时，继续读取直到单独一行的 `}` 为止。
+/// 输入有错误时显示与编译时相同的错误信息，之前的变量和函数不受影响
+fn repl(semantic_options: SemanticOptions, limits: Limits, render: &RenderOptions) -> Result<()> {
+    let mut session = ReplSession::new(io::stdout(), semantic_options, limits);
+    let mut lines = io::stdin().lines();
+    let mut prompt = |text: &str| -> Result<Option<String>> {
+        print!("{text}");
+        io::stdout().flush().context("failed to flush stdout")?;
+        lines.next().transpose().context("failed to read input")
+    };
+
+    println!("Kairo REPL (type :quit to exit)");
+    while let Some(line) = prompt("> ")? {
+        let mut input = line.trim_end().to_string();
+        match input.trim() {
+            "" => continue,
+            ":quit" => break,
+            command if command.starts_with(':') => {
+                eprintln!("unknown command `{command}` (type :quit to exit)");
+                continue;
+            }
+            _ => {}
+        }
+
+        // 多行函数定义：读取到单独一行的右花括号为止
+        if session.needs_more(&input) {
+            while let Some(line) = prompt("... ")? {
+                input.push('\n');
+                input.push_str(line.trim_end());
+                if line.trim() == "}" {
+                    break;
+                }
+            }
+        }
+
+        match session.eval(&input) {
+            Ok(evaluation) => {
+                for warning in &evaluation.warnings {
+                    eprintln!("{}", warning.render(render));
+                }
+                if let Some(value) = evaluation.value {
+                    println!("{value}");
+                }
+            }
+            Err(e) => print_error(&e, render),
+        }
+    }
+    Ok(())
+}
+
+/// 打印合并后的全部生效设置
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径（None表示不读取编译指示）
+/// * `overrides` - 命令行参数中指定的设置
+/// 
+/// # 返回值
+/// * `Result<()>` - 成功返回Ok(())，读取源文件失败或编译指示无效时返回错误信息
+/// 
+/// # 说明
+/// 与编译时使用同一个`Config::resolve`合并设置，因此输出与实际编译时生效的设置一致
+fn dump_config(file: Option<PathBuf>, overrides: &CliOverrides) -> Result<()> {
+    let source = match &file {
+        Some(file) => read_input(file)?.0,
+        None => String::new(),
+    };
+    print!("{}", Config::resolve(&source, overrides)?);
+    Ok(())
+}
+
+/// 打印构建可执行文件时将执行的rustc命令（不执行rustc）
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `name` - 源文件路径或显示名称（见`read_input`）
+/// * `overrides` - 命令行参数中指定的设置（发布模式、整数溢出的处理方式）
+/// * `emit` - 要生成的构建产物列表（其中的可执行文件不会生成）
+/// * `output` - 构建产物的输出位置选项
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `reporter` - 语义警告的报告目标
+/// 
+/// # 返回值
+/// * `Result<()>` - 成功返回Ok(())，失败返回错误信息
+/// 
+/// # 功能
+/// 生成除可执行文件以外的构建产物（总是包括rustc的输入.rs文件），然后打印rustc命令
+#[allow(clippy::too_many_arguments)]
+fn print_rustc_invocation(
+    source: &str,
+    name: &Path,
+    overrides: &CliOverrides,
+    emit: &[EmitKind],
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<()> {
+    let mut emit: Vec<EmitKind> = emit.iter().copied().filter(|kind| *kind != EmitKind::Exe).collect();
+    if !emit.contains(&EmitKind::Rust) {
+        emit.push(EmitKind::Rust);
+    }
+    build_file(source, name, overrides, &emit, output, semantic_options, codegen_options, reporter)?;
+
+    let argv = compiler::rustc_invocation(source, name, overrides, output)?;
+    let quoted: Vec<String> = argv
+        .iter()
+        .map(|arg| {
+            if arg.contains(char::is_whitespace) || arg.contains('\'') {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    println!("{}", quoted.join(" "));
+    Ok(())
+}
+
+/// 校验入口函数名是否为合法的Rust标识符
+/// 
+/// # 参数
+/// * `s` - 命令行中给出的入口函数名
+/// 
+/// # 返回值
+/// * `Result<String, String>` - 合法时返回函数名，否则返回错误描述
+fn parse_entry_name(s: &str) -> Result<String, String> {
+    let mut chars = s.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "_";
+    if valid {
+        Ok(s.to_string())
+    } else {
+        Err(format!("`{s}` is not a valid function name"))
+    }
+}
+
+/// 读取命令行指定的源代码
+/// 
+/// # 参数
+/// * `file` - .kr源文件路径；为`-`时从标准输入读取
+/// 
+/// # 返回值
+/// * `Result<(String, PathBuf)>` - 源代码，以及用于错误信息和构建产物文件名的名称
+///   （从标准输入读取时为`<stdin>`，此时不检查扩展名）
+fn read_input(file: &Path) -> Result<(String, PathBuf)> {
+    if file == Path::new("-") {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source).context("failed to read source from stdin")?;
+        return Ok((source, PathBuf::from("<stdin>")));
+    }
+    ensure_kr_ext(file)?;
+    Ok((compiler::read_source(file)?, file.to_path_buf()))
 }
 
 /// 确保文件具有.kr扩展名
@@ -116,7 +1147,7 @@ fn build_file(file: PathBuf, release: bool) -> Result<PathBuf> {
 /// # 检查项目
 /// 1. 文件是否存在
 /// 2. 文件扩展名是否为.kr
-fn ensure_kr_ext(path: &PathBuf) -> Result<()> {
+fn ensure_kr_ext(path: &Path) -> Result<()> {
     if !path.exists() {
         return Err(anyhow!("source file not found: {}", path.display()));
     }
@@ -125,3 +1156,36 @@ fn ensure_kr_ext(path: &PathBuf) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_matches_file_names() {
+        assert!(wildcard_match("*.kr", "main.kr"));
+        assert!(wildcard_match("*.kr", ".kr"));
+        assert!(!wildcard_match("*.kr", "main.rs"));
+        assert!(wildcard_match("t?st_*.kr", "test_loop.kr"));
+        assert!(wildcard_match("a*b*c", "aXbYbc"));
+        assert!(!wildcard_match("a*b*c", "aXbY"));
+    }
+
+    #[test]
+    fn literal_paths_are_kept_unchanged() {
+        let files = [PathBuf::from("-"), PathBuf::from("missing/main.kr")];
+        assert_eq!(expand_globs(&files).unwrap(), files);
+    }
+
+    #[test]
+    fn run_collects_arguments_after_double_dash() {
+        let cli = Cli::try_parse_from(["kairo", "run", "main.kr", "--", "foo", "--lenient", "bar"]).unwrap();
+        let Commands::Run { files, args, semantic, .. } = cli.command else {
+            panic!("expected the run command");
+        };
+        assert_eq!(files, [PathBuf::from("main.kr")]);
+        // `--` 之后的参数原样传给程序，不当作kairo的选项
+        assert_eq!(args, ["foo", "--lenient", "bar"]);
+        assert!(!semantic.lenient);
+    }
+}
@@ -0,0 +1,190 @@
+use super::node::{Expr, Program, Stmt};
+
+/// 将程序的抽象语法树转换为GraphViz DOT格式
+/// 
+/// # 参数
+/// * `program` - Kairo程序的抽象语法树
+/// 
+/// # 返回值
+/// * `String` - DOT格式的有向图，可用 `dot -Tpng` 渲染为图片
+/// 
+/// # 输出格式
+/// 每个AST节点对应一个 `nN` 节点，标签为节点类型及其关键字段；
+/// 父节点到子节点有一条边，二元运算的左右操作数按顺序排列
+pub fn to_dot(program: &Program) -> String {
+    let mut out = DotWriter { out: String::new(), next_id: 0 };
+    out.out.push_str("digraph ast {\n");
+    out.out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+    let root = out.node("Program");
+    for stmt in &program.statements {
+        let id = out.stmt(stmt);
+        out.edge(root, id);
+    }
+
+    out.out.push_str("}\n");
+    out.out
+}
+
+/// DOT输出缓冲区
+/// 
+/// # 字段
+/// * `out` - 已生成的DOT文本
+/// * `next_id` - 下一个节点的编号
+struct DotWriter {
+    out: String,
+    next_id: usize,
+}
+
+impl DotWriter {
+    /// 输出一个节点并返回其编号
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.out.push_str(&format!("    n{} [label=\"{}\"];\n", id, escape_label(label)));
+        id
+    }
+
+    /// 输出一条从`from`指向`to`的边
+    fn edge(&mut self, from: usize, to: usize) {
+        self.out.push_str(&format!("    n{} -> n{};\n", from, to));
+    }
+
+    /// 输出语句节点及其子节点
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Print { args, .. } => {
+                let id = self.node("Print");
+                for arg in args {
+                    let child = self.expr(arg);
+                    self.edge(id, child);
+                }
+                id
+            }
+            Stmt::Assign { name, decl_mut, expr, .. } => {
+                let prefix = if *decl_mut { "$" } else { "" };
+                let id = self.node(&format!("Assign\n{}{}", prefix, name));
+                let child = self.expr(expr);
+                self.edge(id, child);
+                id
+            }
+            Stmt::Const { name, expr, .. } => {
+                let id = self.node(&format!("Const\n{}", name));
+                let child = self.expr(expr);
+                self.edge(id, child);
+                id
+            }
+            Stmt::FnDef { name, params, ret, body, .. } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|p| match &p.ty {
+                        Some(ty) => format!("{}: {}", p.name, ty),
+                        None => p.name.clone(),
+                    })
+                    .collect();
+                let ret = ret.as_ref().map_or(String::new(), |ty| format!(" -> {}", ty));
+                let id = self.node(&format!("FnDef\n{}({}){}", name, params.join(", "), ret));
+                for stmt in body {
+                    let child = self.stmt(stmt);
+                    self.edge(id, child);
+                }
+                id
+            }
+            Stmt::Return { expr, .. } => {
+                let id = self.node("Return");
+                if let Some(expr) = expr {
+                    let child = self.expr(expr);
+                    self.edge(id, child);
+                }
+                id
+            }
+            Stmt::Expr { expr, .. } => {
+                let id = self.node("ExprStmt");
+                let child = self.expr(expr);
+                self.edge(id, child);
+                id
+            }
+            Stmt::Invalid { .. } => self.node("Invalid"),
+        }
+    }
+
+    /// 输出表达式节点及其子节点
+    fn expr(&mut self, expr: &Expr) -> usize {
+        let label = match expr {
+            Expr::StringLit(s, _) => format!("StringLit\n{:?}", s),
+            Expr::IntLit(v, _) => format!("IntLit\n{}", v),
+            Expr::FloatLit(v, _) => format!("FloatLit\n{:?}", v),
+            Expr::BoolLit(v, _) => format!("BoolLit\n{}", v),
+            Expr::Ident(name, _) => format!("Ident\n{}", name),
+            Expr::BinaryAdd(..) => "BinaryAdd\n+".to_string(),
+            Expr::BinarySub(..) => "BinarySub\n-".to_string(),
+            Expr::BinaryMul(..) => "BinaryMul\n*".to_string(),
+            Expr::BinaryDiv(..) => "BinaryDiv\n/".to_string(),
+            Expr::BinaryMod(..) => "BinaryMod\n%".to_string(),
+            Expr::Compare(op, ..) => format!("Compare\n{}", op.symbol()),
+            Expr::Neg(..) => "Neg\n-".to_string(),
+            Expr::Group(..) => "Group\n( )".to_string(),
+            Expr::TypeOf(..) => "TypeOf".to_string(),
+            Expr::Call(name, ..) => format!("Call\n{}", name),
+            Expr::Read(kind, _) => format!("Read\n{}", kind.name()),
+        };
+
+        let id = self.node(&label);
+        for child in expr.children() {
+            let child_id = self.expr(child);
+            self.edge(id, child_id);
+        }
+        id
+    }
+}
+
+/// 转义DOT标签中的特殊字符
+/// 
+/// # 转义规则
+/// * `\` -> `\\`
+/// * `"` -> `\"`
+/// * 换行 -> `\n`（DOT中的居中换行）
+fn escape_label(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser;
+    use std::collections::HashSet;
+    use std::path::Path;
+
+    #[test]
+    fn emits_well_formed_dot() {
+        let source = "x = 1 + 2\nfun f(s: string) -> string {\n    return s\n}\nprint(\"say \\\"hi\\\"\", f(\"a\"), x)\n";
+        let program = parser::parse(source, Path::new("test.kr")).unwrap();
+        let dot = to_dot(&program);
+
+        let lines: Vec<&str> = dot.lines().collect();
+        assert_eq!(lines[0], "digraph ast {");
+        assert_eq!(lines[1], "    node [shape=box, fontname=\"monospace\"];");
+        assert_eq!(*lines.last().unwrap(), "}");
+
+        // 每行是一个节点或一条边，边的两端都是已声明的节点，除根节点外每个节点恰好有一个父节点
+        let mut nodes = HashSet::new();
+        let mut children = Vec::new();
+        for line in &lines[2..lines.len() - 1] {
+            let line = line.strip_prefix("    ").and_then(|l| l.strip_suffix(';')).expect(line);
+            if let Some((id, label)) = line.split_once(" [label=\"") {
+                let label = label.strip_suffix("\"]").expect(line);
+                // 标签中的引号都已转义
+                assert!(!label.replace("\\\"", "").contains('"'), "unescaped quote in `{line}`");
+                assert!(nodes.insert(id), "duplicate node {id}");
+            } else {
+                let (from, to) = line.split_once(" -> ").expect(line);
+                assert!(nodes.contains(from) && nodes.contains(to), "undeclared node in `{line}`");
+                children.push(to);
+            }
+        }
+        assert_eq!(children.len(), nodes.len() - 1);
+        assert_eq!(children.iter().collect::<HashSet<_>>().len(), children.len());
+        assert!(dot.contains("[label=\"Assign\\nx\"]"), "{dot}");
+        assert!(dot.contains("[label=\"StringLit\\n\\\"say \\\\\\\"hi\\\\\\\"\\\"\"]"), "{dot}");
+    }
+}
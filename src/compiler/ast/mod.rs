@@ -6,9 +6,20 @@ pub mod node;
 /// 提供源码位置和范围的定义
 pub mod span;
 
+/// GraphViz DOT输出模块
+/// 将抽象语法树转换为可视化的有向图
+pub mod dot;
+
+/// 源码位置查询模块
+/// 根据行列号查找覆盖该位置的AST节点（供编辑器悬停提示等功能使用）
+pub mod query;
+
 /// 导出AST节点类型
 /// 方便其他模块使用
-pub use node::{Program, Stmt, Expr};
+pub use node::{Program, Stmt, Expr, CompareOp, Param, ReadKind};
+
+/// 导出源码位置查询函数
+pub use query::{stmt_at, expr_at};
 
 /// 导出源码位置类型
 pub use span::{SourceSpan};
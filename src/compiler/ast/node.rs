@@ -6,18 +6,30 @@ use super::span::SourceSpan;
 pub struct Program {
     /// 程序中的语句列表
     pub statements: Vec<Stmt>,
+    /// 需要保留的空行的行号（按升序排列，包括函数体中和注释之间的空行）
+    /// 
+    /// 供格式化工具保留用户用空行划分的语句分组：
+    /// 连续多个空行视为一个，只记录其中的第一行；文件开头和结尾的空行、块注释内部的空行不记录
+    pub blank_lines: Vec<usize>,
 }
 
 /// 语句类型
 /// 表示Kairo语言中的各种语句
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    /// 打印语句：print("内容")
+    /// 打印语句：print("内容") 或 print(a, b, ...)
     /// 
     /// # 字段
-    /// * `content` - 要打印的字符串内容
-    /// * `_span` - 源码位置信息（用于错误报告）
-    Print { content: String, _span: SourceSpan },
+    /// * `args` - 要打印的参数（可以为空，此时输出空行）
+    /// * `span` - 源码位置信息（用于错误报告）
+    /// 
+    /// # 输出规则
+    /// * 第一个参数是含 `{}` 的字符串字面量时，作为格式字符串，其余参数依次填入 `{}`
+    /// * 否则依次输出所有参数，以空格分隔
+    /// 
+    /// # 格式占位符
+    /// 字符串字面量参数中可以用 `{变量名}` 输出变量的值，`{{` 和 `}}` 输出花括号本身
+    Print { args: Vec<Expr>, span: SourceSpan },
     
     /// 赋值语句：变量名 = 表达式 或 $变量名 = 表达式
     /// 
@@ -28,6 +40,59 @@ pub enum Stmt {
     /// * `span` - 整个语句的源码位置
     /// * `name_span` - 变量名的源码位置
     Assign { name: String, decl_mut: bool, expr: Expr, span: SourceSpan, name_span: SourceSpan },
+
+    /// 常量声明语句：const 变量名 = 常量表达式
+    /// 
+    /// # 字段
+    /// * `name` - 常量名
+    /// * `expr` - 常量的初始值（只能由字面量和其他常量组成）
+    /// * `span` - 整个语句的源码位置
+    /// * `name_span` - 常量名的源码位置
+    Const { name: String, expr: Expr, span: SourceSpan, name_span: SourceSpan },
+
+    /// 函数定义语句：fun 函数名(参数, ...) -> 返回值类型 { 函数体 }（也可以用 `fn` 关键字）
+    /// 
+    /// # 字段
+    /// * `name` - 函数名
+    /// * `params` - 参数列表
+    /// * `ret` - 返回值类型注解（省略时由return语句推断）
+    /// * `body` - 函数体中的语句
+    /// * `span` - 函数头（`fun` 所在的行）的源码位置
+    /// * `name_span` - 函数名的源码位置
+    FnDef { name: String, params: Vec<Param>, ret: Option<String>, body: Vec<Stmt>, span: SourceSpan, name_span: SourceSpan },
+
+    /// 返回语句：return 表达式 或 return（只能出现在函数体中）
+    /// 
+    /// # 字段
+    /// * `expr` - 返回值（没有返回值时为None）
+    /// * `span` - 整个语句的源码位置
+    Return { expr: Option<Expr>, span: SourceSpan },
+
+    /// 表达式语句：单独一行的函数调用，如 greet("Alice")
+    /// 
+    /// # 字段
+    /// * `expr` - 函数调用表达式（返回值被丢弃）
+    /// * `span` - 整个语句的源码位置
+    Expr { expr: Expr, span: SourceSpan },
+
+    /// 无法解析的语句（只出现在收集全部语法错误时，见`parser::parse_recovering`）
+    /// 
+    /// # 字段
+    /// * `name` - 语句看起来是赋值或常量声明时的变量名（如 `x = 1 +` 中的 `x`），
+    ///   语义分析把它当作类型未知的变量，之后的使用不会报告为未定义
+    /// * `span` - 整个语句的源码位置
+    Invalid { name: Option<String>, span: SourceSpan },
+}
+
+/// 函数参数
+#[derive(Debug, Clone)]
+pub struct Param {
+    /// 参数名
+    pub name: String,
+    /// 类型注解（省略时为int）
+    pub ty: Option<String>,
+    /// 参数（包括类型注解）的源码位置
+    pub span: SourceSpan,
 }
 
 /// 表达式类型
@@ -47,6 +112,20 @@ pub enum Expr {
     /// * `i64` - 整数值
     /// * `SourceSpan` - 源码位置信息
     IntLit(i64, SourceSpan),
+
+    /// 浮点数字面量：3.14, -0.5
+    /// 
+    /// # 字段
+    /// * `f64` - 浮点数值
+    /// * `SourceSpan` - 源码位置信息
+    FloatLit(f64, SourceSpan),
+
+    /// 布尔字面量：true, false
+    /// 
+    /// # 字段
+    /// * `bool` - 布尔值
+    /// * `SourceSpan` - 源码位置信息
+    BoolLit(bool, SourceSpan),
     
     /// 标识符：变量名
     /// 
@@ -62,4 +141,199 @@ pub enum Expr {
     /// * `Box<Expr>` - 右操作数
     /// * `SourceSpan` - 源码位置信息
     BinaryAdd(Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 二元减法表达式：a - b
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 左操作数
+    /// * `Box<Expr>` - 右操作数
+    /// * `SourceSpan` - 源码位置信息
+    BinarySub(Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 二元乘法表达式：a * b
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 左操作数
+    /// * `Box<Expr>` - 右操作数
+    /// * `SourceSpan` - 源码位置信息
+    BinaryMul(Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 二元除法表达式：a / b
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 左操作数（被除数）
+    /// * `Box<Expr>` - 右操作数（除数）
+    /// * `SourceSpan` - 源码位置信息
+    BinaryDiv(Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 二元取余表达式：a % b
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 左操作数（被除数）
+    /// * `Box<Expr>` - 右操作数（除数）
+    /// * `SourceSpan` - 源码位置信息
+    BinaryMod(Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 括号分组表达式：(a + b)
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 括号内的表达式
+    /// * `SourceSpan` - 源码位置信息（从左括号到右括号）
+    Group(Box<Expr>, SourceSpan),
+
+    /// 编译期类型查询：typeof(x)
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 要查询类型的表达式（不会被求值）
+    /// * `SourceSpan` - 源码位置信息
+    TypeOf(Box<Expr>, SourceSpan),
+
+    /// 比较表达式：a < b, a == b 等，结果为布尔值
+    /// 
+    /// # 字段
+    /// * `CompareOp` - 比较运算符
+    /// * `Box<Expr>` - 左操作数
+    /// * `Box<Expr>` - 右操作数
+    /// * `SourceSpan` - 源码位置信息
+    Compare(CompareOp, Box<Expr>, Box<Expr>, SourceSpan),
+
+    /// 取负表达式：-x, -(a + b)
+    /// 
+    /// # 字段
+    /// * `Box<Expr>` - 被取负的表达式
+    /// * `SourceSpan` - 源码位置信息（包含负号）
+    Neg(Box<Expr>, SourceSpan),
+
+    /// 函数调用表达式：add(1, 2)
+    /// 
+    /// # 字段
+    /// * `String` - 函数名
+    /// * `Vec<Expr>` - 实参列表
+    /// * `SourceSpan` - 源码位置信息（从函数名到右括号）
+    Call(String, Vec<Expr>, SourceSpan),
+
+    /// 读取输入：read()、read_int()
+    /// 
+    /// # 字段
+    /// * `ReadKind` - 读取的值的类型
+    /// * `SourceSpan` - 源码位置信息（从函数名到右括号）
+    Read(ReadKind, SourceSpan),
+}
+
+/// 读取输入的内置函数（从标准输入读取一行，不包含行尾的换行符）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadKind {
+    /// `read()`：读取一行字符串
+    Line,
+    /// `read_int()`：读取一行并解析为整数（不是整数时报告运行时错误）
+    Int,
+}
+
+impl ReadKind {
+    /// 根据函数名查找对应的内置函数
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "read" => Some(ReadKind::Line),
+            "read_int" => Some(ReadKind::Int),
+            _ => None,
+        }
+    }
+
+    /// 获取内置函数的函数名
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReadKind::Line => "read",
+            ReadKind::Int => "read_int",
+        }
+    }
+}
+
+/// 比较运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// 小于 `<`
+    Lt,
+    /// 小于等于 `<=`
+    Le,
+    /// 大于 `>`
+    Gt,
+    /// 大于等于 `>=`
+    Ge,
+    /// 等于 `==`
+    Eq,
+    /// 不等于 `!=`
+    Ne,
+}
+
+impl CompareOp {
+    /// 获取运算符的源码写法（与Rust的写法相同）
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+            CompareOp::Eq => "==",
+            CompareOp::Ne => "!=",
+        }
+    }
+}
+
+impl Expr {
+    /// 获取表达式的源码位置信息
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            Expr::StringLit(_, span)
+            | Expr::IntLit(_, span)
+            | Expr::FloatLit(_, span)
+            | Expr::BoolLit(_, span)
+            | Expr::Ident(_, span)
+            | Expr::BinaryAdd(_, _, span)
+            | Expr::BinarySub(_, _, span)
+            | Expr::BinaryMul(_, _, span)
+            | Expr::BinaryDiv(_, _, span)
+            | Expr::BinaryMod(_, _, span)
+            | Expr::Group(_, span)
+            | Expr::TypeOf(_, span)
+            | Expr::Compare(_, _, _, span)
+            | Expr::Neg(_, span)
+            | Expr::Call(_, _, span)
+            | Expr::Read(_, span) => *span,
+        }
+    }
+
+    /// 获取表达式的直接子表达式（按源码顺序）
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::StringLit(..)
+            | Expr::IntLit(..)
+            | Expr::FloatLit(..)
+            | Expr::BoolLit(..)
+            | Expr::Ident(..)
+            | Expr::Read(..) => vec![],
+            Expr::BinaryAdd(a, b, _)
+            | Expr::BinarySub(a, b, _)
+            | Expr::BinaryMul(a, b, _)
+            | Expr::BinaryDiv(a, b, _)
+            | Expr::BinaryMod(a, b, _)
+            | Expr::Compare(_, a, b, _) => vec![a, b],
+            Expr::Group(inner, _) | Expr::TypeOf(inner, _) | Expr::Neg(inner, _) => vec![inner],
+            Expr::Call(_, args, _) => args.iter().collect(),
+        }
+    }
+}
+
+impl Stmt {
+    /// 获取语句的源码位置信息
+    pub fn span(&self) -> SourceSpan {
+        match self {
+            Stmt::Print { span, .. }
+            | Stmt::Assign { span, .. }
+            | Stmt::Const { span, .. }
+            | Stmt::FnDef { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Expr { span, .. }
+            | Stmt::Invalid { span, .. } => *span,
+        }
+    }
 }
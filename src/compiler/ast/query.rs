@@ -0,0 +1,106 @@
+use super::node::{Expr, Program, Stmt};
+
+/// 查找覆盖指定源码位置的语句
+/// 
+/// # 参数
+/// * `program` - Kairo程序的抽象语法树
+/// * `line` - 行号（从1开始）
+/// * `col` - 列号（从1开始，按字符计算）
+/// 
+/// # 返回值
+/// * `Option<&Stmt>` - 覆盖该位置的语句；位置落在空行、注释或行首缩进上时返回None
+/// 
+/// # 说明
+/// 位置在函数体内时返回函数体中的语句，而不是整个函数定义
+pub fn stmt_at(program: &Program, line: usize, col: usize) -> Option<&Stmt> {
+    find_stmt(&program.statements, line, col)
+}
+
+/// 在语句列表中查找覆盖指定位置的最内层语句
+fn find_stmt(stmts: &[Stmt], line: usize, col: usize) -> Option<&Stmt> {
+    for stmt in stmts {
+        if let Stmt::FnDef { body, .. } = stmt
+            && let Some(inner) = find_stmt(body, line, col)
+        {
+            return Some(inner);
+        }
+        if stmt.span().contains(line, col) {
+            return Some(stmt);
+        }
+    }
+    None
+}
+
+/// 查找覆盖指定源码位置的最内层表达式
+/// 
+/// # 参数
+/// * `program` - Kairo程序的抽象语法树
+/// * `line` - 行号（从1开始）
+/// * `col` - 列号（从1开始，按字符计算）
+/// 
+/// # 返回值
+/// * `Option<&Expr>` - 覆盖该位置的最小表达式节点；位置不在任何表达式上时返回None
+/// 
+/// # 示例
+/// 对于 `z = a + (b * c)`，`b` 所在的位置返回 `Ident(b)`，
+/// `*` 所在的位置返回 `BinaryMul`，`(` 所在的位置返回 `Group`
+pub fn expr_at(program: &Program, line: usize, col: usize) -> Option<&Expr> {
+    let root = match stmt_at(program, line, col)? {
+        Stmt::Assign { expr, .. } | Stmt::Const { expr, .. } | Stmt::Expr { expr, .. } => expr,
+        Stmt::Return { expr, .. } => expr.as_ref()?,
+        Stmt::Print { args, .. } => args.iter().find(|arg| arg.span().contains(line, col))?,
+        Stmt::FnDef { .. } | Stmt::Invalid { .. } => return None,
+    };
+    if !root.span().contains(line, col) {
+        return None;
+    }
+
+    // 从根节点开始，逐层进入覆盖该位置的子表达式
+    let mut current = root;
+    while let Some(child) = current.children().into_iter().find(|e| e.span().contains(line, col)) {
+        current = child;
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser;
+    use std::path::Path;
+
+    /// 解析源代码（源代码必须没有语法错误）
+    fn parse(source: &str) -> Program {
+        parser::parse(source, Path::new("test.kr")).expect("source should parse")
+    }
+
+    #[test]
+    fn finds_innermost_expression() {
+        let program = parse("a = 1\nb = 2\nc = 3\nz = a + (b * -c)\nprint(z)\n");
+        // z = a + (b * -c)
+        // 1234567890123456
+        assert!(matches!(expr_at(&program, 4, 5), Some(Expr::Ident(name, _)) if name == "a"));
+        assert!(matches!(expr_at(&program, 4, 7), Some(Expr::BinaryAdd(..))));
+        assert!(matches!(expr_at(&program, 4, 9), Some(Expr::Group(..))));
+        assert!(matches!(expr_at(&program, 4, 10), Some(Expr::Ident(name, _)) if name == "b"));
+        assert!(matches!(expr_at(&program, 4, 12), Some(Expr::BinaryMul(..))));
+        assert!(matches!(expr_at(&program, 4, 14), Some(Expr::Neg(..))));
+        assert!(matches!(expr_at(&program, 4, 15), Some(Expr::Ident(name, _)) if name == "c"));
+        assert!(expr_at(&program, 4, 1).is_none());
+        assert!(matches!(stmt_at(&program, 4, 1), Some(Stmt::Assign { name, .. }) if name == "z"));
+    }
+
+    #[test]
+    fn finds_statements_and_arguments_inside_functions() {
+        let program = parse("fun f(x) {\n    return g(x + 1, 2)\n}\nfun g(a, b) {\n    return a * b\n}\nprint(f(1))\n");
+        assert!(matches!(stmt_at(&program, 2, 5), Some(Stmt::Return { .. })));
+        //     return g(x + 1, 2)
+        // 12345678901234567890123
+        assert!(matches!(expr_at(&program, 2, 12), Some(Expr::Call(name, ..)) if name == "g"));
+        assert!(matches!(expr_at(&program, 2, 16), Some(Expr::BinaryAdd(..))));
+        assert!(matches!(expr_at(&program, 2, 18), Some(Expr::IntLit(1, _))));
+        assert!(matches!(expr_at(&program, 2, 21), Some(Expr::IntLit(2, _))));
+        assert!(matches!(expr_at(&program, 7, 9), Some(Expr::IntLit(1, _))));
+        assert!(stmt_at(&program, 2, 1).is_none());
+    }
+}
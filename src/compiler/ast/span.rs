@@ -35,4 +35,18 @@ impl SourceSpan {
             end: SourcePos { line, col: end_col },
         }
     }
+
+    /// 判断源码位置是否在范围内
+    /// 
+    /// # 参数
+    /// * `line` - 行号（从1开始）
+    /// * `col` - 列号（从1开始）
+    /// 
+    /// # 返回值
+    /// 位置在范围内返回true（起始位置包含在内，结束位置不包含）
+    pub fn contains(&self, line: usize, col: usize) -> bool {
+        let after_start = (line, col) >= (self.start.line, self.start.col);
+        let before_end = (line, col) < (self.end.line, self.end.col);
+        after_start && before_end
+    }
 }
@@ -1,57 +1,245 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 
 use anyhow::Result;
 
-use crate::compiler::ast::{Expr, Program, Stmt};
-use crate::compiler::semantics::{Mutability, SemanticInfo};
+use crate::compiler::ast::{Expr, Program, ReadKind, SourceSpan, Stmt};
+use crate::compiler::semantics::format::{is_format_print, parse_placeholders, Placeholder};
+use crate::compiler::semantics::{Mutability, SemanticInfo, Type};
+
+/// Rust代码生成选项
+#[derive(Debug, Clone, Default)]
+pub struct CodegenOptions {
+    /// 是否通过生成的`kairo_print`函数输出，而不是直接调用`println!`
+    /// 
+    /// 嵌入或测试生成的代码时，只需替换这一个函数即可重定向全部输出
+    pub print_helper: bool,
+    /// 入口函数名（None表示`main`）
+    /// 
+    /// 不是`main`时生成 `pub fn 名称()`，便于宿主Rust代码嵌入后调用
+    pub entry: Option<String>,
+    /// 是否在写入前用rustfmt格式化生成的代码
+    /// 
+    /// 系统中没有rustfmt或格式化失败时只输出警告，保留未格式化的代码
+    pub rustfmt: bool,
+    /// 是否在每条生成的语句之前输出注释，说明它为何这样转换（便于学习编译器的工作方式）
+    pub explain: bool,
+    /// 整数加、减、乘和取负溢出时的处理方式
+    /// 
+    /// 生成的代码显式调用对应的i64方法，因此结果与rustc的构建模式（是否开启溢出检查）无关。
+    /// 通过`compile_source`等函数编译时由合并后的设置（`Config::overflow`）决定，这里的值不使用
+    pub overflow: Overflow,
+}
+
+/// 整数运算溢出时的处理方式
+/// 
+/// # 变体
+/// * `Check` - 报告运行时错误（指出溢出的运算及其位置）并退出，与解释器的行为一致（默认）
+/// * `Wrap` - 按二进制补码回绕，如 `9223372036854775807 + 1` 的结果为 `-9223372036854775808`
+/// * `Saturate` - 结果限制在i64的范围内，如 `9223372036854775807 + 1` 的结果为 `9223372036854775807`
+/// 
+/// 整数除法和取余不受影响：除以0和 `i64最小值 / -1` 在任何构建模式下都会使程序出错退出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// 报告运行时错误
+    #[default]
+    Check,
+    /// 回绕
+    Wrap,
+    /// 饱和
+    Saturate,
+}
+
+impl Overflow {
+    /// 对应的i64方法名前缀（如 `checked` 对应 `i64::checked_add`）
+    fn method_prefix(self) -> &'static str {
+        match self {
+            Overflow::Check => "checked",
+            Overflow::Wrap => "wrapping",
+            Overflow::Saturate => "saturating",
+        }
+    }
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Overflow::Check => write!(f, "check"),
+            Overflow::Wrap => write!(f, "wrap"),
+            Overflow::Saturate => write!(f, "saturate"),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "check" => Ok(Overflow::Check),
+            "wrap" => Ok(Overflow::Wrap),
+            "saturate" => Ok(Overflow::Saturate),
+            _ => Err(format!("unknown overflow mode `{s}` (expected: wrap, check, saturate)")),
+        }
+    }
+}
 
 /// 将Kairo程序转换为Rust代码
 /// 
 /// # 参数
 /// * `program` - Kairo程序的抽象语法树
 /// * `semantic` - 语义分析信息（包含变量可变性信息）
+/// * `options` - 代码生成选项
 /// 
 /// # 返回值
 /// * `Result<String>` - 生成的Rust源代码字符串
 /// 
 /// # 转换规则
 /// 1. 不可变变量：直接转换为Rust的let绑定
-/// 2. 可变变量：使用Rc<RefCell<T>>实现可变性
-/// 3. 打印语句：转换为println!宏调用
-/// 4. 表达式：递归转换各种表达式类型
-pub fn generate_rust(program: &Program, semantic: &SemanticInfo) -> Result<String> {
+/// 2. 可变变量：转换为 `let mut` 绑定（之后没有重新赋值时为普通的let绑定），直接赋值修改
+///    （宽松模式下重新赋值的不可变变量生成新的let绑定遮蔽原变量）
+/// 3. 常量：不生成绑定，在每个使用处内联其初始值
+/// 4. 打印语句：转换为println!宏调用（或启用`print_helper`时转换为kairo_print调用）
+/// 5. 表达式：递归转换各种表达式类型，只由字面量和常量组成的运算在编译期折叠为字面量；
+///    整数的加、减、乘和取负按`options.overflow`转换为对应的i64方法调用
+/// 6. 函数定义：在入口函数之后生成同名的Rust函数，参数和返回值带有类型
+/// 7. 读取输入：在程序末尾生成从标准输入读取一行的辅助函数（只在用到时生成）
+pub fn generate_rust(program: &Program, semantic: &SemanticInfo, options: &CodegenOptions) -> Result<String> {
     let mut out = String::new();
-    
-    // 检查是否需要可变性支持，如果需要则导入相关模块
-    let needs_rc = semantic.vars.values().any(|m| matches!(m, Mutability::Mutable));
-    if needs_rc {
-        out.push_str("use std::rc::Rc;\n");
-        out.push_str("use std::cell::RefCell;\n\n");
+
+    match options.entry.as_deref() {
+        None | Some("main") => out.push_str("fn main() {\n"),
+        Some(entry) => out.push_str(&format!("pub fn {}() {{\n", entry)),
+    }
+
+    gen_block(&program.statements, semantic, options, &mut out);
+
+    out.push_str("}\n");
+
+    // 在入口函数之后输出用户定义的函数
+    for stmt in &program.statements {
+        if let Stmt::FnDef { name, body, .. } = stmt
+            && let (Some(sig), Some(local)) = (semantic.functions.get(name), semantic.bodies.get(name))
+        {
+            let params: Vec<String> = sig
+                .params
+                .iter()
+                .map(|(param, ty)| format!("{}: {}", param, rust_param_type(*ty)))
+                .collect();
+            let ret = sig.ret.map_or(String::new(), |ty| format!(" -> {}", rust_return_type(ty)));
+            out.push('\n');
+            if options.explain {
+                let ret_reason = match sig.ret {
+                    Some(ty) => format!("返回值类型由return语句推断为 {ty}"),
+                    None => "没有带值的return语句，因此没有返回值".to_string(),
+                };
+                out.push_str(&format!(
+                    "// 第 {} 行：函数 → 参数类型来自类型注解（省略时为int），{}\n",
+                    stmt.span().start.line,
+                    ret_reason
+                ));
+            }
+            out.push_str(&format!("fn {}({}){} {{\n", name, params.join(", "), ret));
+            gen_block(body, local, options, &mut out);
+            out.push_str("}\n");
+        }
+    }
+
+    // 输出打印辅助函数
+    if options.print_helper {
+        out.push_str("\n/// Kairo程序的全部输出都经过此函数，替换它即可重定向输出\n");
+        out.push_str("fn kairo_print(args: std::fmt::Arguments) {\n");
+        out.push_str("    println!(\"{args}\");\n");
+        out.push_str("}\n");
+    }
+
+    // 输出溢出检查的辅助函数（只在生成的代码调用了它时输出）
+    if out.contains("kairo_overflow(") {
+        out.push_str("\n/// 整数运算溢出时输出运行时错误并退出（错误信息与解释器一致）\n");
+        out.push_str("fn kairo_overflow(what: &str, line: u32, col: u32) -> ! {\n");
+        out.push_str("    eprintln!(\"运行时错误：{what}（第 {line} 行，第 {col} 列）\");\n");
+        out.push_str("    std::process::exit(101);\n");
+        out.push_str("}\n");
     }
-    
-    out.push_str("fn main() {\n");
 
+    // 输出读取输入的辅助函数（read_int() 依赖 read()）
+    if out.contains("kairo_read") {
+        out.push_str("\n/// 从标准输入读取一行，去除末尾的换行符（输入结束时为空字符串）\n");
+        out.push_str("fn kairo_read() -> String {\n");
+        out.push_str("    let mut line = String::new();\n");
+        out.push_str("    std::io::stdin().read_line(&mut line).expect(\"failed to read input\");\n");
+        out.push_str("    line.truncate(line.trim_end_matches(['\\n', '\\r']).len());\n");
+        out.push_str("    line\n");
+        out.push_str("}\n");
+    }
+    if out.contains("kairo_read_int(") {
+        out.push_str("\n/// 从标准输入读取一行并转换为整数，不是整数时输出运行时错误并退出（错误信息与解释器一致）\n");
+        out.push_str("fn kairo_read_int(line: u32, col: u32) -> i64 {\n");
+        out.push_str("    let input = kairo_read();\n");
+        out.push_str("    input.trim().parse().unwrap_or_else(|_| {\n");
+        out.push_str("        eprintln!(\"运行时错误：输入的 `{input}` 不是整数（第 {line} 行，第 {col} 列）\");\n");
+        out.push_str("        std::process::exit(101);\n");
+        out.push_str("    })\n");
+        out.push_str("}\n");
+    }
+
+    Ok(out)
+}
+
+/// 将语句列表转换为Rust代码（入口函数或函数体的内容）
+/// 
+/// # 参数
+/// * `stmts` - 顶层或函数体中的语句
+/// * `semantic` - 当前代码块的语义分析信息
+/// * `options` - 代码生成选项
+/// * `out` - 输出缓冲区
+fn gen_block<'a>(
+    stmts: &'a [Stmt],
+    semantic: &SemanticInfo,
+    options: &CodegenOptions,
+    out: &mut String,
+) {
     // 跟踪已声明的变量，用于决定是使用let声明还是赋值
-    let mut declared: HashMap<&str, bool> = HashMap::new();
+    let mut declared: HashMap<&'a str, bool> = HashMap::new();
 
     // 遍历所有语句并转换为Rust代码
-    for stmt in &program.statements {
+    for (index, stmt) in stmts.iter().enumerate() {
+        if options.explain
+            && let Some(reason) = explain_stmt(stmt, &stmts[index + 1..], semantic, options, &declared)
+        {
+            out.push_str(&format!("    // 第 {} 行：{}\n", stmt.span().start.line, reason));
+        }
+
         match stmt {
-            Stmt::Print { content, .. } => {
+            Stmt::Print { args, span } => {
                 // 转换打印语句为println!宏
-                out.push_str(&format!("    println!(\"{}\");\n", escape(content)));
+                let (fmt, fmt_args) = gen_print_args(args, *span, semantic, options);
+                if options.print_helper {
+                    out.push_str(&format!("    kairo_print(format_args!(\"{}\"{}));\n", fmt, fmt_args));
+                } else if args.is_empty() {
+                    out.push_str("    println!();\n");
+                } else {
+                    out.push_str(&format!("    println!(\"{}\"{});\n", fmt, fmt_args));
+                }
             }
             Stmt::Assign { name, decl_mut, expr, .. } => {
                 // 获取变量的可变性信息
                 let mutability = semantic.vars.get(name).cloned().unwrap_or(Mutability::Immutable);
                 let is_first = !declared.contains_key(name.as_str());
-                let expr_code = gen_expr(expr, &semantic.vars);
+                let mut expr_code = gen_value(expr, semantic, options);
+
+                // 可变的字符串变量统一保存为String，使字面量和拼接结果都能赋值给它
+                if mutability == Mutability::Mutable && semantic.types.get(name) == Some(&Type::Str) {
+                    expr_code = format!("String::from({})", expr_code);
+                }
                 
                 // 根据变量状态生成不同的Rust代码
                 match (is_first, mutability, *decl_mut) {
-                    // 首次声明可变变量
+                    // 首次声明可变变量（之后没有重新赋值时不需要mut，避免rustc的unused_mut警告）
                     (true, Mutability::Mutable, true) => {
-                        out.push_str(&format!("    let {} = Rc::new(RefCell::new({}));\n", name, expr_code));
+                        let keyword = if is_reassigned(name, &stmts[index + 1..]) { "let mut" } else { "let" };
+                        out.push_str(&format!("    {} {} = {};\n", keyword, name, expr_code));
                         declared.insert(name, true);
                     }
                     // 首次声明不可变变量
@@ -61,31 +249,137 @@ pub fn generate_rust(program: &Program, semantic: &SemanticInfo) -> Result<Strin
                     }
                     // 修改已存在的可变变量
                     (false, Mutability::Mutable, _) => {
-                        out.push_str(&format!("    *{}.borrow_mut() = {};\n", name, expr_code));
+                        out.push_str(&format!("    {} = {};\n", name, expr_code));
                     }
-                    // 修改不可变变量（语义分析应该已阻止，但保留安全默认值）
+                    // 重新赋值不可变变量（仅宽松模式下允许）：用新的let绑定遮蔽原变量
                     (false, Mutability::Immutable, _) => {
-                        out.push_str(&format!("    let {} = {}; // (note) immutable redeclaration fallback\n", name, expr_code));
+                        out.push_str(&format!("    let {} = {};\n", name, expr_code));
                     }
                     // 语义不一致的情况（不应该发生，但保留安全默认值）
                     (true, Mutability::Mutable, false) | (true, Mutability::Immutable, true) => {
                         out.push_str(&format!("    let {} = {};\n", name, expr_code));
                         declared.insert(name, true);
                     }
+                    // 常量只能通过const声明（语义分析应该已阻止）
+                    (_, Mutability::Const, _) => {}
                 }
             }
+            Stmt::Const { .. } => {
+                // 常量不生成绑定，其值在使用处内联
+            }
+            Stmt::FnDef { .. } | Stmt::Invalid { .. } => {
+                // 函数定义在入口函数之后单独输出；无法解析的语句不会通过语义分析，不生成代码
+            }
+            Stmt::Return { expr: Some(expr), .. } => {
+                // 字符串返回值统一为String（参见`rust_return_type`）
+                let mut code = gen_value(expr, semantic, options);
+                if semantic.type_of(expr) == Some(Type::Str) && string_var(expr, semantic).is_none() {
+                    code = format!("String::from({})", code);
+                }
+                out.push_str(&format!("    return {};\n", code));
+            }
+            Stmt::Return { expr: None, .. } => {
+                out.push_str("    return;\n");
+            }
+            Stmt::Expr { expr, .. } => {
+                // 单独一行的函数调用，丢弃返回值
+                out.push_str(&format!("    {};\n", gen_expr(expr, semantic, options)));
+            }
         }
     }
+}
 
-    out.push_str("}\n");
-    Ok(out)
+/// 说明语句为何被转换为对应的Rust代码（用于`explain`选项）
+/// 
+/// # 参数
+/// * `stmt` - 当前语句
+/// * `rest` - 同一代码块中当前语句之后的语句（用于查找重新赋值的位置）
+/// * `semantic` - 当前代码块的语义分析信息
+/// * `options` - 代码生成选项
+/// * `declared` - 当前语句之前已声明的变量
+/// 
+/// # 返回值
+/// * `Option<String>` - 转换的理由；函数定义在入口函数之后单独说明，这里返回None
+fn explain_stmt(
+    stmt: &Stmt,
+    rest: &[Stmt],
+    semantic: &SemanticInfo,
+    options: &CodegenOptions,
+    declared: &HashMap<&str, bool>,
+) -> Option<String> {
+    let reason = match stmt {
+        Stmt::Print { .. } if options.print_helper => "print → kairo_print，全部输出经过同一个函数".to_string(),
+        Stmt::Print { .. } => "print → println!，参数在编译期展开为格式字符串".to_string(),
+        Stmt::Assign { name, .. } => {
+            let mutability = semantic.vars.get(name).cloned().unwrap_or(Mutability::Immutable);
+            let ty = semantic.types.get(name).map_or(String::new(), |ty| format!("（{ty}）"));
+            match (declared.contains_key(name.as_str()), mutability) {
+                (false, Mutability::Mutable) => {
+                    let reassigned = rest.iter().find_map(|later| match later {
+                        Stmt::Assign { name: later_name, span, .. } if later_name == name => Some(span.start.line),
+                        _ => None,
+                    });
+                    let string_note = if semantic.types.get(name) == Some(&Type::Str) { "，字符串保存为String" } else { "" };
+                    match reassigned {
+                        Some(line) => format!("可变变量 `{name}`{ty} → let mut绑定，因为在第 {line} 行重新赋值{string_note}"),
+                        None => format!("可变变量 `{name}`{ty} → let绑定，虽然声明时带有 `$`，但之后没有重新赋值{string_note}"),
+                    }
+                }
+                (false, _) => format!("不可变变量 `{name}`{ty} → let绑定，之后不会重新赋值"),
+                (true, Mutability::Mutable) => format!("重新赋值可变变量 `{name}` → 直接赋值"),
+                (true, _) => format!("宽松模式下重新赋值不可变变量 `{name}` → 用新的let绑定遮蔽原变量"),
+            }
+        }
+        Stmt::Const { name, .. } => format!("常量 `{name}` → 不生成绑定，在每个使用处内联其初始值"),
+        Stmt::FnDef { .. } | Stmt::Invalid { .. } => return None,
+        Stmt::Return { expr: Some(expr), .. } if semantic.type_of(expr) == Some(Type::Str) => {
+            "return → 字符串返回值转换为String，避免返回对局部变量的引用".to_string()
+        }
+        Stmt::Return { .. } => "return → Rust的return语句".to_string(),
+        Stmt::Expr { .. } => "函数调用语句 → 丢弃返回值".to_string(),
+    };
+    Some(reason)
+}
+
+/// 获取函数参数在Rust中的类型
+/// 
+/// # 说明
+/// 字符串参数使用 `&str`，调用时传入引用，调用者的变量在调用之后仍可使用
+fn rust_param_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "i64",
+        Type::Float => "f64",
+        Type::Str => "&str",
+        Type::Bool => "bool",
+    }
+}
+
+/// 获取函数返回值在Rust中的类型
+/// 
+/// # 说明
+/// 字符串返回值使用 `String`（可能是函数中拼接出的新字符串），其他类型与参数相同
+fn rust_return_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Str => "String",
+        _ => rust_param_type(ty),
+    }
+}
+
+/// 判断变量在之后的语句中是否被重新赋值（决定是否需要声明为 `let mut`）
+/// 
+/// # 参数
+/// * `name` - 变量名
+/// * `rest` - 同一代码块中声明语句之后的语句（函数体看不到外层的变量，因此只需查找同一代码块）
+fn is_reassigned(name: &str, rest: &[Stmt]) -> bool {
+    rest.iter().any(|stmt| matches!(stmt, Stmt::Assign { name: later, .. } if later == name))
 }
 
 /// 将表达式转换为Rust代码
 /// 
 /// # 参数
 /// * `expr` - 要转换的表达式
-/// * `vars` - 变量可变性映射表
+/// * `semantic` - 语义分析信息（变量可变性和常量初始值）
+/// * `options` - 代码生成选项（整数溢出的处理方式）
 /// 
 /// # 返回值
 /// * `String` - 生成的Rust表达式代码
@@ -93,24 +387,52 @@ pub fn generate_rust(program: &Program, semantic: &SemanticInfo) -> Result<Strin
 /// # 转换规则
 /// 1. 字符串字面量：添加引号并转义特殊字符
 /// 2. 整数字面量：直接转换为字符串
-/// 3. 标识符：根据可变性决定是否使用borrow()
-/// 4. 二元加法：递归转换左右操作数
-fn gen_expr(expr: &Expr, vars: &HashMap<String, Mutability>) -> String {
+/// 3. 标识符：直接使用变量名（可变的字符串变量取其副本），常量则内联其初始值
+/// 4. 二元运算（+ - * / % 和比较）：递归转换左右操作数；
+///    两个字符串相加转换为 `format!("{}{}", a, b)`，
+///    两个整数的加、减、乘转换为如 `i64::checked_add(a, b)` 的调用（见`gen_int_op`）
+/// 5. 括号分组：转换内部表达式
+/// 6. 取负：转换为 `(-内部表达式)`，整数取负同样转换为i64方法调用
+/// 7. 类型查询：替换为类型名称的字符串字面量
+/// 8. 函数调用：转换为同名Rust函数的调用
+/// 9. 读取输入：转换为 `kairo_read()` 或 `kairo_read_int(行, 列)` 辅助函数的调用
+/// 
+/// 常量折叠在代码生成之前完成（见`lower::fold_constants`），这里按原样转换表达式
+fn gen_expr(expr: &Expr, semantic: &SemanticInfo, options: &CodegenOptions) -> String {
+
     match expr {
         Expr::StringLit(s, _) => {
             // 字符串字面量：添加引号并转义
             format!("\"{}\"", escape(s))
         }
         Expr::IntLit(v, _) => {
-            // 整数字面量：直接转换
+            // 整数字面量：直接转换；超出i32范围时加上i64后缀，
+            // 否则rustc会把无后缀的字面量推断为i32而报告溢出
+            if i32::try_from(*v).is_ok() {
+                v.to_string()
+            } else {
+                format!("{}_i64", v)
+            }
+        }
+        Expr::BoolLit(v, _) => {
+            // 布尔字面量：直接转换
             v.to_string()
         }
+        Expr::FloatLit(v, _) => {
+            // 浮点数字面量：Debug格式总是保留小数点或指数（3.0 而不是 3），
+            // 保证Rust将其推断为f64而不是整数
+            format!("{:?}", v)
+        }
         Expr::Ident(name, _) => {
             // 标识符：根据可变性决定访问方式
-            match vars.get(name) {
-                Some(Mutability::Mutable) => {
-                    // 可变变量：使用borrow()获取值
-                    format!("*{}.borrow()", name)
+            match semantic.vars.get(name) {
+                Some(Mutability::Mutable) if semantic.types.get(name) == Some(&Type::Str) => {
+                    // 可变的字符串变量：取其副本，避免赋值给其他变量时移出String
+                    format!("{}.clone()", name)
+                }
+                Some(Mutability::Const) => {
+                    // 常量：内联初始值
+                    gen_expr(&semantic.consts[name], semantic, options)
                 }
                 _ => {
                     // 不可变变量：直接使用
@@ -118,10 +440,257 @@ fn gen_expr(expr: &Expr, vars: &HashMap<String, Mutability>) -> String {
                 }
             }
         }
-        Expr::BinaryAdd(a, b, _) => {
-            // 二元加法：递归转换左右操作数
-            format!("({} + {})", gen_expr(a, vars), gen_expr(b, vars))
+        Expr::BinaryAdd(a, b, _)
+            if semantic.type_of(a) == Some(Type::Str) && semantic.type_of(b) == Some(Type::Str) =>
+        {
+            // 字符串拼接：Rust中 &str + &str 不合法，用format!生成新的String
+            format!("format!(\"{{}}{{}}\", {}, {})", gen_expr(a, semantic, options), gen_expr(b, semantic, options))
+        }
+        Expr::BinaryAdd(a, b, span) if is_int_pair(a, b, semantic) => {
+            gen_int_op("add", "整数加法溢出", &[a, b], *span, semantic, options)
+        }
+        Expr::BinaryAdd(a, b, _) => gen_arith(a, "+", b, semantic, options),
+        Expr::BinarySub(a, b, span) if is_int_pair(a, b, semantic) => {
+            gen_int_op("sub", "整数减法溢出", &[a, b], *span, semantic, options)
+        }
+        Expr::BinarySub(a, b, _) => gen_arith(a, "-", b, semantic, options),
+        Expr::BinaryMul(a, b, span) if is_int_pair(a, b, semantic) => {
+            gen_int_op("mul", "整数乘法溢出", &[a, b], *span, semantic, options)
+        }
+        Expr::BinaryMul(a, b, _) => gen_arith(a, "*", b, semantic, options),
+        Expr::BinaryDiv(a, b, _) => gen_arith(a, "/", b, semantic, options),
+        Expr::BinaryMod(a, b, _) => gen_arith(a, "%", b, semantic, options),
+        Expr::Compare(op, a, b, _)
+            if semantic.type_of(a) == Some(Type::Str) || semantic.type_of(b) == Some(Type::Str) =>
+        {
+            // 字符串比较：操作数可能是String也可能是&str，两侧都解引用为&str再比较
+            // （String与&str直接比较时rustc报告E0308）
+            format!("(&*{} {} &*{})", gen_expr(a, semantic, options), op.symbol(), gen_expr(b, semantic, options))
+        }
+        // 比较运算与算术运算的数值提升规则相同
+        Expr::Compare(op, a, b, _) => gen_arith(a, op.symbol(), b, semantic, options),
+        Expr::TypeOf(inner, _) => {
+            // 类型查询：在编译期替换为类型名称的字符串字面量
+            match semantic.type_of(inner) {
+                Some(ty) => format!("\"{}\"", ty),
+                None => "\"unknown\"".to_string(),
+            }
+        }
+        Expr::Neg(inner, span) if semantic.type_of(inner) == Some(Type::Int) => {
+            gen_int_op("neg", "整数取负溢出", &[inner], *span, semantic, options)
+        }
+        Expr::Neg(inner, _) => {
+            // 取负：外层括号避免与相邻运算符结合出错；
+            // 操作数本身以负号开头时（如 `- -5`）再加一层括号，不生成 `--5`
+            let inner_code = gen_expr(inner, semantic, options);
+            if inner_code.starts_with('-') {
+                format!("(-({}))", inner_code)
+            } else {
+                format!("(-{})", inner_code)
+            }
+        }
+        Expr::Group(inner, _) => {
+            // 括号分组：二元运算本身已带括号，直接转换内部表达式
+            gen_expr(inner, semantic, options)
+        }
+        Expr::Call(name, args, _) => {
+            // 函数调用：字符串实参以引用传入（参见`rust_param_type`）
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| {
+                    let code = gen_expr(arg, semantic, options);
+                    if semantic.type_of(arg) == Some(Type::Str) {
+                        format!("&{}", code)
+                    } else {
+                        code
+                    }
+                })
+                .collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Expr::Read(ReadKind::Line, _) => "kairo_read()".to_string(),
+        Expr::Read(ReadKind::Int, span) => {
+            format!("kairo_read_int({}, {})", span.start.line, span.start.col)
+        }
+    }
+}
+
+/// 生成print语句的格式字符串和参数列表
+/// 
+/// # 参数
+/// * `args` - print语句的参数
+/// * `span` - print语句的源码位置
+/// * `semantic` - 语义分析信息
+/// 
+/// # 返回值
+/// * `(String, String)` - 转义后的格式字符串，以及形如 `, a, x = 10` 的参数列表
+/// 
+/// # 转换规则
+/// 1. 格式字符串形式（`print("x = {}", x)`）：第一个参数直接作为格式字符串，其余参数依次填入
+/// 2. 其他形式：字符串字面量直接写入格式字符串（保留 `{变量名}` 占位符），
+///    其余参数生成 `{}`，各部分以空格分隔
+fn gen_print_args(args: &[Expr], span: SourceSpan, semantic: &SemanticInfo, options: &CodegenOptions) -> (String, String) {
+    let mut pieces: Vec<String> = Vec::new();
+    let mut contents: Vec<&str> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+    let format_print = is_format_print(args);
+
+    for (i, arg) in args.iter().enumerate() {
+        match arg {
+            Expr::StringLit(content, _) if i == 0 || !format_print => {
+                pieces.push(escape(content));
+                contents.push(content);
+            }
+            _ => {
+                pieces.push("{}".to_string());
+                values.push(gen_expr(arg, semantic, options));
+            }
+        }
+    }
+
+    let fmt = if format_print {
+        pieces.remove(0)
+    } else {
+        pieces.join(" ")
+    };
+
+    // 位置参数必须在命名参数之前
+    let mut fmt_args: String = values.iter().map(|value| format!(", {}", value)).collect();
+    fmt_args.push_str(&gen_named_args(&contents, span, semantic, options));
+    (fmt, fmt_args)
+}
+
+/// 为print字符串中的命名占位符生成显式的命名参数
+/// 
+/// # 参数
+/// * `contents` - print的各字符串参数的内容
+/// * `span` - print语句的源码位置
+/// * `semantic` - 语义分析信息
+/// 
+/// # 返回值
+/// * `String` - 形如 `, x = 10` 的参数列表（不需要时为空字符串）
+/// 
+/// # 说明
+/// 变量可以直接被Rust格式字符串捕获（`{x}`）；
+/// 常量被内联，生成的代码中没有对应的绑定，需要显式传入值
+fn gen_named_args(contents: &[&str], span: SourceSpan, semantic: &SemanticInfo, options: &CodegenOptions) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for content in contents {
+        for placeholder in parse_placeholders(content).unwrap_or_default() {
+            if let Placeholder::Named(name) = placeholder
+                && matches!(semantic.vars.get(&name), Some(Mutability::Const))
+                && !names.contains(&name)
+            {
+                names.push(name);
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let value = gen_expr(&Expr::Ident(name.clone(), span), semantic, options);
+            format!(", {} = {}", name, value)
+        })
+        .collect()
+}
+
+/// 将作为完整值使用（赋给变量或作为返回值）的表达式转换为Rust代码
+/// 
+/// # 参数
+/// * `expr` - 赋值或return语句中的表达式
+/// * `semantic` - 语义分析信息
+/// * `options` - 代码生成选项
+/// 
+/// # 返回值
+/// * `String` - 生成的Rust表达式代码
+/// 
+/// # 说明
+/// 不可变的字符串变量可能是`&str`（字面量、参数），也可能是`String`（拼接、读取输入的结果）。
+/// 直接写 `let z = y;` 会把String移出`y`，之后再使用`y`时rustc报告E0382，
+/// 因此这里转换为 `y.to_string()` 取得副本（对`&str`调用`.clone()`会触发rustc的noop_method_call警告）。
+/// 可变的字符串变量在`gen_expr`中已经取了副本
+fn gen_value(expr: &Expr, semantic: &SemanticInfo, options: &CodegenOptions) -> String {
+    match string_var(expr, semantic) {
+        Some(name) if semantic.vars.get(name) == Some(&Mutability::Immutable) => format!("{}.to_string()", name),
+        _ => gen_expr(expr, semantic, options),
+    }
+}
+
+/// 判断表达式是否是一个字符串变量（忽略外层括号，常量除外）
+/// 
+/// # 返回值
+/// * `Option<&str>` - 是字符串变量时返回变量名；`gen_value`对其生成的代码总是String
+fn string_var<'a>(expr: &'a Expr, semantic: &SemanticInfo) -> Option<&'a str> {
+    match expr {
+        Expr::Group(inner, _) => string_var(inner, semantic),
+        Expr::Ident(name, _)
+            if semantic.types.get(name) == Some(&Type::Str)
+                && semantic.vars.get(name) != Some(&Mutability::Const) =>
+        {
+            Some(name)
         }
+        _ => None,
+    }
+}
+
+/// 将二元算术或比较运算转换为Rust代码
+/// 
+/// # 参数
+/// * `a` - 左操作数
+/// * `op` - Rust运算符（`+` `-` `*` `/` `%` 或比较运算符）
+/// * `b` - 右操作数
+/// * `semantic` - 语义分析信息（用于确定操作数类型）
+/// 
+/// # 返回值
+/// * `String` - 生成的Rust表达式代码
+/// 
+/// # 数值提升
+/// Rust不允许i64与f64直接运算。整数与浮点数混合运算时，
+/// 整数一侧会转换为f64（`(x as f64)`），结果为浮点数。
+/// 两侧都是整数时保持整数运算（除法为整数除法）
+fn gen_arith(a: &Expr, op: &str, b: &Expr, semantic: &SemanticInfo, options: &CodegenOptions) -> String {
+    let mut lhs = gen_expr(a, semantic, options);
+    let mut rhs = gen_expr(b, semantic, options);
+    match (semantic.type_of(a), semantic.type_of(b)) {
+        (Some(Type::Int), Some(Type::Float)) => lhs = format!("({} as f64)", lhs),
+        (Some(Type::Float), Some(Type::Int)) => rhs = format!("({} as f64)", rhs),
+        _ => {}
+    }
+    format!("({} {} {})", lhs, op, rhs)
+}
+
+/// 判断二元运算的两个操作数是否都是整数
+fn is_int_pair(a: &Expr, b: &Expr, semantic: &SemanticInfo) -> bool {
+    semantic.type_of(a) == Some(Type::Int) && semantic.type_of(b) == Some(Type::Int)
+}
+
+/// 将可能溢出的整数运算转换为i64方法调用
+/// 
+/// # 参数
+/// * `method` - 运算名称（`add` `sub` `mul` 或 `neg`）
+/// * `what` - 溢出时的错误信息（如 `整数加法溢出`，与解释器一致）
+/// * `operands` - 操作数（取负时只有一个）
+/// * `span` - 运算的源码位置（用于溢出时的错误信息）
+/// * `semantic` - 语义分析信息
+/// * `options` - 代码生成选项（整数溢出的处理方式）
+/// 
+/// # 返回值
+/// * `String` - 如 `i64::wrapping_add(a, b)`；检查溢出时为
+///   `i64::checked_add(a, b).unwrap_or_else(|| kairo_overflow("整数加法溢出", 3, 5))`
+/// 
+/// # 说明
+/// 使用 `i64::方法(a, b)` 而不是 `a.方法(b)` 的形式：
+/// 无后缀的整数字面量作为方法接收者时rustc无法推断其类型
+fn gen_int_op(method: &str, what: &str, operands: &[&Expr], span: SourceSpan, semantic: &SemanticInfo, options: &CodegenOptions) -> String {
+    let args: Vec<String> = operands.iter().map(|operand| gen_expr(operand, semantic, options)).collect();
+    let call = format!("i64::{}_{}({})", options.overflow.method_prefix(), method, args.join(", "));
+    match options.overflow {
+        Overflow::Check => format!(
+            "{}.unwrap_or_else(|| kairo_overflow(\"{}\", {}, {}))",
+            call, what, span.start.line, span.start.col
+        ),
+        Overflow::Wrap | Overflow::Saturate => call,
     }
 }
 
@@ -136,6 +705,186 @@ fn gen_expr(expr: &Expr, vars: &HashMap<String, Mutability>) -> String {
 /// # 转义规则
 /// * `\` -> `\\`
 /// * `"` -> `\"`
+/// * 换行、制表符、回车、空字符 -> `\n` `\t` `\r` `\0`
 fn escape(s: &str) -> String {
-    s.replace('\\', "\\\\").replace('"', "\\\"")
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            '\0' => out.push_str("\\0"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::compile_source_to_rust;
+    use crate::compiler::config::CliOverrides;
+    use crate::compiler::semantics::diagnostics::{Diagnostics, Reporter};
+    use crate::compiler::semantics::analysis::SemanticOptions;
+    use std::path::Path;
+    use std::process::Command;
+
+    /// 生成源代码对应的Rust代码
+    fn rust_of(source: &str) -> String {
+        rust_with(source, &CodegenOptions::default())
+    }
+
+    /// 按指定的代码生成选项生成源代码对应的Rust代码
+    fn rust_with(source: &str, options: &CodegenOptions) -> String {
+        let overrides = CliOverrides::default();
+        compile_source_to_rust(source, Path::new("test.kr"), &overrides, &SemanticOptions::default(), options, &Reporter::default())
+            .expect("source should compile")
+    }
+
+    /// 用rustc编译生成的Rust代码，返回是否成功
+    fn rustc_accepts(name: &str, rust_code: &str) -> bool {
+        let dir = std::env::temp_dir().join(format!("kairo-codegen-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("main.rs"), rust_code).unwrap();
+        let status = Command::new("rustc")
+            .args(["--edition", "2024", "-o"])
+            .arg(dir.join("main"))
+            .arg(dir.join("main.rs"))
+            .status()
+            .expect("failed to run rustc");
+        let _ = std::fs::remove_dir_all(&dir);
+        status.success()
+    }
+
+    #[test]
+    fn copies_concatenated_string_on_assignment() {
+        let rust_code = rust_of("x = \"a\"\ny = x + x\nz = y\nprint(y)\nprint(z)\n");
+        assert!(rust_code.contains("let z = y.to_string();"), "{rust_code}");
+        assert!(rustc_accepts("concat", &rust_code));
+    }
+
+    #[test]
+    fn copies_string_parameter_on_return() {
+        let rust_code = rust_of("fun f(s: string) -> string {\n    t = (s)\n    return t\n}\nprint(f(\"q\"))\n");
+        assert!(rust_code.contains("let t = s.to_string();"), "{rust_code}");
+        assert!(rust_code.contains("return t.to_string();"), "{rust_code}");
+        assert!(rustc_accepts("param", &rust_code));
+    }
+
+    #[test]
+    fn copies_read_input_on_assignment() {
+        let rust_code = rust_of("n = read()\nb = n\nprint(n)\nprint(b)\n");
+        assert!(rust_code.contains("let b = n.to_string();"), "{rust_code}");
+        assert!(rustc_accepts("read", &rust_code));
+    }
+
+    #[test]
+    fn compares_strings_as_str() {
+        let rust_code = rust_of("$x = \"a\"\ny = x < \"b\"\nprint(y)\n");
+        assert!(rust_code.contains("(&*x.clone() < &*\"b\")"), "{rust_code}");
+        assert!(rustc_accepts("compare-mut", &rust_code));
+        let rust_code = rust_of("a = \"a\"\ns = a + \"b\"\nprint(s < \"c\")\n");
+        assert!(rustc_accepts("compare-concat", &rust_code), "{rust_code}");
+    }
+
+    #[test]
+    fn pragma_sets_overflow_mode() {
+        let rust_code = rust_of("//!kairo overflow=wrap release\n$x = 1\nx = x + 1\nprint(x)\n");
+        assert!(rust_code.contains("i64::wrapping_add(x, 1)"), "{rust_code}");
+        assert!(!rust_code.contains("kairo_overflow"), "{rust_code}");
+    }
+
+    #[test]
+    fn folds_constant_arithmetic() {
+        let rust_code = rust_of("x = 2 + 3 * 4\nprint(x)\n");
+        assert!(rust_code.contains("let x = 14;"), "{rust_code}");
+    }
+
+    #[test]
+    fn inlines_constants_without_let() {
+        let rust_code = rust_of("const N = 5\nconst M = N * 2\ny = M + 1\nprint(y, N)\n");
+        assert!(!rust_code.contains("let N") && !rust_code.contains("let M"), "{rust_code}");
+        assert!(rust_code.contains("let y = 11;"), "{rust_code}");
+        assert!(rust_code.contains("println!(\"{} {}\", y, 5);"), "{rust_code}");
+    }
+
+    #[test]
+    fn folded_mutable_variable_needs_no_imports() {
+        let rust_code = rust_of("$x = 2 + 3\nprint(x)\n");
+        assert!(rust_code.contains("let x = 5;"), "{rust_code}");
+        assert!(!rust_code.contains("use std::rc") && !rust_code.contains("Rc"), "{rust_code}");
+        assert!(!rust_code.contains("RefCell"), "{rust_code}");
+        assert!(rustc_accepts("folded-mut", &rust_code));
+    }
+
+    #[test]
+    fn print_helper_is_defined_and_used() {
+        let options = CodegenOptions { print_helper: true, ..Default::default() };
+        let rust_code = rust_with("x = 1\nprint(\"x =\", x)\nprint()\n", &options);
+        assert!(rust_code.contains("fn kairo_print(args: std::fmt::Arguments) {"), "{rust_code}");
+        assert!(rust_code.contains("kairo_print(format_args!(\"x = {}\", x));"), "{rust_code}");
+        assert!(!rust_code.contains("    println!(\"{}\""), "{rust_code}");
+        assert!(rustc_accepts("print-helper", &rust_code));
+        assert!(!rust_of("print(1)\n").contains("kairo_print"));
+    }
+
+    #[test]
+    fn typeof_emits_type_name_literal() {
+        for (expr, name) in [("1", "int"), ("1.5", "float"), ("\"a\"", "string"), ("1 < 2", "bool"), ("n", "int"), ("s + s", "string")] {
+            let rust_code = rust_of(&format!("n = 1\ns = \"b\"\nprint(typeof({expr}), n, s)\n"));
+            assert!(rust_code.contains(&format!("println!(\"{{}} {{}} {{}}\", \"{name}\", n, s);")), "typeof({expr}):\n{rust_code}");
+        }
+    }
+
+    #[test]
+    fn negates_nested_and_grouped_expressions() {
+        let rust_code = rust_of("a = 1.5\nx = - -a\ny = -(a + a)\nprint(x, y, - -5)\n");
+        assert!(rust_code.contains("let x = (-(-a));"), "{rust_code}");
+        assert!(rust_code.contains("let y = (-(a + a));"), "{rust_code}");
+        assert!(rust_code.contains("println!(\"{} {} {}\", x, y, 5);"), "{rust_code}");
+        assert!(rustc_accepts("neg", &rust_code));
+    }
+
+    #[test]
+    fn prints_mixed_literal_and_identifier_arguments() {
+        let rust_code = rust_of("x = 1\ny = \"b, c\"\nprint(\"x =\", x, \"y =\", y, 2.5, true)\nprint()\nprint(\"a, (b)\", x)\n");
+        assert!(rust_code.contains("println!(\"x = {} y = {} {} {}\", x, y, 2.5, true);"), "{rust_code}");
+        assert!(rust_code.contains("println!();"), "{rust_code}");
+        assert!(rust_code.contains("println!(\"a, (b) {}\", x);"), "{rust_code}");
+        assert!(rustc_accepts("print-args", &rust_code));
+    }
+
+    #[test]
+    fn entry_names_the_generated_function() {
+        let options = CodegenOptions { entry: Some("run".to_string()), ..Default::default() };
+        let rust_code = rust_with("print(1)\n", &options);
+        assert!(rust_code.starts_with("pub fn run() {\n"), "{rust_code}");
+        assert!(!rust_code.contains("fn main"), "{rust_code}");
+        assert!(rust_of("print(1)\n").starts_with("fn main() {\n"));
+    }
+
+    #[test]
+    fn explains_mutable_variable_lowering() {
+        let options = CodegenOptions { explain: true, ..Default::default() };
+        let rust_code = rust_with("$x = 1\ny = 2\nx = x + y\nprint(x)\n", &options);
+        assert!(
+            rust_code.contains("    // 第 1 行：可变变量 `x`（int） → let mut绑定，因为在第 3 行重新赋值\n    let mut x = 1;\n"),
+            "{rust_code}"
+        );
+        assert!(rust_code.contains("    // 第 2 行：不可变变量 `y`（int） → let绑定，之后不会重新赋值\n"), "{rust_code}");
+        assert!(!rust_of("$x = 1\nprint(x)\n").contains("//"));
+    }
+
+    #[test]
+    fn division_by_zero_is_a_compile_error() {
+        let source = "x = 1 / 0\nprint(x)\n";
+        let overrides = CliOverrides::default();
+        let result = compile_source_to_rust(source, Path::new("test.kr"), &overrides, &SemanticOptions::default(), &CodegenOptions::default(), &Reporter::default());
+        let err = result.expect_err("division by zero should not compile");
+        let diagnostics = err.downcast::<Diagnostics>().expect("friendly diagnostics");
+        assert_eq!(diagnostics.0[0].message, "不能除以0");
+    }
 }
@@ -0,0 +1,271 @@
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use super::codegen::rust::Overflow;
+use super::interp::Limits;
+use super::parser::{self, pragma::Pragma};
+use super::semantics::diagnostics::{self, ColorChoice};
+
+/// 设置值的来源
+/// 
+/// # 变体
+/// * `Default` - 内置的默认值
+/// * `Env` - 环境变量（保存变量名）
+/// * `Pragma` - 源文件顶部的编译指示
+/// * `Cli` - 命令行参数（保存参数名）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// 内置的默认值
+    Default,
+    /// 环境变量
+    Env(&'static str),
+    /// 编译指示
+    Pragma,
+    /// 命令行参数
+    Cli(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Env(name) => write!(f, "env {name}"),
+            Source::Pragma => write!(f, "pragma"),
+            Source::Cli(flag) => write!(f, "CLI {flag}"),
+        }
+    }
+}
+
+/// 带来源的设置值
+/// 
+/// # 字段
+/// * `value` - 生效的值
+/// * `source` - 值的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    /// 创建使用默认值的设置
+    fn default_value(value: T) -> Self {
+        Setting { value, source: Source::Default }
+    }
+
+    /// 用优先级更高的来源覆盖设置值
+    pub fn set(&mut self, value: T, source: Source) {
+        self.value = value;
+        self.source = source;
+    }
+}
+
+/// 合并后的全部生效设置
+/// 
+/// # 字段
+/// * `out_dir` - 构建产物的输出目录
+/// * `release` - 是否使用发布模式（优化）
+/// * `overflow` - 整数加、减、乘和取负溢出时的处理方式（与发布模式无关）
+/// * `edition` - 编译生成代码使用的Rust版本
+/// * `rustc` - 调用的rustc程序
+/// * `color` - 诊断信息是否带颜色
+/// * `caret_char` - 诊断信息中标记出错位置的字符
+/// * `full_errors` - 出错时是否显示完整的错误链
+/// * `max_call_depth` - 解释器中函数调用的最大嵌套深度
+/// * `max_steps` - 解释器每次执行最多执行的语句条数
+/// 
+/// # 优先级
+/// 命令行参数 > 编译指示 > 环境变量 > 默认值；
+/// 每项设置只接受其中一部分来源（如编译指示目前只能设置发布模式和整数溢出的处理方式）
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub out_dir: Setting<PathBuf>,
+    pub release: Setting<bool>,
+    pub overflow: Setting<Overflow>,
+    pub edition: Setting<String>,
+    pub rustc: Setting<String>,
+    pub color: Setting<bool>,
+    pub caret_char: Setting<char>,
+    pub full_errors: Setting<bool>,
+    pub max_call_depth: Setting<usize>,
+    pub max_steps: Setting<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            out_dir: Setting::default_value(default_out_dir()),
+            release: Setting::default_value(false),
+            overflow: Setting::default_value(Overflow::default()),
+            edition: Setting::default_value("2024".to_string()),
+            rustc: Setting::default_value("rustc".to_string()),
+            color: Setting::default_value(true),
+            caret_char: Setting::default_value('^'),
+            full_errors: Setting::default_value(false),
+            max_call_depth: Setting::default_value(Limits::default().max_call_depth),
+            max_steps: Setting::default_value(Limits::default().max_steps),
+        }
+    }
+}
+
+/// 命令行参数中指定的设置（None或false表示未指定，由编译指示、环境变量或默认值决定）
+/// 
+/// # 字段
+/// * `out_dir` - 构建产物和中间文件的输出目录（--out-dir，相对于当前目录）；
+///   同时编译同名源文件的调用方（如并行运行的测试）应各自指定不同的目录，以免互相覆盖`<stem>.rs`等文件
+/// * `release` - 是否使用发布模式（--release）
+/// * `overflow` - 整数溢出的处理方式（--overflow）
+/// * `color` - 诊断信息是否带颜色（--color）
+/// * `caret_char` - 标记出错位置的字符（--caret-char）
+/// * `full_errors` - 出错时是否显示完整的错误链（--full-errors）
+/// * `max_call_depth` - 解释器中函数调用的最大嵌套深度（--max-call-depth）
+/// * `max_steps` - 解释器每次执行最多执行的语句条数（--max-steps）
+#[derive(Debug, Clone, Default)]
+pub struct CliOverrides {
+    pub out_dir: Option<PathBuf>,
+    pub release: Option<bool>,
+    pub overflow: Option<Overflow>,
+    pub color: Option<ColorChoice>,
+    pub caret_char: Option<char>,
+    pub full_errors: bool,
+    pub max_call_depth: Option<usize>,
+    pub max_steps: Option<u64>,
+}
+
+impl Config {
+    /// 在默认值的基础上读取环境变量
+    /// 
+    /// # 说明
+    /// 识别的环境变量：NO_COLOR（设置即禁用颜色）、KAIRO_CARET_CHAR（无效时忽略）、
+    /// KAIRO_FULL_ERROR（非空且不为0时启用）
+    pub fn from_env() -> Self {
+        let mut config = Config::default();
+        if env::var("NO_COLOR").is_ok() {
+            config.color.set(false, Source::Env("NO_COLOR"));
+        }
+        if let Some(marker) = env::var("KAIRO_CARET_CHAR").ok().and_then(|v| diagnostics::parse_caret_char(&v).ok()) {
+            config.caret_char.set(marker, Source::Env("KAIRO_CARET_CHAR"));
+        }
+        if env::var("KAIRO_FULL_ERROR").is_ok_and(|v| !v.is_empty() && v != "0") {
+            config.full_errors.set(true, Source::Env("KAIRO_FULL_ERROR"));
+        }
+        config
+    }
+
+    /// 解析编译一个源文件时的生效设置
+    /// 
+    /// # 参数
+    /// * `source` - 源代码字符串（用于读取编译指示）
+    /// * `overrides` - 命令行参数中指定的设置
+    /// 
+    /// # 返回值
+    /// * `Result<Config>` - 合并了环境变量、编译指示和命令行参数的设置；编译指示无效时返回错误
+    pub fn resolve(source: &str, overrides: &CliOverrides) -> Result<Self> {
+        let mut config = Config::from_env();
+        config.apply_pragma(&parser::parse_pragma(source)?);
+        if let Some(release) = overrides.release {
+            config.release.set(release, Source::Cli("--release"));
+        }
+        if let Some(overflow) = overrides.overflow {
+            config.overflow.set(overflow, Source::Cli("--overflow"));
+        }
+        if let Some(dir) = &overrides.out_dir {
+            config.out_dir.set(dir.clone(), Source::Cli("--out-dir"));
+        }
+        if let Some(choice) = overrides.color {
+            config.color.set(choice.enabled(), Source::Cli("--color"));
+        }
+        if let Some(marker) = overrides.caret_char {
+            config.caret_char.set(marker, Source::Cli("--caret-char"));
+        }
+        if overrides.full_errors {
+            config.full_errors.set(true, Source::Cli("--full-errors"));
+        }
+        if let Some(depth) = overrides.max_call_depth {
+            config.max_call_depth.set(depth, Source::Cli("--max-call-depth"));
+        }
+        if let Some(steps) = overrides.max_steps {
+            config.max_steps.set(steps, Source::Cli("--max-steps"));
+        }
+        Ok(config)
+    }
+
+    /// 解释器的执行上限
+    pub fn limits(&self) -> Limits {
+        Limits { max_call_depth: self.max_call_depth.value, max_steps: self.max_steps.value }
+    }
+
+    /// 应用编译指示中的设置
+    pub fn apply_pragma(&mut self, pragma: &Pragma) {
+        if let Some(release) = pragma.release {
+            self.release.set(release, Source::Pragma);
+        }
+        if let Some(overflow) = pragma.overflow {
+            self.overflow.set(overflow, Source::Pragma);
+        }
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = if self.color.value { "always" } else { "never" };
+        let rows = [
+            ("out_dir", self.out_dir.value.display().to_string(), self.out_dir.source),
+            ("release", self.release.value.to_string(), self.release.source),
+            ("overflow", self.overflow.value.to_string(), self.overflow.source),
+            ("edition", self.edition.value.clone(), self.edition.source),
+            ("rustc", self.rustc.value.clone(), self.rustc.source),
+            ("color", color.to_string(), self.color.source),
+            ("caret_char", self.caret_char.value.to_string(), self.caret_char.source),
+            ("full_errors", self.full_errors.value.to_string(), self.full_errors.source),
+            ("max_call_depth", self.max_call_depth.value.to_string(), self.max_call_depth.source),
+            ("max_steps", self.max_steps.value.to_string(), self.max_steps.source),
+        ];
+        for (key, value, source) in rows {
+            writeln!(f, "{key:<14} = {value:<20} ({source})")?;
+        }
+        Ok(())
+    }
+}
+
+/// 构建产物的默认输出目录：`target/kairo_out`
+pub fn default_out_dir() -> PathBuf {
+    PathBuf::from("target").join("kairo_out")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pragma_sets_overflow_when_cli_does_not() {
+        let config = Config::resolve("//!kairo overflow=wrap\n", &CliOverrides::default()).unwrap();
+        assert_eq!(config.overflow, Setting { value: Overflow::Wrap, source: Source::Pragma });
+    }
+
+    #[test]
+    fn cli_overflow_overrides_pragma() {
+        let overrides = CliOverrides { overflow: Some(Overflow::Saturate), ..Default::default() };
+        let config = Config::resolve("//!kairo overflow=wrap\n", &overrides).unwrap();
+        assert_eq!(config.overflow, Setting { value: Overflow::Saturate, source: Source::Cli("--overflow") });
+    }
+
+    #[test]
+    fn cli_overrides_are_recorded_with_their_flag() {
+        let overrides = CliOverrides {
+            out_dir: Some(PathBuf::from("build")),
+            release: Some(true),
+            caret_char: Some('~'),
+            full_errors: true,
+            ..Default::default()
+        };
+        let config = Config::resolve("", &overrides).unwrap();
+        assert_eq!(config.out_dir, Setting { value: PathBuf::from("build"), source: Source::Cli("--out-dir") });
+        assert_eq!(config.release.source, Source::Cli("--release"));
+        assert_eq!(config.caret_char, Setting { value: '~', source: Source::Cli("--caret-char") });
+        assert_eq!(config.full_errors.source, Source::Cli("--full-errors"));
+    }
+}
@@ -0,0 +1,337 @@
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use super::ast::{Expr, Param, SourceSpan, Stmt};
+use super::parser;
+
+/// 每级缩进的空格数
+const INDENT: &str = "    ";
+
+/// 将Kairo源代码格式化为规范的写法
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `file` - 源文件路径（用于错误报告）
+/// 
+/// # 返回值
+/// * `Result<String>` - 格式化后的源代码；源代码有语法错误时返回错误信息
+/// 
+/// # 格式规则
+/// 1. `=` 和二元运算符两侧各一个空格，逗号之后一个空格，括号内侧没有空格
+/// 2. `$` 紧贴变量名（`$ x = 1` 写作 `$x = 1`），函数定义统一使用 `fun` 关键字
+/// 3. 每条语句单独一行（以分号分隔写在同一行的多条语句拆分为多行）；
+///    函数体缩进4个空格，写在同一行的函数体展开为多行，没有语句的函数体写作 `{}`
+/// 4. 注释行（包括编译指示）和shebang保留原样，只调整缩进；
+///    行尾注释保留在该行代码之后，与代码之间以一个空格分隔；
+///    单独成行的块注释只调整第一行的缩进，之后各行保留原样
+/// 5. 连续多个空行合并为一个，文件开头、结尾和代码块首尾的空行删除
+/// 6. 括号、数字和字符串字面量保留源码中的写法（如 `0xFF`、`1_000`、转义字符）
+/// 
+/// 格式化是幂等的：对格式化的结果再次格式化不会有任何改变。
+/// 块注释与代码写在同一行时（如 `x = /* 初始值 */ 1`）无法保留注释，因此报告错误而不是删除注释
+pub fn format_source(source: &str, file: &Path) -> Result<String> {
+    let program = parser::parse(source, file)?;
+    let masked = parser::mask_block_comments(source)?;
+    for (i, (original, code)) in source.lines().zip(masked.text.lines()).enumerate() {
+        if original != code && !is_trivia(code) {
+            bail!("无法格式化：第 {} 行的块注释与代码写在同一行，请把块注释移到单独的行", i + 1);
+        }
+    }
+    let mut formatter = Formatter {
+        lines: source.lines().collect(),
+        blank_lines: &program.blank_lines,
+        code: masked.text.lines().collect(),
+        continued_lines: masked.continued_lines,
+        out: String::new(),
+        next_line: 1,
+        pending_blank: false,
+        block_empty: true,
+        commented_line: 0,
+    };
+    for stmt in &program.statements {
+        formatter.stmt(stmt, 0);
+    }
+    formatter.trivia_until(formatter.lines.len() + 1, 0);
+    Ok(formatter.out)
+}
+
+/// 把一个表达式输出为规范的写法（与格式化源文件时相同，见`format_source`）
+/// 
+/// # 参数
+/// * `expr` - 要输出的表达式
+/// * `source` - 表达式所在的源代码（数字和字符串字面量按源码位置取出原文）
+/// 
+/// # 返回值
+/// * `String` - 格式化后的表达式
+pub fn format_expr(expr: &Expr, source: &str) -> String {
+    let formatter = Formatter {
+        lines: source.lines().collect(),
+        blank_lines: &[],
+        code: Vec::new(),
+        continued_lines: Vec::new(),
+        out: String::new(),
+        next_line: 1,
+        pending_blank: false,
+        block_empty: true,
+        commented_line: 0,
+    };
+    formatter.expr(expr)
+}
+
+/// 格式化输出缓冲区
+/// 
+/// # 字段
+/// * `lines` - 源代码的各行（用于取出注释和字面量的原文）
+/// * `blank_lines` - 解析器记录的需要保留的空行（见`Program::blank_lines`）
+/// * `code` - 块注释替换为空白后的各行（用于区分代码和注释）
+/// * `continued_lines` - 从块注释内部开始的行号（原样输出）
+/// * `out` - 已生成的源代码
+/// * `next_line` - 下一个尚未处理的源码行号（从1开始）
+/// * `pending_blank` - 下一行输出之前是否需要空行
+/// * `block_empty` - 当前代码块中是否还没有输出任何内容（代码块开头的空行不保留）
+/// * `commented_line` - 最近一次输出了行尾注释的源码行号（同一行展开为多行时注释只输出一次）
+struct Formatter<'a> {
+    lines: Vec<&'a str>,
+    blank_lines: &'a [usize],
+    code: Vec<&'a str>,
+    continued_lines: Vec<usize>,
+    out: String,
+    next_line: usize,
+    pending_blank: bool,
+    block_empty: bool,
+    commented_line: usize,
+}
+
+impl Formatter<'_> {
+    /// 以指定的缩进输出一行（需要时先输出一个空行）
+    fn line(&mut self, indent: usize, text: &str) {
+        self.raw_line(&format!("{}{}", INDENT.repeat(indent), text));
+    }
+
+    /// 原样输出一行（需要时先输出一个空行）
+    fn raw_line(&mut self, text: &str) {
+        if self.pending_blank {
+            self.out.push('\n');
+            self.pending_blank = false;
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.block_empty = false;
+    }
+
+    /// 处理第`line_no`行之前尚未处理的空行和注释行
+    /// 
+    /// # 参数
+    /// * `line_no` - 下一条语句（或右花括号）所在的行号
+    /// * `indent` - 注释行的缩进级别
+    fn trivia_until(&mut self, line_no: usize, indent: usize) {
+        while self.next_line < line_no {
+            let original = self.lines.get(self.next_line - 1).copied().unwrap_or("");
+            let text = original.trim();
+            if self.continued_lines.contains(&self.next_line) {
+                // 多行块注释的后续各行（包括其中的空行）保留原样
+                self.raw_line(original.trim_end());
+            } else if text.is_empty() {
+                // 只保留解析器记录的空行（连续多个空行中的第一行）；
+                // 代码块开头的空行不保留，结尾的空行在输出右花括号或到达文件末尾时丢弃
+                if self.blank_lines.binary_search(&self.next_line).is_ok() {
+                    self.pending_blank = !self.block_empty;
+                }
+            } else if is_trivia(self.code[self.next_line - 1]) || (self.next_line == 1 && text.starts_with("#!")) {
+                self.line(indent, text);
+            }
+            self.next_line += 1;
+        }
+    }
+
+    /// 输出一条语句（函数定义包括其函数体和右花括号）
+    fn stmt(&mut self, stmt: &Stmt, indent: usize) {
+        let line_no = stmt.span().start.line;
+        self.trivia_until(line_no, indent);
+        self.next_line = line_no + 1;
+
+        let code = match stmt {
+            Stmt::Print { args, .. } => format!("print({})", self.args(args)),
+            Stmt::Assign { name, decl_mut, expr, .. } => {
+                let sigil = if *decl_mut { "$" } else { "" };
+                format!("{sigil}{name} = {}", self.expr(expr))
+            }
+            Stmt::Const { name, expr, .. } => format!("const {name} = {}", self.expr(expr)),
+            Stmt::Return { expr: Some(expr), .. } => format!("return {}", self.expr(expr)),
+            Stmt::Return { expr: None, .. } => "return".to_string(),
+            Stmt::Expr { expr, .. } => self.expr(expr),
+            // 有语法错误时不会格式化，保留原文
+            Stmt::Invalid { span, .. } => self.source_text(*span),
+            Stmt::FnDef { name, params, ret, body, span, .. } => {
+                self.fn_def(name, params, ret.as_deref(), body, *span, indent);
+                return;
+            }
+        };
+        let text = self.with_comment(code, line_no);
+        self.line(indent, &text);
+    }
+
+    /// 在一行代码之后加上源码中该行的行尾注释
+    /// 
+    /// # 参数
+    /// * `code` - 格式化后的代码
+    /// * `line_no` - 代码所在的源码行号
+    /// 
+    /// # 说明
+    /// 写在同一行的函数体展开为多行时，行尾注释只跟在函数头之后
+    fn with_comment(&mut self, code: String, line_no: usize) -> String {
+        let line = self.lines.get(line_no - 1).copied().unwrap_or("");
+        match parser::split_comment(line).1 {
+            Some(comment) if self.commented_line != line_no => {
+                self.commented_line = line_no;
+                format!("{code} {comment}")
+            }
+            _ => code,
+        }
+    }
+
+    /// 输出函数定义
+    /// 
+    /// # 参数
+    /// * `span` - 函数头的源码位置
+    /// * `indent` - 函数头的缩进级别
+    /// 
+    /// # 说明
+    /// 函数体写在同一行时没有单独的右花括号行；
+    /// 否则右花括号是从最后一条语句所在的行开始第一个单独的 `}`（可以以分号与其他语句分隔），
+    /// 其间的注释属于函数体
+    fn fn_def(&mut self, name: &str, params: &[Param], ret: Option<&str>, body: &[Stmt], span: SourceSpan, indent: usize) {
+        let line_no = span.start.line;
+        let params: Vec<String> = params
+            .iter()
+            .map(|param| match &param.ty {
+                Some(ty) => format!("{}: {ty}", param.name),
+                None => param.name.clone(),
+            })
+            .collect();
+        let ret = ret.map(|ty| format!(" -> {ty}")).unwrap_or_default();
+        let header = format!("fun {name}({}){ret} {{", params.join(", "));
+
+        // 函数定义的源码范围只到函数头（或同一行的函数体）结束，之后可能还有以分号分隔的其他语句
+        let inline = self.source_text(span).ends_with('}');
+        let close_line = if inline {
+            None
+        } else {
+            // 右花括号可以与最后一条语句写在同一行（如 `return x; }`）
+            let after_body = body.last().map_or(line_no + 1, |stmt| stmt.span().start.line);
+            (after_body..=self.code.len()).find(|&n| {
+                parser::split_statements(parser::split_comment(self.code[n - 1]).0)
+                    .iter()
+                    .any(|segment| segment.trim() == "}")
+            })
+        };
+        // 函数体为空时函数头与右花括号之间的非空行都是注释；
+        // 右花括号的行尾注释同样需要保留单独的右花括号行
+        let has_comments = close_line.is_some_and(|close| {
+            (line_no + 1..close).any(|n| !self.lines[n - 1].trim().is_empty())
+                || parser::split_comment(self.lines[close - 1]).1.is_some()
+        });
+
+        if body.is_empty() && !has_comments {
+            let text = self.with_comment(format!("{header}}}"), line_no);
+            self.line(indent, &text);
+        } else {
+            let text = self.with_comment(header, line_no);
+            self.line(indent, &text);
+            self.block_empty = true;
+            for stmt in body {
+                self.stmt(stmt, indent + 1);
+            }
+            if let Some(close) = close_line {
+                self.trivia_until(close, indent + 1);
+            }
+            // 右花括号之前的空行不保留
+            self.pending_blank = false;
+            let text = match close_line {
+                Some(close) => self.with_comment("}".to_string(), close),
+                None => "}".to_string(),
+            };
+            self.line(indent, &text);
+        }
+        if let Some(close) = close_line {
+            self.next_line = close + 1;
+        }
+    }
+
+    /// 输出以逗号分隔的参数列表
+    fn args(&self, args: &[Expr]) -> String {
+        args.iter().map(|arg| self.expr(arg)).collect::<Vec<_>>().join(", ")
+    }
+
+    /// 输出表达式
+    /// 
+    /// # 说明
+    /// 括号分组在抽象语法树中有对应的节点，因此按原样输出即可保持运算顺序不变
+    fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            // 字面量保留源码中的写法；数字字面量的负号与数字之间的空白删除
+            Expr::StringLit(_, span) => self.source_text(*span),
+            Expr::IntLit(_, span) | Expr::FloatLit(_, span) => {
+                self.source_text(*span).chars().filter(|c| !c.is_whitespace()).collect()
+            }
+            Expr::BoolLit(value, _) => value.to_string(),
+            Expr::Ident(name, _) => name.clone(),
+            Expr::BinaryAdd(a, b, _) => format!("{} + {}", self.expr(a), self.expr(b)),
+            Expr::BinarySub(a, b, _) => format!("{} - {}", self.expr(a), self.expr(b)),
+            Expr::BinaryMul(a, b, _) => format!("{} * {}", self.expr(a), self.expr(b)),
+            Expr::BinaryDiv(a, b, _) => format!("{} / {}", self.expr(a), self.expr(b)),
+            Expr::BinaryMod(a, b, _) => format!("{} % {}", self.expr(a), self.expr(b)),
+            Expr::Compare(op, a, b, _) => format!("{} {} {}", self.expr(a), op.symbol(), self.expr(b)),
+            Expr::Group(inner, _) => format!("({})", self.expr(inner)),
+            Expr::TypeOf(inner, _) => format!("typeof({})", self.expr(inner)),
+            Expr::Neg(inner, _) => match self.expr(inner) {
+                // 连续的负号之间保留空格（`- -x` 而不是 `--x`）
+                inner if inner.starts_with('-') => format!("- {inner}"),
+                inner => format!("-{inner}"),
+            },
+            Expr::Call(name, args, _) => format!("{name}({})", self.args(args)),
+            Expr::Read(kind, _) => format!("{}()", kind.name()),
+        }
+    }
+
+    /// 取出源码范围对应的原文（单行）
+    fn source_text(&self, span: SourceSpan) -> String {
+        let line = self.lines.get(span.start.line - 1).copied().unwrap_or("");
+        line.chars().skip(span.start.col - 1).take(span.end.col - span.start.col).collect()
+    }
+}
+
+/// 判断块注释替换为空白后的一行是否只有注释（或什么都没有）
+fn is_trivia(code: &str) -> bool {
+    let code = code.trim();
+    code.is_empty() || code.starts_with("//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 格式化源代码（源代码必须没有语法错误）
+    fn fmt(source: &str) -> String {
+        format_source(source, Path::new("test.kr")).expect("source should format")
+    }
+
+    #[test]
+    fn normalizes_spacing_and_sigil() {
+        assert_eq!(fmt("$ x=1+2*3\nprint( x,\"a\" )\n"), "$x = 1 + 2 * 3\nprint(x, \"a\")\n");
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let source = "//!kairo release\n\n\nx=1 // one\n  // note\n\n\ny = x+2; print(y)\nfn f(a) {\n\n  return a*2\n\n}\nprint(f(y))\n\n";
+        let once = fmt(source);
+        assert_eq!(fmt(&once), once);
+    }
+
+    #[test]
+    fn keeps_one_blank_line_between_statements() {
+        assert_eq!(fmt("x = 1\n\n\n\ny = 2\n"), "x = 1\n\ny = 2\n");
+        assert_eq!(fmt("x = 1\ny = 2\n"), "x = 1\ny = 2\n");
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+
+use crate::compiler::ast::{CompareOp, Expr, Program, ReadKind, SourceSpan, Stmt};
+use crate::compiler::semantics::format::is_format_print;
+use crate::compiler::semantics::{SemanticInfo, Type};
+
+/// 交互式解释器模块
+/// 逐行读取输入，累积语义信息并立即执行
+pub mod repl;
+
+/// 函数调用的默认最大嵌套深度
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+/// 每次执行默认最多执行的语句条数
+pub const DEFAULT_MAX_STEPS: u64 = 10_000_000;
+
+/// 解释器的执行上限（超过时报告运行时错误）
+/// 
+/// # 字段
+/// * `max_call_depth` - 函数调用的最大嵌套深度（超过时报告错误，而不是让解释器自身栈溢出）
+/// * `max_steps` - 每次执行（交互式解释器中为每次输入）最多执行的语句条数，
+///   防止多次递归调用自身的函数（如 `fun f() { f() f() }`）在达到嵌套深度之前长时间运行
+/// 
+/// # 说明
+/// 通过命令行执行时由合并后的设置决定（`Config::max_call_depth`、`Config::max_steps`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_call_depth: usize,
+    pub max_steps: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { max_call_depth: DEFAULT_MAX_CALL_DEPTH, max_steps: DEFAULT_MAX_STEPS }
+    }
+}
+
+/// 运行时的值
+/// 
+/// # 变体
+/// * `Int` - 整数（i64）
+/// * `Float` - 浮点数（f64）
+/// * `Str` - 字符串
+/// * `Bool` - 布尔值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// 整数
+    Int(i64),
+    /// 浮点数
+    Float(f64),
+    /// 字符串
+    Str(String),
+    /// 布尔值
+    Bool(bool),
+}
+
+impl Value {
+    /// 获取值的类型
+    pub fn ty(&self) -> Type {
+        match self {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Str(_) => Type::Str,
+            Value::Bool(_) => Type::Bool,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// 与生成的Rust代码中 `println!("{}", 值)` 的输出一致
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{v}"),
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Str(s) => f.write_str(s),
+            Value::Bool(v) => write!(f, "{v}"),
+        }
+    }
+}
+
+/// 用户定义的函数
+/// 
+/// # 字段
+/// * `params` - 参数名列表
+/// * `body` - 函数体中的语句
+#[derive(Debug)]
+struct Function {
+    params: Vec<String>,
+    body: Vec<Stmt>,
+}
+
+/// 语句执行后的控制流
+enum Flow {
+    /// 继续执行下一条语句
+    Next,
+    /// 遇到return语句，带有返回值（如果有）
+    Return(Option<Value>),
+}
+
+/// 树遍历解释器
+/// 
+/// 直接遍历抽象语法树执行程序，不生成也不编译Rust代码；
+/// 行为与默认选项（`--overflow=check`）生成的Rust代码一致，整数溢出和除以0会报告运行时错误
+/// 
+/// # 字段
+/// * `out` - print语句的输出目标
+/// * `globals` - 顶层变量的当前值
+/// * `consts` - 顶层常量的值（函数体中也可见）
+/// * `functions` - 已定义的函数
+/// * `depth` - 当前函数调用的嵌套深度
+/// * `steps` - 本次执行已执行的语句条数
+/// * `limits` - 执行上限
+pub struct Interpreter<W: Write> {
+    out: W,
+    globals: HashMap<String, Value>,
+    consts: HashMap<String, Value>,
+    functions: HashMap<String, Rc<Function>>,
+    depth: usize,
+    steps: u64,
+    limits: Limits,
+}
+
+impl<W: Write> Interpreter<W> {
+    /// 创建解释器
+    /// 
+    /// # 参数
+    /// * `out` - print语句的输出目标（如标准输出）
+    /// * `limits` - 执行上限
+    pub fn new(out: W, limits: Limits) -> Self {
+        Interpreter {
+            out,
+            globals: HashMap::new(),
+            consts: HashMap::new(),
+            functions: HashMap::new(),
+            depth: 0,
+            steps: 0,
+            limits,
+        }
+    }
+
+    /// 执行整个程序
+    /// 
+    /// # 参数
+    /// * `program` - 已通过语义检查的程序
+    /// * `semantic` - 程序的语义分析信息
+    /// 
+    /// # 返回值
+    /// * `Result<()>` - 执行成功返回Ok(())，遇到运行时错误时返回错误信息
+    pub fn run(&mut self, program: &Program, semantic: &SemanticInfo) -> Result<()> {
+        self.run_statements(&program.statements, semantic).map(|_| ())
+    }
+
+    /// 在已有的变量和函数的基础上执行一组顶层语句
+    /// 
+    /// # 参数
+    /// * `stmts` - 要执行的顶层语句
+    /// * `semantic` - 包含这些语句在内的整个程序的语义分析信息
+    /// 
+    /// # 返回值
+    /// * `Result<Option<Value>>` - 最后一条语句是有返回值的函数调用语句时返回该值，否则返回None
+    /// 
+    /// # 说明
+    /// 函数可以在定义之前调用，因此先登记全部函数定义再执行。
+    /// 出现运行时错误时，已执行的语句产生的变量仍会保留。
+    /// 语句条数的上限（`Limits::max_steps`）对每次调用分别计算
+    pub fn run_statements(&mut self, stmts: &[Stmt], semantic: &SemanticInfo) -> Result<Option<Value>> {
+        self.steps = 0;
+        for stmt in stmts {
+            if let Stmt::FnDef { name, params, body, .. } = stmt {
+                let params = params.iter().map(|param| param.name.clone()).collect();
+                self.functions.insert(name.clone(), Rc::new(Function { params, body: body.clone() }));
+            }
+        }
+
+        let mut globals = std::mem::take(&mut self.globals);
+        let mut last = None;
+        let mut result = Ok(());
+        for stmt in stmts {
+            match self.exec(stmt, semantic, semantic, &mut globals, true) {
+                Ok(Flow::Return(value)) => last = value,
+                Ok(Flow::Next) => last = None,
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        self.globals = globals;
+        result.map(|_| last)
+    }
+
+    /// 执行函数体中的语句列表
+    /// 
+    /// # 返回值
+    /// * `Result<Option<Value>>` - 遇到带值的return时返回该值
+    fn exec_body(
+        &mut self,
+        stmts: &[Stmt],
+        root: &SemanticInfo,
+        local: &SemanticInfo,
+        vars: &mut HashMap<String, Value>,
+    ) -> Result<Option<Value>> {
+        for stmt in stmts {
+            if let Flow::Return(value) = self.exec(stmt, root, local, vars, false)? {
+                return Ok(value);
+            }
+        }
+        Ok(None)
+    }
+
+    /// 执行单条语句
+    /// 
+    /// # 参数
+    /// * `stmt` - 要执行的语句
+    /// * `root` - 整个程序的语义分析信息（用于查找函数体的符号表）
+    /// * `local` - 当前代码块的语义分析信息（用于typeof）
+    /// * `vars` - 当前代码块中变量的值
+    /// * `top_level` - 是否为顶层语句（顶层的常量在函数体中也可见）
+    /// 
+    /// # 返回值
+    /// * `Result<Flow>` - 是否继续执行下一条语句；
+    ///   顶层的函数调用语句以`Flow::Return`传回返回值，供交互式解释器显示
+    fn exec(
+        &mut self,
+        stmt: &Stmt,
+        root: &SemanticInfo,
+        local: &SemanticInfo,
+        vars: &mut HashMap<String, Value>,
+        top_level: bool,
+    ) -> Result<Flow> {
+        self.steps += 1;
+        if self.steps > self.limits.max_steps {
+            let message = format!(
+                "执行的语句超过了 {} 条的上限（程序可能无限递归；可以用 --max-steps 调整上限）",
+                self.limits.max_steps
+            );
+            return Err(runtime_error(&message, stmt.span()));
+        }
+        match stmt {
+            Stmt::Print { args, .. } => {
+                let line = self.format_print(args, root, local, vars)?;
+                writeln!(self.out, "{line}").context("failed to write program output")?;
+            }
+            Stmt::Assign { name, expr, .. } => {
+                let value = self.eval(expr, root, local, vars)?;
+                vars.insert(name.clone(), value);
+            }
+            Stmt::Const { name, expr, .. } => {
+                let value = self.eval(expr, root, local, vars)?;
+                if top_level {
+                    self.consts.insert(name.clone(), value);
+                } else {
+                    vars.insert(name.clone(), value);
+                }
+            }
+            Stmt::FnDef { .. } | Stmt::Invalid { .. } => {
+                // 函数定义已在执行前登记；无法解析的语句不会通过语义分析，不会被执行
+            }
+            Stmt::Return { expr, .. } => {
+                let value = match expr {
+                    Some(expr) => Some(self.eval(expr, root, local, vars)?),
+                    None => None,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::Expr { expr, .. } => {
+                let value = self.eval(expr, root, local, vars)?;
+                // 顶层的函数调用语句传回返回值，供交互式解释器显示（没有返回值的函数除外）
+                let returns_value = match expr {
+                    Expr::Call(name, _, _) => root.functions.get(name).is_some_and(|sig| sig.returns_value),
+                    _ => true,
+                };
+                if top_level && returns_value {
+                    return Ok(Flow::Return(Some(value)));
+                }
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    /// 计算表达式的值
+    /// 
+    /// # 参数
+    /// * `expr` - 要计算的表达式
+    /// * `root` - 整个程序的语义分析信息
+    /// * `local` - 当前代码块的语义分析信息
+    /// * `vars` - 当前代码块中变量的值
+    /// 
+    /// # 返回值
+    /// * `Result<Value>` - 表达式的值；整数溢出、除以0等情况返回运行时错误
+    /// 
+    /// # 说明
+    /// 没有返回值的函数调用只能作为单独的语句出现（由语义分析保证），
+    /// 此时返回值为占位的 `Value::Bool(false)`，不会被使用
+    fn eval(
+        &mut self,
+        expr: &Expr,
+        root: &SemanticInfo,
+        local: &SemanticInfo,
+        vars: &HashMap<String, Value>,
+    ) -> Result<Value> {
+        match expr {
+            Expr::StringLit(s, _) => Ok(Value::Str(s.clone())),
+            Expr::IntLit(v, _) => Ok(Value::Int(*v)),
+            Expr::FloatLit(v, _) => Ok(Value::Float(*v)),
+            Expr::BoolLit(v, _) => Ok(Value::Bool(*v)),
+            Expr::Ident(name, span) => self.lookup(name, vars, *span),
+            Expr::Group(inner, _) => self.eval(inner, root, local, vars),
+            Expr::TypeOf(inner, _) => {
+                // 与生成的代码一致：在编译期确定类型，不计算操作数
+                let name = local.type_of(inner).map_or("unknown".to_string(), |ty| ty.to_string());
+                Ok(Value::Str(name))
+            }
+            Expr::Neg(inner, span) => match self.eval(inner, root, local, vars)? {
+                Value::Int(v) => v.checked_neg().map(Value::Int).ok_or_else(|| runtime_error("整数取负溢出", *span)),
+                Value::Float(v) => Ok(Value::Float(-v)),
+                other => Err(runtime_error(&format!("{} 不能取负", other.ty()), *span)),
+            },
+            Expr::BinaryAdd(a, b, span)
+            | Expr::BinarySub(a, b, span)
+            | Expr::BinaryMul(a, b, span)
+            | Expr::BinaryDiv(a, b, span)
+            | Expr::BinaryMod(a, b, span) => {
+                let lhs = self.eval(a, root, local, vars)?;
+                let rhs = self.eval(b, root, local, vars)?;
+                arith(expr, lhs, rhs, *span)
+            }
+            Expr::Compare(op, a, b, span) => {
+                let lhs = self.eval(a, root, local, vars)?;
+                let rhs = self.eval(b, root, local, vars)?;
+                compare(*op, &lhs, &rhs, *span).map(Value::Bool)
+            }
+            Expr::Call(name, args, span) => {
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args {
+                    values.push(self.eval(arg, root, local, vars)?);
+                }
+                self.call(name, values, root, *span)
+            }
+            Expr::Read(kind, span) => {
+                let input = self.read_line()?;
+                match kind {
+                    ReadKind::Line => Ok(Value::Str(input)),
+                    ReadKind::Int => match input.trim().parse() {
+                        Ok(v) => Ok(Value::Int(v)),
+                        Err(_) => Err(runtime_error(&format!("输入的 `{input}` 不是整数"), *span)),
+                    },
+                }
+            }
+        }
+    }
+
+    /// 从标准输入读取一行，去除末尾的换行符（输入结束时为空字符串）
+    /// 
+    /// # 说明
+    /// 读取前先刷新输出，保证提示文字出现在等待输入之前
+    fn read_line(&mut self) -> Result<String> {
+        self.out.flush().context("failed to write program output")?;
+        let mut line = String::new();
+        std::io::stdin().lock().read_line(&mut line).context("failed to read input")?;
+        line.truncate(line.trim_end_matches(['\n', '\r']).len());
+        Ok(line)
+    }
+
+    /// 调用用户定义的函数
+    /// 
+    /// # 参数
+    /// * `name` - 函数名
+    /// * `args` - 实参的值
+    /// * `root` - 整个程序的语义分析信息
+    /// * `span` - 调用表达式的源码位置（用于错误报告）
+    fn call(&mut self, name: &str, args: Vec<Value>, root: &SemanticInfo, span: SourceSpan) -> Result<Value> {
+        let (Some(function), Some(body_info)) = (self.functions.get(name).cloned(), root.bodies.get(name)) else {
+            return Err(runtime_error(&format!("函数 `{name}` 未定义"), span));
+        };
+        if self.depth >= self.limits.max_call_depth {
+            let message = format!(
+                "函数调用嵌套超过了 {} 层的上限（`{name}` 可能无限递归；可以用 --max-call-depth 调整上限）",
+                self.limits.max_call_depth
+            );
+            return Err(runtime_error(&message, span));
+        }
+
+        // 参数类型为float时，整数实参提升为浮点数
+        let param_types = root.functions.get(name).map(|sig| sig.params.as_slice()).unwrap_or_default();
+        let mut frame = HashMap::new();
+        for (i, (param, value)) in function.params.iter().zip(args).enumerate() {
+            let value = match (param_types.get(i).map(|(_, ty)| *ty), value) {
+                (Some(Type::Float), Value::Int(v)) => Value::Float(v as f64),
+                (_, value) => value,
+            };
+            frame.insert(param.clone(), value);
+        }
+
+        self.depth += 1;
+        let result = self.exec_body(&function.body, root, body_info, &mut frame);
+        self.depth -= 1;
+        Ok(result?.unwrap_or(Value::Bool(false)))
+    }
+
+    /// 查找变量或常量的当前值
+    fn lookup(&self, name: &str, vars: &HashMap<String, Value>, span: SourceSpan) -> Result<Value> {
+        vars.get(name)
+            .or_else(|| self.consts.get(name))
+            .cloned()
+            .ok_or_else(|| runtime_error(&format!("变量 `{name}` 未定义"), span))
+    }
+
+    /// 生成print语句输出的一行文本（不含换行符）
+    /// 
+    /// # 说明
+    /// 与代码生成的规则一致（参见`gen_print_args`）：格式字符串形式时第一个参数是格式字符串，
+    /// 其余参数依次填入 `{}`；否则各参数以空格分隔，字符串参数中的 `{变量名}` 替换为变量的值
+    fn format_print(
+        &mut self,
+        args: &[Expr],
+        root: &SemanticInfo,
+        local: &SemanticInfo,
+        vars: &HashMap<String, Value>,
+    ) -> Result<String> {
+        let format_print = is_format_print(args);
+        let mut pieces: Vec<String> = Vec::new();
+        let mut positional: Vec<Value> = Vec::new();
+        let mut templates: Vec<(usize, &str, SourceSpan)> = Vec::new();
+
+        for (i, arg) in args.iter().enumerate() {
+            match arg {
+                Expr::StringLit(content, span) if i == 0 || !format_print => {
+                    templates.push((pieces.len(), content, *span));
+                    pieces.push(String::new());
+                }
+                _ if format_print => positional.push(self.eval(arg, root, local, vars)?),
+                _ => pieces.push(self.eval(arg, root, local, vars)?.to_string()),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        for (index, template, span) in templates {
+            pieces[index] = format_template(template, &mut positional, |name| self.lookup(name, vars, span).ok(), span)?;
+        }
+
+        if format_print {
+            Ok(pieces.remove(0))
+        } else {
+            Ok(pieces.join(" "))
+        }
+    }
+}
+
+/// 生成运行时错误
+/// 
+/// # 参数
+/// * `message` - 错误描述
+/// * `span` - 出错的表达式的源码位置
+fn runtime_error(message: &str, span: SourceSpan) -> anyhow::Error {
+    anyhow::anyhow!("运行时错误：{message}（第 {} 行，第 {} 列）", span.start.line, span.start.col)
+}
+
+/// 计算二元算术运算
+/// 
+/// # 规则
+/// 1. 两侧都是整数时为整数运算（除法为整数除法），溢出或除以0时报告运行时错误
+/// 2. 整数与浮点数混合时，整数提升为浮点数
+/// 3. 两个字符串相加时拼接
+fn arith(expr: &Expr, lhs: Value, rhs: Value, span: SourceSpan) -> Result<Value> {
+    match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => {
+            let (result, what) = match expr {
+                Expr::BinaryAdd(..) => (a.checked_add(b), "整数加法溢出"),
+                Expr::BinarySub(..) => (a.checked_sub(b), "整数减法溢出"),
+                Expr::BinaryMul(..) => (a.checked_mul(b), "整数乘法溢出"),
+                Expr::BinaryDiv(..) if b == 0 => (None, "整数除以0"),
+                Expr::BinaryDiv(..) => (a.checked_div(b), "整数除法溢出"),
+                Expr::BinaryMod(..) if b == 0 => (None, "整数对0取余"),
+                _ => (a.checked_rem(b), "整数取余溢出"),
+            };
+            result.map(Value::Int).ok_or_else(|| runtime_error(what, span))
+        }
+        (Value::Str(a), Value::Str(b)) if matches!(expr, Expr::BinaryAdd(..)) => Ok(Value::Str(a + &b)),
+        (lhs, rhs) => {
+            let (Some(a), Some(b)) = (as_float(&lhs), as_float(&rhs)) else {
+                return Err(runtime_error(&format!("{} 和 {} 不能进行算术运算", lhs.ty(), rhs.ty()), span));
+            };
+            let result = match expr {
+                Expr::BinaryAdd(..) => a + b,
+                Expr::BinarySub(..) => a - b,
+                Expr::BinaryMul(..) => a * b,
+                Expr::BinaryDiv(..) => a / b,
+                _ => a % b,
+            };
+            Ok(Value::Float(result))
+        }
+    }
+}
+
+/// 获取数值的浮点数形式（整数提升为浮点数，其他类型返回None）
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(v) => Some(*v as f64),
+        Value::Float(v) => Some(*v),
+        _ => None,
+    }
+}
+
+/// 计算比较运算
+/// 
+/// # 规则
+/// 整数与浮点数比较时整数提升为浮点数；字符串按字典序比较；布尔值false小于true
+fn compare(op: CompareOp, lhs: &Value, rhs: &Value, span: SourceSpan) -> Result<bool> {
+    let ordering = match (lhs, rhs) {
+        (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+        (Value::Str(a), Value::Str(b)) => a.partial_cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+        _ => match (as_float(lhs), as_float(rhs)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => return Err(runtime_error(&format!("{} 和 {} 不能比较", lhs.ty(), rhs.ty()), span)),
+        },
+    };
+    // NaN与任何值比较时只有 `!=` 成立
+    let Some(ordering) = ordering else {
+        return Ok(op == CompareOp::Ne);
+    };
+    Ok(match op {
+        CompareOp::Lt => ordering.is_lt(),
+        CompareOp::Le => ordering.is_le(),
+        CompareOp::Gt => ordering.is_gt(),
+        CompareOp::Ge => ordering.is_ge(),
+        CompareOp::Eq => ordering.is_eq(),
+        CompareOp::Ne => ordering.is_ne(),
+    })
+}
+
+/// 按格式字符串生成文本
+/// 
+/// # 参数
+/// * `template` - 格式字符串（print的字符串内容）
+/// * `positional` - 依次填入 `{}` 的值
+/// * `lookup` - 查找 `{变量名}` 引用的变量的值
+/// * `span` - 字符串字面量的源码位置（用于错误报告）
+/// 
+/// # 说明
+/// 格式字符串已通过语义检查（花括号配对、变量已声明、`{}` 的个数与参数一致）
+fn format_template(
+    template: &str,
+    positional: &mut impl Iterator<Item = Value>,
+    lookup: impl Fn(&str) -> Option<Value>,
+    span: SourceSpan,
+) -> Result<String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let inner: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                let (name, spec) = inner.split_once(':').unwrap_or((&inner, ""));
+                let name = name.trim();
+                let value = if name.is_empty() { positional.next() } else { lookup(name) };
+                let Some(value) = value else {
+                    return Err(runtime_error(&format!("格式字符串中的占位符 `{{{inner}}}` 没有对应的值"), span));
+                };
+                out.push_str(&apply_spec(&value, spec, span)?);
+            }
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+/// 按格式说明（如 `>5`、`.2`、`08.3`）格式化单个值
+/// 
+/// # 支持的格式说明
+/// `[[填充字符]对齐方式][+][0][宽度][.精度]`，对齐方式为 `<` `^` `>`；
+/// 其余Rust格式说明（如 `?`、`x`、`e`、`#`）不支持，会报告运行时错误
+fn apply_spec(value: &Value, spec: &str, span: SourceSpan) -> Result<String> {
+    if spec.is_empty() {
+        return Ok(value.to_string());
+    }
+    let unsupported = || runtime_error(&format!("解释器不支持格式说明 `{{:{spec}}}`"), span);
+
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+    let is_align = |c: char| matches!(c, '<' | '^' | '>');
+    let (fill, align) = match (chars.first(), chars.get(1)) {
+        (Some(&f), Some(&a)) if is_align(a) => {
+            i = 2;
+            (f, Some(a))
+        }
+        (Some(&a), _) if is_align(a) => {
+            i = 1;
+            (' ', Some(a))
+        }
+        _ => (' ', None),
+    };
+    let plus = chars.get(i) == Some(&'+');
+    if plus {
+        i += 1;
+    }
+    let zero = chars.get(i) == Some(&'0');
+    if zero {
+        i += 1;
+    }
+    let rest: String = chars[i..].iter().collect();
+    let (width, precision) = match rest.split_once('.') {
+        Some((width, precision)) => (width, Some(precision.parse::<usize>().map_err(|_| unsupported())?)),
+        None => (rest.as_str(), None),
+    };
+    let width = if width.is_empty() { 0 } else { width.parse::<usize>().map_err(|_| unsupported())? };
+
+    let numeric = matches!(value, Value::Int(_) | Value::Float(_));
+    let mut text = match (value, precision) {
+        (Value::Float(v), Some(p)) => format!("{v:.p$}"),
+        (Value::Str(s), Some(p)) => s.chars().take(p).collect(),
+        _ => value.to_string(),
+    };
+    if plus && numeric && !text.starts_with('-') {
+        text.insert(0, '+');
+    }
+
+    let len = text.chars().count();
+    if len >= width {
+        return Ok(text);
+    }
+    let padding = width - len;
+    if zero && numeric {
+        // 补0时0放在符号之后
+        let sign_len = usize::from(text.starts_with(['+', '-']));
+        text.insert_str(sign_len, &"0".repeat(padding));
+        return Ok(text);
+    }
+    let fill_str = |n: usize| fill.to_string().repeat(n);
+    Ok(match align.unwrap_or(if numeric { '>' } else { '<' }) {
+        '<' => text + &fill_str(padding),
+        '^' => fill_str(padding / 2) + &text + &fill_str(padding - padding / 2),
+        _ => fill_str(padding) + &text,
+    })
+}
@@ -0,0 +1,116 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::compiler::ast::{Program, Stmt};
+use crate::compiler::parser::{self, expr::parse_expr, stmt::parse_fn_header};
+use crate::compiler::semantics::{check_semantics, diagnostics::Diagnostic, SemanticOptions};
+use super::{Interpreter, Limits, Value};
+
+/// 交互式会话中错误信息使用的文件名
+pub const REPL_NAME: &str = "<repl>";
+
+/// 单次输入的执行结果
+/// 
+/// # 字段
+/// * `warnings` - 这次输入新产生的警告
+/// * `value` - 输入是表达式或有返回值的函数调用时，其值
+#[derive(Debug, Default)]
+pub struct Evaluation {
+    pub warnings: Vec<Diagnostic>,
+    pub value: Option<Value>,
+}
+
+/// 交互式会话
+/// 
+/// 保存之前全部输入的源码和语句，每次输入时对整个会话重新做语义分析
+/// （因此之前声明的变量、常量和函数都可见），然后只执行新输入的语句
+/// 
+/// # 字段
+/// * `source` - 已接受的全部输入（用于错误信息中的行号和源码行）
+/// * `statements` - 已执行的全部语句
+/// * `warnings` - 已显示过的警告数
+/// * `options` - 语义检查选项
+/// * `interpreter` - 保存变量当前值的解释器
+pub struct ReplSession<W: Write> {
+    source: String,
+    statements: Vec<Stmt>,
+    warnings: usize,
+    options: SemanticOptions,
+    interpreter: Interpreter<W>,
+}
+
+impl<W: Write> ReplSession<W> {
+    /// 创建交互式会话
+    /// 
+    /// # 参数
+    /// * `out` - print语句的输出目标
+    /// * `options` - 语义检查选项（变量通常在之后的输入中才使用，因此不报告未使用的变量）
+    /// * `limits` - 解释器的执行上限（语句条数对每次输入分别计算）
+    pub fn new(out: W, options: SemanticOptions, limits: Limits) -> Self {
+        ReplSession {
+            source: String::new(),
+            statements: Vec::new(),
+            warnings: 0,
+            options: SemanticOptions { allow_unused: true, ..options },
+            interpreter: Interpreter::new(out, limits),
+        }
+    }
+
+    /// 判断输入是否为尚未结束的函数定义（函数体从下一行开始）
+    /// 
+    /// # 返回值
+    /// * `bool` - 返回true时应继续读取，直到单独一行的 `}` 为止，再把这些行一起交给`eval`
+    pub fn needs_more(&self, line: &str) -> bool {
+        matches!(parse_fn_header(line, 1), Ok(Some(header)) if header.inline_body.is_none())
+    }
+
+    /// 执行一次输入
+    /// 
+    /// # 参数
+    /// * `input` - 一行语句或表达式，或者一个完整的多行函数定义
+    /// 
+    /// # 返回值
+    /// * `Result<Evaluation>` - 成功返回新的警告和表达式的值；
+    ///   语法、语义或运行时错误时返回错误信息，这次输入被丢弃，会话的状态不变
+    /// 
+    /// # 说明
+    /// 无法解析为语句的单行输入会尝试解析为表达式，并打印其值
+    pub fn eval(&mut self, input: &str) -> Result<Evaluation> {
+        let first_line = self.source.lines().count() + 1;
+        let mut source = self.source.clone();
+        source.push_str(input);
+        source.push('\n');
+
+        // 在输入之前补上空行，使解析得到的行号与整个会话中的行号一致
+        let padded = format!("{}{}\n", "\n".repeat(first_line - 1), input);
+        let new_statements = match parser::parse(&padded, Path::new(REPL_NAME)) {
+            Ok(program) => program.statements,
+            Err(stmt_error) if !input.contains('\n') => match parse_expr(input, first_line, 0) {
+                Ok(expr) => {
+                    let span = expr.span();
+                    vec![Stmt::Print { args: vec![expr], span }]
+                }
+                // 两种解析都失败时，优先报告更具体的错误
+                Err(expr_error) if stmt_error.to_string().contains("无法解析第") => return Err(expr_error),
+                Err(_) => return Err(stmt_error),
+            },
+            Err(e) => return Err(e),
+        };
+
+        let mut statements = self.statements.clone();
+        statements.extend(new_statements.iter().cloned());
+        let program = Program { statements, blank_lines: Vec::new() };
+        let semantic = check_semantics(&program, Path::new(REPL_NAME), &source, &self.options)?;
+
+        let value = self.interpreter.run_statements(&new_statements, &semantic)?;
+
+        // 执行成功后才接受这次输入
+        let warnings = semantic.warnings[self.warnings.min(semantic.warnings.len())..].to_vec();
+        self.warnings = semantic.warnings.len();
+        self.source = source;
+        self.statements = program.statements;
+        Ok(Evaluation { warnings, value })
+    }
+}
@@ -0,0 +1,114 @@
+use crate::compiler::ast::{Expr, Program, Stmt};
+use crate::compiler::semantics::fold::fold_const;
+use crate::compiler::semantics::SemanticInfo;
+
+/// 一个降级步骤：就地改写已通过语义检查的程序
+///
+/// # 说明
+/// 步骤同时得到程序的语义分析信息：常量在使用处内联，
+/// 改写常量的初始值时也要改写符号表中保存的值（见`SemanticInfo::consts`）
+pub type Pass = fn(&mut Program, &mut SemanticInfo);
+
+/// 代码生成之前依次执行的步骤
+pub const PASSES: &[Pass] = &[fold_constants];
+
+/// 依次执行全部降级步骤
+///
+/// # 参数
+/// * `program` - 已通过语义检查的程序
+/// * `semantic` - 程序的语义分析信息
+///
+/// # 说明
+/// 降级后的程序与原程序的运行结果完全相同，只用于生成代码（`kairo check --dump-lowered`可以查看）；
+/// 解释器和`--emit ast`使用解析得到的原程序
+pub fn lower(program: &mut Program, semantic: &mut SemanticInfo) {
+    for pass in PASSES {
+        pass(program, semantic);
+    }
+}
+
+/// 常量折叠：把只由字面量和常量组成的表达式替换为计算结果的字面量（见`fold_const`），
+/// 如 `2 + 3 * 4` 替换为 `14`
+///
+/// # 规则
+/// 1. 每个表达式先尝试整体折叠，不能折叠时再分别折叠其子表达式
+/// 2. 函数体中的表达式按函数体的符号表折叠（与常量同名的参数遮蔽常量）
+/// 3. 符号表中的常量初始值同样折叠，使内联常量时直接得到字面量
+/// 4. print的参数折叠为字符串字面量时保留外层的括号分组：
+///    print把字符串字面量参数当作格式字符串（其中的 `{}` 是占位符），而原来的表达式是普通的值
+fn fold_constants(program: &mut Program, semantic: &mut SemanticInfo) {
+    fold_block(&mut program.statements, semantic);
+    fold_symbol_table(semantic);
+}
+
+/// 折叠代码块中各语句的表达式
+fn fold_block(stmts: &mut [Stmt], info: &SemanticInfo) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::Print { args, .. } => {
+                for arg in args {
+                    let was_string = matches!(arg, Expr::StringLit(..));
+                    fold_expr(arg, info);
+                    if !was_string && let Expr::StringLit(_, span) = arg {
+                        let span = *span;
+                        *arg = Expr::Group(Box::new(arg.clone()), span);
+                    }
+                }
+            }
+            Stmt::Assign { expr, .. } | Stmt::Const { expr, .. } | Stmt::Expr { expr, .. } => fold_expr(expr, info),
+            Stmt::Return { expr: Some(expr), .. } => fold_expr(expr, info),
+            Stmt::FnDef { name, body, .. } => {
+                if let Some(body_info) = info.bodies.get(name) {
+                    fold_block(body, body_info);
+                }
+            }
+            Stmt::Return { expr: None, .. } | Stmt::Invalid { .. } => {}
+        }
+    }
+}
+
+/// 折叠一个表达式（能整体折叠时替换为字面量，否则折叠其子表达式）
+fn fold_expr(expr: &mut Expr, info: &SemanticInfo) {
+    if let Some(folded) = fold_const(expr, info) {
+        *expr = folded;
+        return;
+    }
+    match expr {
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            fold_expr(a, info);
+            fold_expr(b, info);
+        }
+        Expr::Group(inner, _) | Expr::Neg(inner, _) => fold_expr(inner, info),
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                fold_expr(arg, info);
+            }
+        }
+        // typeof在编译期按操作数的类型替换为类型名称，不需要折叠操作数
+        Expr::TypeOf(..)
+        | Expr::Read(..)
+        | Expr::Ident(..)
+        | Expr::IntLit(..)
+        | Expr::FloatLit(..)
+        | Expr::StringLit(..)
+        | Expr::BoolLit(..) => {}
+    }
+}
+
+/// 折叠符号表（包括各函数体的符号表）中保存的常量初始值
+fn fold_symbol_table(info: &mut SemanticInfo) {
+    let folded: Vec<(String, Expr)> = info
+        .consts
+        .iter()
+        .filter_map(|(name, expr)| fold_const(expr, info).map(|value| (name.clone(), value)))
+        .collect();
+    info.consts.extend(folded);
+    for body in info.bodies.values_mut() {
+        fold_symbol_table(body);
+    }
+}
@@ -2,7 +2,7 @@
 /// 将AST转换为目标语言代码
 pub mod codegen;
 
-use std::{fs, path::{Path, PathBuf}, process::Command};
+use std::{fs, io::Write, path::{Path, PathBuf}, process::{Command, Stdio}, str::FromStr};
 
 use anyhow::{Context, Result};
 
@@ -21,73 +21,625 @@ pub mod semantics;
 #[path = "ast/mod.rs"]
 pub mod ast;
 
-use semantics::check_semantics;
+/// 配置模块
+/// 合并命令行参数、编译指示、环境变量和默认值，并记录每项设置的来源
+pub mod config;
+
+/// 格式化模块
+/// 把源代码重新输出为规范的写法（保留注释）
+pub mod formatter;
+
+/// 降级模块
+/// 在代码生成之前对抽象语法树依次执行改写步骤（如常量折叠）
+pub mod lower;
+
+/// 解释器模块
+/// 直接遍历抽象语法树执行程序，不生成Rust代码
+#[path = "interp/mod.rs"]
+pub mod interp;
+
+use ast::Program;
+use codegen::rust::CodegenOptions;
+use config::{CliOverrides, Config};
+use parser::error::SyntaxError;
+use semantics::diagnostics::{Diagnostic, Diagnostics, Reporter, Severity};
+use semantics::{check_semantics, SemanticInfo, SemanticOptions};
+
+/// 一次完整分析的结果
+/// 
+/// 供编辑器、语言服务器等工具一次性获取解析和语义分析的全部信息，
+/// 避免对同一份源码重复运行流水线
+#[derive(Debug)]
+pub struct AnalysisResult {
+    /// 解析得到的抽象语法树（有语法错误时为跳过出错语句后的部分程序，出错的语句为`Stmt::Invalid`）
+    pub program: Option<Program>,
+    /// 语义分析信息（解析或语义分析失败时为None）
+    pub semantic: Option<SemanticInfo>,
+    /// 分析过程中产生的全部诊断信息（错误和警告）
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// 对源代码执行解析和语义分析
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `file` - 源文件路径（用于错误报告）
+/// * `options` - 语义检查选项
+/// 
+/// # 返回值
+/// * `AnalysisResult` - 分析结果，尽可能多地包含已计算出的信息
+/// 
+/// # 说明
+/// 该函数不会返回错误：解析或语义分析失败时，
+/// 错误信息会放入`diagnostics`中，已成功的阶段结果仍会保留
+/// 
+/// 有语法错误时，跳过出错的语句后继续对其余语句执行语义分析（见`parser::parse_recovering`），
+/// 全部语法错误和语义错误按位置排序；此时`program`为部分程序，`semantic`为None，
+/// 也不报告警告（跳过的语句可能使变量看起来从未被读取）
+pub fn analyze(source: &str, file: &Path, options: &SemanticOptions) -> AnalysisResult {
+    let mut result = AnalysisResult { program: None, semantic: None, diagnostics: Vec::new() };
+
+    // 解析为抽象语法树
+    let (program, syntax_errors) = parser::parse_recovering(source, file);
+    if !syntax_errors.is_empty() {
+        result.diagnostics = syntax_errors.iter().map(|e| syntax_diagnostic(e, source, file)).collect();
+        if let Err(e) = check_semantics(&program, file, source, options)
+            && let Ok(errors) = e.downcast::<Diagnostics>()
+        {
+            result.diagnostics.extend(errors.0.into_iter().filter(|d| d.severity == Severity::Error));
+        }
+        result.diagnostics.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        result.program = Some(program);
+        return result;
+    }
+
+    // 执行语义分析
+    match check_semantics(&program, file, source, options) {
+        Ok(semantic) => {
+            result.diagnostics.extend(semantic.warnings.iter().cloned());
+            result.semantic = Some(semantic);
+        }
+        Err(e) => match e.downcast::<Diagnostics>() {
+            Ok(errors) => result.diagnostics.extend(errors.0),
+            Err(e) => result.diagnostics.push(Diagnostic::plain(Severity::Error, &file_name(file), None, &e.to_string())),
+        },
+    }
+    result.program = Some(program);
+    result
+}
+
+/// 把解析阶段的错误转换为诊断信息（不是`SyntaxError`时没有位置信息）
+fn syntax_diagnostic(error: &anyhow::Error, source: &str, file: &Path) -> Diagnostic {
+    match error.downcast_ref::<SyntaxError>() {
+        Some(e) => e.to_diagnostic(source, &file_name(file)),
+        None => Diagnostic::plain(Severity::Error, &file_name(file), None, &error.to_string()),
+    }
+}
+
+/// 检查编译指示的格式
+/// 
+/// # 返回值
+/// * `Result<()>` - 格式正确返回Ok(())，否则返回`Diagnostics`错误（位置信息见`syntax_diagnostic`）
+pub fn check_pragma(source: &str, file: &Path) -> Result<()> {
+    parser::parse_pragma(source).map_err(|e| Diagnostics(vec![syntax_diagnostic(&e, source, file)]))?;
+    Ok(())
+}
+
+/// 获取诊断信息中显示的文件名
+fn file_name(file: &Path) -> String {
+    file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>").to_string()
+}
+
+/// 构建产物类型
+/// 
+/// # 变体
+/// * `Exe` - 可执行文件：`<out_dir>/<stem>`（Windows上为`<stem>.exe`），也可由调用方指定路径
+/// * `Rust` - 生成的Rust代码：`<out_dir>/<stem>.rs`，也可由调用方指定目录
+/// * `Ast` - 抽象语法树的文本转储：`<out_dir>/<stem>.ast`
+/// * `Dot` - 抽象语法树的GraphViz DOT图：`<out_dir>/<stem>.dot`
+/// 
+/// 其中`<out_dir>`为合并后的设置`Config::out_dir`，默认为`target/kairo_out`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmitKind {
+    /// 可执行文件
+    Exe,
+    /// 生成的Rust代码
+    Rust,
+    /// 抽象语法树转储
+    Ast,
+    /// 抽象语法树的GraphViz DOT图
+    Dot,
+}
+
+impl FromStr for EmitKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exe" => Ok(EmitKind::Exe),
+            "rust" => Ok(EmitKind::Rust),
+            "ast" => Ok(EmitKind::Ast),
+            "dot" => Ok(EmitKind::Dot),
+            _ => Err(format!("unknown emit kind `{s}` (expected: exe, rust, ast, dot)")),
+        }
+    }
+}
+
+/// 构建产物的输出位置选项
+/// 
+/// 输出目录本身是合并后的设置之一（见`CliOverrides::out_dir`）
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// 可执行文件的输出路径（None表示`<out_dir>/<stem>`）
+    pub exe: Option<PathBuf>,
+    /// 生成的Rust代码的输出目录（None表示`out_dir`）
+    pub rust_dir: Option<PathBuf>,
+    /// rustc编译成功后是否保留作为其输入的.rs文件
+    /// 
+    /// 只影响为编译可执行文件而生成的中间文件；
+    /// 通过`--emit rust`请求的.rs文件是构建产物，总是保留
+    pub keep_temps: bool,
+}
 
 /// 将.kr源文件编译为可执行文件（Windows上为.exe）
 /// 
 /// # 参数
 /// * `src_path` - 源文件路径
-/// * `release` - 是否使用发布模式（优化）
+/// * `overrides` - 命令行参数中指定的设置（发布模式、整数溢出的处理方式），未指定的由编译指示或默认值决定
+/// * `output` - 构建产物的输出位置选项
+/// * `semantic_options` - 语义检查选项
+/// * `reporter` - 语义警告的报告目标
 /// 
 /// # 返回值
 /// * `Result<PathBuf>` - 成功返回可执行文件路径，失败返回错误信息
+pub fn compile_file_to_exe(
+    src_path: &Path,
+    overrides: &CliOverrides,
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    reporter: &Reporter,
+) -> Result<PathBuf> {
+    let outputs = compile_file(src_path, overrides, &[EmitKind::Exe], output, semantic_options, &CodegenOptions::default(), reporter)?;
+    Ok(outputs.into_iter().next().expect("exe output requested"))
+}
+
+/// 编译.kr源文件并生成指定的构建产物
+/// 
+/// # 参数
+/// * `src_path` - 源文件路径
+/// * 其余参数与`compile_source`相同
+/// 
+/// # 返回值
+/// * `Result<Vec<PathBuf>>` - 成功返回各构建产物的路径（与`emit`顺序一致），失败返回错误信息
+pub fn compile_file(
+    src_path: &Path,
+    overrides: &CliOverrides,
+    emit: &[EmitKind],
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<Vec<PathBuf>> {
+    let source = read_source(src_path)?;
+    compile_source(&source, src_path, overrides, emit, output, semantic_options, codegen_options, reporter)
+}
+
+/// 编译已读取的源代码并生成指定的构建产物
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `name` - 源文件的路径或显示名称（如 `<stdin>`），用于错误报告和构建产物的文件名
+/// * `overrides` - 命令行参数中指定的设置（发布模式、整数溢出的处理方式），未指定的由编译指示或默认值决定
+/// * `emit` - 需要生成的构建产物列表
+/// * `output` - 构建产物的输出位置选项
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `reporter` - 语义警告的报告目标（警告不影响编译）
+/// 
+/// # 返回值
+/// * `Result<Vec<PathBuf>>` - 成功返回各构建产物的路径（与`emit`顺序一致），失败返回错误信息
 /// 
 /// # 编译流程
-/// 1. 读取源文件
-/// 2. 解析为抽象语法树
-/// 3. 执行语义分析
-/// 4. 生成Rust代码
-/// 5. 调用rustc编译为可执行文件
-pub fn compile_file_to_exe(src_path: &Path, release: bool) -> Result<PathBuf> {
-    // 读取源文件内容
-    let source = fs::read_to_string(src_path)
-        .with_context(|| format!("failed to read source: {}", src_path.display()))?;
+/// 1. 解析编译指示
+/// 2. 解析为抽象语法树并执行语义分析（见`analyze`）
+/// 3. 生成Rust代码（可选用rustfmt格式化）
+/// 4. 调用rustc编译为可执行文件（仅当需要可执行文件时），
+///    成功后删除中间的.rs文件（除非请求了`EmitKind::Rust`或`keep_temps`）
+#[allow(clippy::too_many_arguments)]
+pub fn compile_source(
+    source: &str,
+    name: &Path,
+    overrides: &CliOverrides,
+    emit: &[EmitKind],
+    output: &OutputOptions,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<Vec<PathBuf>> {
+    // 合并设置（优先级：命令行参数 > 编译指示 > 默认值）
+    check_pragma(source, name)?;
+    let config = Config::resolve(source, overrides)?;
 
-    // 解析为抽象语法树
-    let program = parser::parse(&source, src_path)?;
-    
-    // 执行语义分析
-    let semantic = check_semantics(&program, src_path, &source)?;
+    // 可执行文件必须以main为入口
+    if emit.contains(&EmitKind::Exe)
+        && let Some(entry) = codegen_options.entry.as_deref().filter(|entry| *entry != "main")
+    {
+        anyhow::bail!("entry function `{entry}` cannot be used for an executable; use `--emit rust` to generate embeddable code");
+    }
 
-    // 生成Rust代码
-    let rust_code = codegen::rust::generate_rust(&program, &semantic)?;
+    // 解析、语义分析并生成Rust代码
+    let (program, rust_code) = lower_to_rust(source, name, semantic_options, codegen_options, &config, reporter)?;
 
     // 准备输出路径
+    // 只读目录等无法写入的位置：错误信息中给出目录和替代做法（这是最常见的第一处写入）
+    let out_dir = &config.out_dir.value;
+    fs::create_dir_all(out_dir).map_err(|e| {
+        anyhow::anyhow!(
+            "cannot create output directory {}: {e}; use --out-dir to choose a writable directory, or `kairo run --interpret` to run without writing files",
+            out_dir.display()
+        )
+    })?;
+
+    let rs_path = rust_output_path(name, out_dir, output);
+    let ast_path = output_path(name, out_dir, "ast");
+    let dot_path = output_path(name, out_dir, "dot");
+    let exe_path = output.exe.clone().unwrap_or_else(|| exe_output_path(name, out_dir));
+
+    // 写入生成的Rust代码（编译可执行文件时也需要它作为rustc的输入）
+    if emit.contains(&EmitKind::Rust) || emit.contains(&EmitKind::Exe) {
+        if let Some(dir) = &output.rust_dir {
+            fs::create_dir_all(dir).with_context(|| format!("create dir: {}", dir.display()))?;
+        }
+        fs::write(&rs_path, &rust_code).with_context(|| format!("write file: {}", rs_path.display()))?;
+    }
+
+    // 写入抽象语法树转储
+    if emit.contains(&EmitKind::Ast) {
+        fs::write(&ast_path, format!("{program:#?}\n"))
+            .with_context(|| format!("write file: {}", ast_path.display()))?;
+    }
+
+    // 写入抽象语法树的DOT图
+    if emit.contains(&EmitKind::Dot) {
+        fs::write(&dot_path, ast::dot::to_dot(&program))
+            .with_context(|| format!("write file: {}", dot_path.display()))?;
+    }
+
+    // 调用rustc编译（指定了输出路径时先创建其所在目录）
+    if emit.contains(&EmitKind::Exe) {
+        if let Some(parent) = exe_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("create dir: {}", parent.display()))?;
+        }
+        let argv = rustc_args(&rs_path, &exe_path, &config);
+        let status = Command::new(&argv[0])
+            .args(&argv[1..])
+            .status()
+            .with_context(|| format!("failed to run rustc for {}", rs_path.display()))?;
+
+        if !status.success() {
+            anyhow::bail!("rustc failed to compile generated code. See above errors.");
+        }
+
+        // 只为编译而生成的.rs文件是中间文件，编译成功后默认删除
+        if !output.keep_temps && !emit.contains(&EmitKind::Rust) {
+            fs::remove_file(&rs_path).with_context(|| format!("remove file: {}", rs_path.display()))?;
+        }
+    }
+
+    let outputs = emit
+        .iter()
+        .map(|kind| match kind {
+            EmitKind::Exe => exe_path.clone(),
+            EmitKind::Rust => rs_path.clone(),
+            EmitKind::Ast => ast_path.clone(),
+            EmitKind::Dot => dot_path.clone(),
+        })
+        .collect();
+    Ok(outputs)
+}
+
+/// 将源代码转换为Rust代码（不调用rustc）
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `name` - 源文件的路径或显示名称（用于错误报告）
+/// * `overrides` - 命令行参数中指定的设置（其中的发布模式不影响生成的代码）
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `reporter` - 语义警告的报告目标
+/// 
+/// # 返回值
+/// * `Result<String>` - 成功返回生成的Rust源代码，解析或语义分析失败时返回友好的错误信息
+pub fn compile_source_to_rust(
+    source: &str,
+    name: &Path,
+    overrides: &CliOverrides,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    reporter: &Reporter,
+) -> Result<String> {
+    // 发布模式只影响rustc的参数，这里只使用合并后的整数溢出处理方式
+    check_pragma(source, name)?;
+    let config = Config::resolve(source, overrides)?;
+    lower_to_rust(source, name, semantic_options, codegen_options, &config, reporter).map(|(_, rust_code)| rust_code)
+}
+
+/// 用解释器直接执行源代码（不生成Rust代码，也不调用rustc）
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `name` - 源文件的路径或显示名称（用于错误报告）
+/// * `overrides` - 命令行参数中指定的设置（解释器的执行上限）
+/// * `semantic_options` - 语义检查选项
+/// * `reporter` - 语义警告的报告目标
+/// * `out` - print语句的输出目标
+/// 
+/// # 返回值
+/// * `Result<()>` - 执行成功返回Ok(())，解析、语义分析或运行时错误时返回友好的错误信息
+/// 
+/// # 说明
+/// 不会读写任何文件。
+/// 解释器的语义与默认选项的生成代码一致（如整数溢出报告错误），编译指示中的发布模式对其无效
+pub fn interpret_source<W: Write>(
+    source: &str,
+    name: &Path,
+    overrides: &CliOverrides,
+    semantic_options: &SemanticOptions,
+    reporter: &Reporter,
+    out: W,
+) -> Result<()> {
+    check_pragma(source, name)?;
+    let config = Config::resolve(source, overrides)?;
+    let analysis = analyze(source, name, semantic_options);
+    let (Some(program), Some(semantic)) = (analysis.program, analysis.semantic) else {
+        return Err(Diagnostics(analysis.diagnostics).into());
+    };
+    for warning in &semantic.warnings {
+        reporter.report(warning);
+    }
+    interp::Interpreter::new(out, config.limits()).run(&program, &semantic)
+}
+
+/// 读取源文件内容
+/// 
+/// # 参数
+/// * `src_path` - 源文件路径
+/// 
+/// # 返回值
+/// * `Result<String>` - 成功返回源代码字符串，失败返回带路径的错误信息
+pub fn read_source(src_path: &Path) -> Result<String> {
+    fs::read_to_string(src_path).with_context(|| format!("failed to read source: {}", src_path.display()))
+}
+
+/// 对源代码执行解析和语义分析，然后生成Rust代码
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `src_path` - 源文件路径（用于错误报告）
+/// * `semantic_options` - 语义检查选项
+/// * `codegen_options` - Rust代码生成选项
+/// * `config` - 生效的设置（整数溢出的处理方式）
+/// * `reporter` - 语义警告的报告目标
+/// 
+/// # 返回值
+/// * `Result<(Program, String)>` - 成功返回抽象语法树和生成的Rust代码
+/// 
+/// # 说明
+/// 语义警告通过`reporter`报告，不影响代码生成
+fn lower_to_rust(
+    source: &str,
+    src_path: &Path,
+    semantic_options: &SemanticOptions,
+    codegen_options: &CodegenOptions,
+    config: &Config,
+    reporter: &Reporter,
+) -> Result<(Program, String)> {
+    // 整数溢出的处理方式由合并后的设置决定（命令行参数 > 编译指示 > 默认值）
+    let codegen_options = CodegenOptions { overflow: config.overflow.value, ..codegen_options.clone() };
+
+    // 解析并执行语义分析
+    let analysis = analyze(source, src_path, semantic_options);
+    let (Some(program), Some(semantic)) = (analysis.program, analysis.semantic) else {
+        return Err(Diagnostics(analysis.diagnostics).into());
+    };
+
+    // 输出警告（不影响编译）
+    for warning in &semantic.warnings {
+        reporter.report(warning);
+    }
+
+    // 降级后生成Rust代码（返回的仍是解析得到的程序，供输出AST使用）
+    let (mut lowered, mut semantic) = (program.clone(), semantic);
+    lower::lower(&mut lowered, &mut semantic);
+    let mut rust_code = codegen::rust::generate_rust(&lowered, &semantic, &codegen_options)?;
+    if codegen_options.rustfmt {
+        match rustfmt(&rust_code) {
+            Ok(formatted) => rust_code = formatted,
+            Err(e) => eprintln!("warning: {e:#}; keeping unformatted Rust code"),
+        }
+    }
+    Ok((program, rust_code))
+}
+
+/// 用rustfmt格式化生成的Rust代码
+/// 
+/// # 参数
+/// * `code` - 生成的Rust源代码
+/// 
+/// # 返回值
+/// * `Result<String>` - 成功返回格式化后的代码；rustfmt不可用或格式化失败时返回错误
+fn rustfmt(code: &str) -> Result<String> {
+    let mut child = Command::new("rustfmt")
+        .args(["--edition", "2024", "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to run rustfmt")?;
+
+    // 写入stdin后立即关闭，rustfmt读到EOF才会开始输出
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(code.as_bytes())
+        .context("failed to write to rustfmt")?;
+
+    let output = child.wait_with_output().context("failed to run rustfmt")?;
+    if !output.status.success() {
+        anyhow::bail!("rustfmt failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    String::from_utf8(output.stdout).context("rustfmt produced invalid UTF-8")
+}
+
+/// 计算构建产物的输出路径：`<out_dir>/<stem>.<ext>`
+/// 
+/// # 参数
+/// * `src_path` - 源文件路径
+/// * `out_dir` - 生效的输出目录
+/// * `ext` - 构建产物的扩展名（如 `rs`、`ast`）
+fn output_path(src_path: &Path, out_dir: &Path, ext: &str) -> PathBuf {
+    output_dir_for(src_path, out_dir, |stem| format!("{stem}.{ext}"))
+}
+
+/// 计算生成的Rust代码的输出路径：`<rust_dir>/<stem>.rs`（默认为`<out_dir>/<stem>.rs`）
+fn rust_output_path(src_path: &Path, out_dir: &Path, output: &OutputOptions) -> PathBuf {
+    let path = output_path(src_path, out_dir, "rs");
+    match &output.rust_dir {
+        Some(dir) => dir.join(path.file_name().expect("output path has a file name")),
+        None => path,
+    }
+}
+
+/// 计算可执行文件的输出路径：`<out_dir>/<stem>`（Windows上为`<stem>.exe`）
+fn exe_output_path(src_path: &Path, out_dir: &Path) -> PathBuf {
+    output_dir_for(src_path, out_dir, |stem| {
+        if cfg!(target_os = "windows") {
+            format!("{stem}.exe")
+        } else {
+            stem.to_string()
+        }
+    })
+}
+
+/// 在输出目录下，按源文件名生成构建产物的路径
+/// 
+/// # 说明
+/// 显示名称中的尖括号会被去掉，因此从标准输入读取的`<stdin>`对应`stdin.rs`等文件
+fn output_dir_for(src_path: &Path, out_dir: &Path, file_name: impl FnOnce(&str) -> String) -> PathBuf {
     let file_stem = src_path
         .file_stem()
         .and_then(|s| s.to_str())
+        .map(|s| s.trim_start_matches('<').trim_end_matches('>'))
+        .filter(|s| !s.is_empty())
         .unwrap_or("out");
+    out_dir.join(file_name(file_stem))
+}
 
-    let out_dir = PathBuf::from("target").join("kairo_out");
-    fs::create_dir_all(&out_dir).with_context(|| format!("create dir: {}", out_dir.display()))?;
+/// 构建调用rustc的完整命令行
+/// 
+/// # 参数
+/// * `rs_path` - 生成的Rust源文件路径
+/// * `exe_path` - 可执行文件输出路径
+/// * `config` - 生效的设置（rustc程序、发布模式和Rust版本）
+/// 
+/// # 返回值
+/// * `Vec<String>` - 命令行参数，第一个元素是rustc的程序名
+fn rustc_args(rs_path: &Path, exe_path: &Path, config: &Config) -> Vec<String> {
+    let mut argv = vec![config.rustc.value.clone()];
+    if config.release.value {
+        argv.push("-O".to_string()); // 优化标志
+    }
+    argv.push(format!("--edition={}", config.edition.value));
+    argv.push("-o".to_string());
+    argv.push(exe_path.display().to_string());
+    argv.push(rs_path.display().to_string());
+    argv
+}
 
-    let rs_path = out_dir.join(format!("{file_stem}.rs"));
-    let exe_name = if cfg!(target_os = "windows") { 
-        format!("{file_stem}.exe") 
-    } else { 
-        file_stem.to_string() 
-    };
-    let exe_path = out_dir.join(exe_name);
+/// 计算编译源代码为可执行文件时将执行的rustc命令（不执行）
+/// 
+/// # 参数
+/// * `source` - 源代码字符串（用于读取编译指示）
+/// * `name` - 源文件的路径或显示名称（决定构建产物的文件名）
+/// * `overrides` - 命令行参数中指定的设置（发布模式、整数溢出的处理方式），未指定的由编译指示或默认值决定
+/// * `output` - 构建产物的输出位置选项
+/// 
+/// # 返回值
+/// * `Result<Vec<String>>` - 与`compile_source`实际执行的命令完全相同的命令行参数
+pub fn rustc_invocation(source: &str, name: &Path, overrides: &CliOverrides, output: &OutputOptions) -> Result<Vec<String>> {
+    let config = Config::resolve(source, overrides)?;
+    let out_dir = &config.out_dir.value;
+    let exe_path = output.exe.clone().unwrap_or_else(|| exe_output_path(name, out_dir));
+    Ok(rustc_args(&rust_output_path(name, out_dir, output), &exe_path, &config))
+}
 
-    // 写入生成的Rust代码
-    fs::write(&rs_path, rust_code).with_context(|| format!("write file: {}", rs_path.display()))?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ast::Stmt;
 
-    // 调用rustc编译
-    let mut cmd = Command::new("rustc");
-    if release {
-        cmd.arg("-O"); // 优化标志
+    #[test]
+    fn analyze_keeps_partial_program_after_syntax_error() {
+        let analysis = analyze("x = 1\ny = (2\nprint(x)\n", Path::new("test.kr"), &SemanticOptions::default());
+        assert!(analysis.diagnostics.iter().any(|d| d.severity == Severity::Error));
+        assert!(analysis.semantic.is_none());
+        let program = analysis.program.expect("partial program");
+        assert_eq!(program.statements.len(), 3);
+        assert!(matches!(program.statements[1], Stmt::Invalid { .. }));
+        assert!(matches!(program.statements[2], Stmt::Print { .. }));
     }
-    let status = cmd
-        .arg("--edition=2024")
-        .arg("-o")
-        .arg(&exe_path)
-        .arg(&rs_path)
-        .status()
-        .with_context(|| format!("failed to run rustc for {}", rs_path.display()))?;
 
-    if !status.success() {
-        anyhow::bail!("rustc failed to compile generated code. See above errors.");
+    #[test]
+    fn concurrent_compiles_use_separate_out_dirs() {
+        let base = std::env::temp_dir().join(format!("kairo-concurrent-{}", std::process::id()));
+        let exes: Vec<PathBuf> = std::thread::scope(|scope| {
+            let handles: Vec<_> = ["first", "second"]
+                .into_iter()
+                .map(|word| {
+                    let overrides = CliOverrides { out_dir: Some(base.join(word)), ..Default::default() };
+                    scope.spawn(move || {
+                        let source = format!("print(\"{word}\")\n");
+                        let outputs = compile_source(
+                            &source,
+                            Path::new("same.kr"),
+                            &overrides,
+                            &[EmitKind::Exe],
+                            &OutputOptions::default(),
+                            &SemanticOptions::default(),
+                            &CodegenOptions::default(),
+                            &Reporter::default(),
+                        );
+                        outputs.expect("compile should succeed").remove(0)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        for (exe, word) in exes.iter().zip(["first", "second"]) {
+            assert!(exe.starts_with(base.join(word)), "{}", exe.display());
+            let output = Command::new(exe).output().unwrap();
+            assert_eq!(String::from_utf8_lossy(&output.stdout), format!("{word}\n"));
+        }
+        fs::remove_dir_all(&base).unwrap();
     }
 
-    Ok(exe_path)
+    #[test]
+    fn rustfmt_output_is_stable() {
+        // 没有安装rustfmt时跳过
+        if rustfmt("fn main() {}\n").is_err() {
+            eprintln!("rustfmt is not available; skipping");
+            return;
+        }
+        let source = "const N = 2\n$x = 1\nx = x * N\nfun f(s: string) -> string {\n    return s + \"!\"\n}\nprint(\"a message long enough to push the line past the rustfmt width:\", x, x, x, f(\"a\"), f(\"b\"))\n";
+        let compile = |rustfmt| {
+            let options = CodegenOptions { rustfmt, ..Default::default() };
+            compile_source_to_rust(source, Path::new("fmt.kr"), &CliOverrides::default(), &SemanticOptions::default(), &options, &Reporter::default())
+                .expect("source should compile")
+        };
+        let (formatted, unformatted) = (compile(true), compile(false));
+        assert_ne!(formatted, unformatted, "the sample should need formatting");
+        assert_eq!(rustfmt(&formatted).unwrap(), formatted);
+    }
 }
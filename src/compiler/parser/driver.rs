@@ -1,52 +1,310 @@
 use std::path::Path;
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 
-use crate::compiler::ast::Program;
-use super::stmt;
+use crate::compiler::ast::{Program, Stmt};
+use super::error::{syntax_bail, SyntaxError};
+use super::lexer::{check_strings_closed, mask_block_comments, split_comment, split_statements};
+use super::stmt::{self, FnHeader};
 
 /// 解析Kairo源代码为抽象语法树
 /// 
 /// # 参数
 /// * `source` - 源代码字符串
-/// * `_file` - 源文件路径（用于错误报告，当前未使用）
+/// * `file` - 源文件路径（用于错误报告）
 /// 
 /// # 返回值
-/// * `Result<Program>` - 解析成功返回Program AST，失败返回错误信息
+/// * `Result<Program>` - 解析成功返回Program AST，失败返回第一个语法错误
+/// 
+/// # 说明
+/// 解析规则见`parse_recovering`；需要全部语法错误时（如`check`）请使用`parse_recovering`
+pub fn parse(source: &str, file: &Path) -> Result<Program> {
+    let (program, errors) = parse_recovering(source, file);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(program),
+    }
+}
+
+/// 解析Kairo源代码为抽象语法树，遇到语法错误时跳到下一行继续解析
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// * `_file` - 源文件路径（用于错误报告）
+/// 
+/// # 返回值
+/// * `(Program, Vec<anyhow::Error>)` - 由能够解析的语句组成的Program AST，
+///   以及按行号顺序排列的全部语法错误（没有错误时为空）
 /// 
 /// # 功能
-/// 1. 逐行解析源代码
-/// 2. 跳过空行和注释行（以//开头）
-/// 3. 尝试解析为打印语句或赋值语句
-/// 4. 如果无法解析则返回语法错误
-pub fn parse(source: &str, _file: &Path) -> Result<Program> {
+/// 1. 把块注释（`/* ... */`，可以跨越多行）替换为空白，再逐行解析源代码（见`mask_block_comments`）
+/// 2. 跳过空行、注释行（以//开头）和首行的shebang（以#!开头），
+///    并记录需要保留的空行位置（见`Program::blank_lines`）；
+///    其余各行先去除行尾注释（字符串字面量中的 `//` 不是注释，见`split_comment`），
+///    并检查字符串字面量都在本行闭合（见`check_strings_closed`）
+/// 3. 每行按顶层的分号切分为多条语句（如 `x = 1; y = 2`），各条语句的行号相同、列号不同；
+///    空语句（如末尾的分号之后）忽略
+/// 4. 函数头之后直到单独的 `}` 为止的语句组成函数体（函数不能嵌套定义）
+/// 5. 其余各条语句尝试解析为打印语句、返回语句、函数调用语句或赋值语句
+/// 6. 如果无法解析则记录语法错误
+/// 
+/// # 错误恢复
+/// 1. 无法解析的语句以`Stmt::Invalid`代替（见`stmt::invalid_stmt`），
+///    同一行中剩余的语句被跳过，从下一行继续解析
+/// 2. 出错的行以 `{` 结尾（函数头出错或函数嵌套定义）时，直到对应的 `}` 为止的函数体仍检查语法错误，
+///    但整个函数被跳过，避免函数体被当作顶层语句而产生连带的错误
+/// 3. 缺少右花括号的函数同样被跳过（之后的各行都被当作了它的函数体）
+/// 4. 块注释未闭合或嵌套时无法确定各行的内容，只返回该错误和空的Program
+pub fn parse_recovering(source: &str, _file: &Path) -> (Program, Vec<anyhow::Error>) {
     let mut statements = Vec::new();
+    let mut blank_lines = Vec::new();
+    let mut errors = Vec::new();
+    // 当前这组连续空行的第一行（之后还有非空行时才记录）；是否已经出现过非空行
+    let mut blank_run: Option<usize> = None;
+    let mut seen_content = false;
+    // 正在解析的函数（已读取函数头，尚未遇到右花括号）及其函数体
+    let mut current_fn: Option<(FnHeader, Vec<Stmt>)> = None;
+    // 是否正在跳过函数头出错的函数体（直到右花括号为止）
+    let mut skipping_fn = false;
+
+    // 块注释替换为空白后逐行解析，行号和列号不变
+    let masked = match mask_block_comments(source) {
+        Ok(masked) => masked,
+        Err(error) => return (Program { statements, blank_lines }, vec![error]),
+    };
 
     // 逐行解析源代码
-    for (i, raw_line) in source.lines().enumerate() {
+    for (i, (original, raw_line)) in source.lines().zip(masked.text.lines()).enumerate() {
         let line_no = i + 1; // 行号从1开始
         let line_trim = raw_line.trim();
         
-        // 跳过空行和注释行
-        if line_trim.is_empty() || line_trim.starts_with("//") { 
-            continue; 
+        // 记录空行（只有块注释的行和块注释内部的行不算空行），连续多个空行只记录第一行
+        if original.trim().is_empty() && !masked.continued_lines.contains(&line_no) {
+            if seen_content && blank_run.is_none() {
+                blank_run = Some(line_no);
+            }
+            continue;
         }
+        blank_lines.extend(blank_run.take());
+        seen_content = true;
 
-        // 尝试解析为打印语句
-        if let Some(stmt) = stmt::parse_print(line_trim, line_no)? {
-            statements.push(stmt);
+        // 跳过注释行（包括只有块注释的行）
+        if line_trim.is_empty() {
             continue;
         }
-        
-        // 尝试解析为赋值语句（使用原始行，因为需要保留空格信息）
-        if let Some(stmt) = stmt::parse_assign(raw_line, line_no)? {
-            statements.push(stmt);
+        if line_trim.starts_with("//") { 
+            continue; 
+        }
+
+        // 跳过首行的shebang，便于直接执行脚本
+        if line_no == 1 && line_trim.starts_with("#!") {
             continue;
         }
 
+        // 去除行尾注释（如 `x = 1  // 计数器`），保留行首空白使列号不变
+        let (raw_line, _) = split_comment(raw_line);
+
+        let mut parse_line = || -> Result<()> {
+            if let Err(error) = check_strings_closed(raw_line, line_no) {
+                let invalid = stmt::invalid_stmt(raw_line, line_no);
+                match current_fn.as_mut() {
+                    _ if skipping_fn => {}
+                    Some((_, body)) => body.push(invalid),
+                    None => statements.push(invalid),
+                }
+                return Err(error);
+            }
+
+            for segment in split_statements(raw_line) {
+                if skipping_fn {
+                    // 跳过的函数体：只检查语法错误，不保留语句
+                    if segment.trim() == "}" {
+                        skipping_fn = false;
+                    } else {
+                        parse_stmt(&segment, line_no, true)?;
+                    }
+                    continue;
+                }
+
+                let stmt = if segment.trim() == "}" {
+                    // 函数体结束
+                    match current_fn.take() {
+                        Some((header, body)) => header.into_stmt(body),
+                        None => syntax_bail!(line_no, "语法错误：多余的右花括号 `}}`（第 {line_no} 行）"),
+                    }
+                } else if let Some(mut header) = stmt::parse_fn_header(&segment, line_no)? {
+                    if current_fn.is_some() {
+                        syntax_bail!(line_no, "语法错误：函数不能嵌套定义，请把函数 `{}` 移到其他函数之外（第 {line_no} 行）", header.name);
+                    }
+                    match header.inline_body.take() {
+                        // 函数体与函数头写在同一行（可以是以分号分隔的多条语句）
+                        Some(body_line) => {
+                            let body = split_statements(&body_line)
+                                .iter()
+                                .map(|body_stmt| parse_stmt(body_stmt, line_no, true))
+                                .collect::<Result<Vec<_>>>()?;
+                            header.into_stmt(body)
+                        }
+                        // 函数体从下一行开始
+                        None => {
+                            current_fn = Some((header, Vec::new()));
+                            continue;
+                        }
+                    }
+                } else if let Some((_, body)) = current_fn.as_mut() {
+                    match parse_stmt(&segment, line_no, true) {
+                        Ok(stmt) => body.push(stmt),
+                        Err(error) => {
+                            body.push(stmt::invalid_stmt(&segment, line_no));
+                            return Err(error);
+                        }
+                    }
+                    continue;
+                } else {
+                    match parse_stmt(&segment, line_no, false) {
+                        Ok(stmt) => stmt,
+                        Err(error) => {
+                            statements.push(stmt::invalid_stmt(&segment, line_no));
+                            return Err(error);
+                        }
+                    }
+                };
+
+                statements.push(stmt);
+            }
+            Ok(())
+        };
+        if let Err(error) = parse_line() {
+            errors.push(error);
+            // 出错的行以 `{` 结尾时（函数头出错或函数嵌套定义），跳过之后的函数体
+            if raw_line.trim_end().ends_with('{') {
+                skipping_fn = true;
+            }
+        }
+    }
+
+    if let Some((header, _)) = current_fn {
+        let line = header.span.start.line;
+        let message = format!("语法错误：函数 `{}` 缺少右花括号 `}}`（函数从第 {line} 行开始）", header.name);
+        errors.push(SyntaxError::new(line, None, message).into());
+    }
+
+    (Program { statements, blank_lines }, errors)
+}
+
+/// 解析单行语句
+/// 
+/// # 参数
+/// * `raw_line` - 原始行内容（需要保留空格和列号信息）
+/// * `line_no` - 行号
+/// * `in_fn` - 是否在函数体中（只有函数体中可以使用return）
+/// 
+/// # 返回值
+/// * `Result<Stmt>` - 解析成功返回语句，无法解析时返回语法错误
+fn parse_stmt(raw_line: &str, line_no: usize, in_fn: bool) -> Result<Stmt> {
+    if let Some(stmt) = stmt::parse_print(raw_line, line_no)? {
+        return Ok(stmt);
+    }
+    if let Some(stmt) = stmt::parse_return(raw_line, line_no)? {
+        if !in_fn {
+            syntax_bail!(line_no, "语法错误：return 只能在函数体中使用（第 {line_no} 行）");
+        }
+        return Ok(stmt);
+    }
+    if let Some(stmt) = stmt::parse_call(raw_line, line_no)? {
+        return Ok(stmt);
+    }
+    match stmt::parse_assign(raw_line, line_no)? {
+        Some(stmt) => Ok(stmt),
         // 如果都无法解析，返回语法错误
-        bail!("语法错误：无法解析第 {line_no} 行：{raw_line}");
+        None => syntax_bail!(line_no, "语法错误：无法解析第 {line_no} 行：{}", raw_line.trim()),
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::ast::Expr;
 
-    Ok(Program { statements })
+    /// 解析源代码，返回唯一的语法错误
+    fn only_error(source: &str) -> SyntaxError {
+        let (_, errors) = parse_recovering(source, Path::new("test.kr"));
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        errors[0].downcast_ref::<SyntaxError>().expect("syntax error").clone()
+    }
+
+    #[test]
+    fn unterminated_string_in_print() {
+        let error = only_error("print(\"abc)\n");
+        assert_eq!((error.line, error.col), (1, Some(7)));
+        assert_eq!(error.help.expect("help").summary, "字符串字面量未闭合，缺少右引号 `\"`");
+    }
+
+    #[test]
+    fn unterminated_string_in_assignment() {
+        let (program, errors) = parse_recovering("x = \"abc\nprint(x)\n", Path::new("test.kr"));
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        let error = errors[0].downcast_ref::<SyntaxError>().expect("syntax error");
+        assert_eq!((error.line, error.col), (1, Some(5)));
+        // 出错的赋值仍然声明了变量，之后的语句照常解析
+        assert!(matches!(&program.statements[0], Stmt::Invalid { name: Some(name), .. } if name == "x"));
+        assert!(matches!(program.statements[1], Stmt::Print { .. }));
+    }
+
+    #[test]
+    fn recovers_after_errors_and_keeps_later_statements() {
+        let (program, errors) = parse_recovering("1x = 5\ny = 2\nz = (\nprint(y)\n", Path::new("test.kr"));
+        let lines: Vec<usize> = errors.iter().map(|e| e.downcast_ref::<SyntaxError>().expect("syntax error").line).collect();
+        assert_eq!(lines, [1, 3], "{errors:?}");
+        assert_eq!(program.statements.len(), 4);
+        assert!(matches!(program.statements[0], Stmt::Invalid { .. }));
+        assert!(matches!(&program.statements[1], Stmt::Assign { name, .. } if name == "y"));
+        assert!(matches!(&program.statements[2], Stmt::Invalid { name: Some(name), .. } if name == "z"));
+        assert!(matches!(program.statements[3], Stmt::Print { .. }));
+    }
+
+    #[test]
+    fn records_first_line_of_each_blank_run() {
+        let (program, errors) = parse_recovering("\nx = 1\n\n\ny = 2\n/*\n\n*/\nprint(y)\n\n", Path::new("test.kr"));
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(program.blank_lines, vec![3]);
+    }
+
+    #[test]
+    fn skips_block_comments_across_lines() {
+        let source = "x = 1 /* a\n b */\nprint(\"/* not */\", x) /* c */\n/*\nprint(2)\n*/\nprint(x)\n";
+        let (program, errors) = parse_recovering(source, Path::new("test.kr"));
+        assert!(errors.is_empty(), "{errors:?}");
+        let lines: Vec<usize> = program.statements.iter().map(|stmt| stmt.span().start.line).collect();
+        assert_eq!(lines, vec![1, 3, 7]);
+        let Stmt::Print { args, .. } = &program.statements[1] else {
+            panic!("expected print");
+        };
+        assert!(matches!(&args[0], Expr::StringLit(s, _) if s == "/* not */"));
+    }
+
+    #[test]
+    fn splits_statements_on_semicolons() {
+        let (program, errors) = parse_recovering("x = 1; y = 2; print(\"a;b\", x)\nprint(y);\n", Path::new("test.kr"));
+        assert!(errors.is_empty(), "{errors:?}");
+        let spans: Vec<(usize, usize)> = program.statements.iter().map(|stmt| (stmt.span().start.line, stmt.span().start.col)).collect();
+        // 同一行的语句有各自的列号；行尾多余的分号被忽略
+        assert_eq!(spans, vec![(1, 1), (1, 8), (1, 15), (2, 1)]);
+        let Stmt::Print { args, .. } = &program.statements[2] else {
+            panic!("expected print");
+        };
+        assert!(matches!(&args[0], Expr::StringLit(s, _) if s == "a;b"));
+    }
+
+    #[test]
+    fn print_allows_whitespace_around_parentheses() {
+        for source in ["print(\"x\")\n", "print (\"x\")\n", "print( \"x\" )\n", "  print  (  \"x\"  )  \n"] {
+            let (program, errors) = parse_recovering(source, Path::new("test.kr"));
+            assert!(errors.is_empty(), "{source:?}: {errors:?}");
+            let [Stmt::Print { args, .. }] = program.statements.as_slice() else {
+                panic!("{source:?} should be a single print");
+            };
+            assert!(matches!(args.as_slice(), [Expr::StringLit(s, _)] if s == "x"), "{source:?}: {args:?}");
+        }
+    }
 }
@@ -0,0 +1,96 @@
+use std::fmt;
+
+use crate::compiler::ast::SourceSpan;
+use crate::compiler::semantics::diagnostics::{get_line, Diagnostic, Severity};
+
+/// 语法错误（包括编译指示错误）
+/// 
+/// # 字段
+/// * `line` - 出错的行号（从1开始）
+/// * `col` - 出错的列号（从1开始，按字符计；只能确定出错行时为None）
+/// * `message` - 完整的错误信息（已包含行号）
+/// * `help` - 终端输出为诊断块时的摘要和修复建议（None时只输出`message`）
+/// 
+/// # 说明
+/// 解析函数仍然返回`anyhow::Result`，需要出错位置的调用方（如JSON诊断输出）
+/// 可以用`downcast_ref::<SyntaxError>()`取出位置信息
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub line: usize,
+    pub col: Option<usize>,
+    pub message: String,
+    pub help: Option<SyntaxHelp>,
+}
+
+/// 语法错误的诊断块内容
+/// 
+/// # 字段
+/// * `summary` - 错误摘要（诊断块的标题）
+/// * `end_col` - 插入符号标记范围的结束列号（不包含，起始列号为`SyntaxError::col`）
+/// * `suggestions` - 多行建议文本（已组合好）
+#[derive(Debug, Clone)]
+pub struct SyntaxHelp {
+    pub summary: String,
+    pub end_col: usize,
+    pub suggestions: String,
+}
+
+impl SyntaxError {
+    /// 创建语法错误
+    pub fn new(line: usize, col: Option<usize>, message: String) -> Self {
+        SyntaxError { line, col, message, help: None }
+    }
+
+    /// 附加诊断块内容，使终端输出与语义错误一样带有源码行、插入符号和修复建议
+    /// 
+    /// # 参数
+    /// * `summary` - 错误摘要
+    /// * `end_col` - 插入符号标记范围的结束列号（不包含）
+    /// * `suggestions` - 多行建议文本
+    pub fn with_help(mut self, summary: String, end_col: usize, suggestions: String) -> Self {
+        self.help = Some(SyntaxHelp { summary, end_col, suggestions });
+        self
+    }
+
+    /// 转换为诊断信息
+    /// 
+    /// # 参数
+    /// * `source` - 源代码字符串
+    /// * `filename` - 文件名
+    /// 
+    /// # 返回值
+    /// * `Diagnostic` - 错误诊断：带诊断块内容时为`render_error`的诊断块；
+    ///   否则只有一行信息，带列号时范围为该字符，没有列号时为出错行去除首尾空白后的内容
+    pub fn to_diagnostic(&self, source: &str, filename: &str) -> Diagnostic {
+        if let (Some(col), Some(help)) = (self.col, &self.help) {
+            let span = SourceSpan::single_line(self.line, col, help.end_col);
+            return Diagnostic::error(&help.summary, filename, span, &get_line(source, self.line), &help.suggestions);
+        }
+        let span = match self.col {
+            Some(col) => SourceSpan::single_line(self.line, col, col + 1),
+            None => {
+                let line = get_line(source, self.line);
+                let indent = line.chars().count() - line.trim_start().chars().count();
+                SourceSpan::single_line(self.line, indent + 1, line.trim_end().chars().count() + 1)
+            }
+        };
+        Diagnostic::plain(Severity::Error, filename, Some(span), &self.message)
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// 以`SyntaxError`提前返回，用法与`anyhow::bail!`相同，只是第一个参数为出错的行号
+macro_rules! syntax_bail {
+    ($line:expr, $($arg:tt)+) => {
+        return Err($crate::compiler::parser::error::SyntaxError::new($line, None, format!($($arg)+)).into())
+    };
+}
+
+pub(crate) use syntax_bail;
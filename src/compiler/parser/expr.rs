@@ -1,116 +1,569 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
 
-use crate::compiler::ast::{Expr, SourceSpan};
+use crate::compiler::ast::{CompareOp, Expr, ReadKind, SourceSpan};
+use crate::compiler::formatter::format_expr;
+
+use super::error::{syntax_bail, SyntaxError};
+use super::lexer::{tokenize, Token, TokenKind};
 
 /// 解析表达式字符串
 /// 
 /// # 参数
 /// * `s` - 要解析的表达式字符串
 /// * `line_no` - 行号（用于错误报告）
+/// * `col_offset` - `s`在源码行中之前的字符数（`s`从行首开始时为0）
 /// 
 /// # 返回值
 /// * `Result<Expr>` - 解析成功返回表达式AST，失败返回错误信息
 /// 
 /// # 功能
-/// 支持左结合的加法运算：a + b + 1
-/// 将表达式按+分割，递归解析每个部分
-pub(crate) fn parse_expr(s: &str, line_no: usize) -> Result<Expr> {
-    // 支持左结合的加法：a + b + 1
-    let parts: Vec<&str> = s.split('+').map(|t| t.trim()).collect();
-    
-    // 如果没有+号，直接解析为原子表达式
-    if parts.len() == 1 {
-        return parse_atom(parts[0], line_no);
-    }
-    
-    // 从第一个部分开始构建表达式
-    let mut expr = parse_atom(parts[0], line_no)?;
-    
-    // 依次处理后续部分，构建左结合的加法表达式
-    for part in parts.iter().skip(1) {
-        let rhs = parse_atom(part, line_no)?;
-        
-        // 计算新表达式的源码范围
-        let span = match (&expr, &rhs) {
-            (Expr::StringLit(_, a), Expr::StringLit(_, b)) => SourceSpan::single_line(line_no, a.start.col, b.end.col),
-            (Expr::StringLit(_, a), _) => *a,
-            (Expr::IntLit(_, a), _) => *a,
-            (Expr::Ident(_, a), _) => *a,
-            (Expr::BinaryAdd(_, _, a), _) => *a,
-        };
-        
-        expr = Expr::BinaryAdd(Box::new(expr), Box::new(rhs), span);
+/// 先将表达式切分为词法单元，再用优先级爬升（Pratt）算法构建表达式树。
+/// 各节点的源码位置为其在源码行中的列范围
+/// 
+/// # 优先级（从高到低）
+/// 1. `( ... )`、函数调用 `f(a, b)`、负号 `-x` `-(a + b)`
+/// 2. `*` `/` `%`
+/// 3. `+` `-`
+/// 4. `<` `<=` `>` `>=` `==` `!=`
+/// 
+/// 算术运算符都是左结合的：`a - b - c` 解析为 `(a - b) - c`；
+/// 比较运算符不能连用：`a < b < c` 会报错
+pub(crate) fn parse_expr(s: &str, line_no: usize, col_offset: usize) -> Result<Expr> {
+    let tokens = tokenize(s, line_no, col_offset)?;
+    let mut parser = ExprParser { tokens, pos: 0, source: s.trim(), raw: s, line_no, col_offset };
+
+    let expr = parser.parse_bp(0)?;
+
+    // 表达式之后不应再有多余的内容
+    match parser.peek() {
+        None => Ok(expr),
+        Some(TokenKind::RParen) => syntax_bail!(line_no, "语法错误：多余的右括号 `)`（第 {line_no} 行）"),
+        Some(_) => parser.error(),
     }
-    Ok(expr)
 }
 
-/// 解析原子表达式（不可再分割的基本表达式）
+/// 获取二元运算符的绑定强度
 /// 
 /// # 参数
-/// * `s` - 要解析的原子表达式字符串
-/// * `line_no` - 行号（用于错误报告）
+/// * `kind` - 词法单元类型
 /// 
 /// # 返回值
-/// * `Result<Expr>` - 解析成功返回表达式AST，失败返回错误信息
+/// * `Option<(u8, u8)>` - 二元运算符返回（左绑定强度, 右绑定强度），否则返回None
 /// 
-/// # 支持的原子表达式类型
-/// 1. 字符串字面量："hello"
-/// 2. 整数字面量：42, -10
-/// 3. 标识符：变量名
-fn parse_atom(s: &str, line_no: usize) -> Result<Expr> {
-    // 解析字符串字面量："hello"
-    if s.starts_with('"') && s.ends_with('"') && s.len() >= 2 {
-        return Ok(Expr::StringLit(
-            s[1..s.len()-1].to_string(), // 去掉首尾的引号
-            SourceSpan::single_line(line_no, 1, s.len())
-        ));
-    }
-    
-    // 解析整数字面量：42, -10
-    if let Ok(v) = s.parse::<i64>() {
-        return Ok(Expr::IntLit(
-            v, 
-            SourceSpan::single_line(line_no, 1, s.len())
-        ));
-    }
-    
-    // 解析标识符：变量名
-    if is_ident(s) {
-        return Ok(Expr::Ident(
-            s.to_string(), 
-            SourceSpan::single_line(line_no, 1, s.len())
-        ));
-    }
-    
-    // 如果都不匹配，返回语法错误
-    bail!("语法错误：无法解析表达式 `{s}`（第 {line_no} 行）");
+/// # 说明
+/// 右绑定强度大于左绑定强度，使同级运算符左结合
+fn infix_binding_power(kind: &TokenKind) -> Option<(u8, u8)> {
+    match kind {
+        TokenKind::Compare(_) => Some((1, 2)),
+        TokenKind::Plus | TokenKind::Minus => Some((3, 4)),
+        TokenKind::Star | TokenKind::Slash | TokenKind::Percent => Some((5, 6)),
+        _ => None,
+    }
 }
 
-/// 检查字符串是否为有效的标识符
-/// 
-/// # 参数
-/// * `s` - 要检查的字符串
-/// 
-/// # 返回值
-/// * `bool` - 如果是有效标识符返回true，否则返回false
+/// 前缀负号的绑定强度（高于所有二元运算符）
+const PREFIX_NEG_BP: u8 = 7;
+
+/// 表达式语法分析器
 /// 
-/// # 标识符规则
-/// 1. 首字符必须是字母或下划线
-/// 2. 后续字符可以是字母、数字或下划线
-fn is_ident(s: &str) -> bool {
-    let mut chars = s.chars();
-    
-    // 检查首字符
-    match chars.next() {
-        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
-        _ => return false,
-    }
-    
-    // 检查后续字符
-    for c in chars {
-        if !(c.is_ascii_alphanumeric() || c == '_') { 
-            return false; 
+/// # 字段
+/// * `tokens` - 词法单元列表
+/// * `pos` - 当前位置
+/// * `source` - 表达式原文（用于错误报告）
+/// * `raw` - 未去除首尾空白的表达式原文（与`col_offset`一起定位字面量的原文）
+/// * `line_no` - 行号（用于错误报告）
+/// * `col_offset` - 表达式在源码行中之前的字符数
+struct ExprParser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    source: &'a str,
+    raw: &'a str,
+    line_no: usize,
+    col_offset: usize,
+}
+
+impl ExprParser<'_> {
+    /// 查看当前词法单元的类型（不前进）
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    /// 取出当前词法单元并前进
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// 生成通用的表达式语法错误
+    fn error<T>(&self) -> Result<T> {
+        syntax_bail!(self.line_no, "语法错误：无法解析表达式 `{}`（第 {} 行）", self.source, self.line_no);
+    }
+
+    /// 根据列范围生成源码位置
+    fn span(&self, start: usize, end: usize) -> SourceSpan {
+        SourceSpan::single_line(self.line_no, start, end)
+    }
+
+    /// 解析绑定强度不低于`min_bp`的表达式
+    /// 
+    /// # 参数
+    /// * `min_bp` - 最小绑定强度
+    /// 
+    /// # 返回值
+    /// * `Result<Expr>` - 解析成功返回表达式AST，失败返回错误信息
+    fn parse_bp(&mut self, min_bp: u8) -> Result<Expr> {
+        let mut lhs = self.parse_prefix()?;
+
+        while let Some((l_bp, r_bp)) = self.peek().and_then(infix_binding_power) {
+            if l_bp < min_bp {
+                break;
+            }
+
+            let op = self.next().expect("peeked operator").kind;
+
+            let rhs = self.parse_bp(r_bp)?;
+
+            // 比较运算不能连用（a < b < c），括号包起来的比较除外
+            if let (TokenKind::Compare(op), Expr::Compare(..)) = (&op, &lhs) {
+                return Err(self.chained_comparison(&lhs, *op, &rhs).into());
+            }
+
+            // 新表达式的源码范围从左操作数开始到右操作数结束
+            let span = SourceSpan { start: lhs.span().start, end: rhs.span().end };
+            let (a, b) = (Box::new(lhs), Box::new(rhs));
+            lhs = match op {
+                TokenKind::Plus => Expr::BinaryAdd(a, b, span),
+                TokenKind::Minus => Expr::BinarySub(a, b, span),
+                TokenKind::Star => Expr::BinaryMul(a, b, span),
+                TokenKind::Percent => Expr::BinaryMod(a, b, span),
+                TokenKind::Compare(op) => Expr::Compare(op, a, b, span),
+                _ => Expr::BinaryDiv(a, b, span),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    /// 解析前缀表达式（原子表达式、括号分组和负号）
+    /// 
+    /// # 返回值
+    /// * `Result<Expr>` - 解析成功返回表达式AST，失败返回错误信息
+    /// 
+    /// # 支持的前缀表达式类型
+    /// 1. 括号分组：(a + b)
+    /// 2. 字符串字面量："hello"
+    /// 3. 整数字面量：42, -10, 0xFF, 0o17, 0b1010, 1_000
+    /// 4. 浮点数字面量：3.14, -0.5
+    /// 5. 布尔字面量：true, false
+    /// 6. 类型查询：typeof(x)
+    /// 7. 读取输入：read()、read_int()
+    /// 8. 函数调用：add(1, 2)
+    /// 9. 标识符：变量名
+    /// 10. 取负：-x, -(a + b), - -5
+    fn parse_prefix(&mut self) -> Result<Expr> {
+        let Some(token) = self.next() else {
+            return self.error();
+        };
+
+        match token.kind {
+            TokenKind::LParen => {
+                let inner = self.parse_bp(0)?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, end, .. }) => {
+                        Ok(Expr::Group(Box::new(inner), self.span(token.start, end)))
+                    }
+                    Some(_) => self.error(),
+                    None => syntax_bail!(self.line_no, "语法错误：未闭合的括号（第 {} 行）", self.line_no),
+                }
+            }
+            TokenKind::Str(s) => Ok(Expr::StringLit(s, self.span(token.start, token.end))),
+            TokenKind::Number(text) => self.parse_number(&text, token.start, token.end),
+            // true/false 是布尔字面量，不是变量名
+            TokenKind::Ident(name) if name == "true" || name == "false" => {
+                Ok(Expr::BoolLit(name == "true", self.span(token.start, token.end)))
+            }
+            // typeof(表达式)：编译期类型查询
+            TokenKind::Ident(name) if name == "typeof" && self.peek() == Some(&TokenKind::LParen) => {
+                self.next();
+                let inner = self.parse_bp(0)?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, end, .. }) => {
+                        Ok(Expr::TypeOf(Box::new(inner), self.span(token.start, end)))
+                    }
+                    Some(_) => self.error(),
+                    None => syntax_bail!(self.line_no, "语法错误：未闭合的括号（第 {} 行）", self.line_no),
+                }
+            }
+            // 函数名(实参, ...)：函数调用；read()、read_int() 是读取一行输入的内置函数
+            TokenKind::Ident(name) if self.peek() == Some(&TokenKind::LParen) => {
+                self.next();
+                let (args, end) = self.parse_call_args()?;
+                match ReadKind::from_name(&name) {
+                    Some(_) if !args.is_empty() => {
+                        syntax_bail!(self.line_no, "语法错误：`{name}()` 不接受参数（第 {} 行）", self.line_no)
+                    }
+                    Some(kind) => Ok(Expr::Read(kind, self.span(token.start, end))),
+                    None => Ok(Expr::Call(name, args, self.span(token.start, end))),
+                }
+            }
+            TokenKind::Ident(name) => Ok(Expr::Ident(name, self.span(token.start, token.end))),
+            TokenKind::Minus => {
+                // 紧跟数字字面量的负号直接并入字面量（使 -9223372036854775808 可以表示）
+                if let Some(Token { kind: TokenKind::Number(text), end, .. }) = self.tokens.get(self.pos).cloned() {
+                    self.pos += 1;
+                    return self.parse_number(&format!("-{text}"), token.start, end);
+                }
+                let inner = self.parse_bp(PREFIX_NEG_BP)?;
+                let span = SourceSpan { start: self.span(token.start, token.end).start, end: inner.span().end };
+                Ok(Expr::Neg(Box::new(inner), span))
+            }
+            TokenKind::RParen => syntax_bail!(self.line_no, "语法错误：多余的右括号 `)`（第 {} 行）", self.line_no),
+            _ => self.error(),
+        }
+    }
+
+    /// 解析函数调用的实参列表（左括号已被取出）
+    /// 
+    /// # 返回值
+    /// * `Result<(Vec<Expr>, usize)>` - 实参列表，以及右括号之后的列号
+    fn parse_call_args(&mut self) -> Result<(Vec<Expr>, usize)> {
+        let mut args = Vec::new();
+        if let Some(Token { kind: TokenKind::RParen, end, .. }) = self.tokens.get(self.pos).cloned() {
+            self.pos += 1;
+            return Ok((args, end));
+        }
+
+        loop {
+            args.push(self.parse_bp(0)?);
+            match self.next() {
+                Some(Token { kind: TokenKind::Comma, .. }) => {}
+                Some(Token { kind: TokenKind::RParen, end, .. }) => return Ok((args, end)),
+                Some(_) => return self.error(),
+                None => syntax_bail!(self.line_no, "语法错误：未闭合的括号（第 {} 行）", self.line_no),
+            }
+        }
+    }
+
+    /// 将数字字面量文本转换为整数或浮点数字面量
+    /// 
+    /// # 参数
+    /// * `text` - 数字字面量文本（可带负号）
+    /// * `start` - 起始列号
+    /// * `end` - 结束列号（不包含）
+    /// 
+    /// # 规则
+    /// 1. `0x`、`0o`、`0b` 前缀分别表示十六进制、八进制、二进制整数
+    /// 2. 其余先尝试解析为十进制整数；失败且包含小数点或指数（`1.5e3`、`1e308`、`2e-3`）时再尝试解析为浮点数，
+    ///    有没有小数点的规则相同
+    /// 3. 整数超出int（i64）范围、浮点数超出float（f64）范围（如 `1e400`）时报告溢出错误
+    /// 4. 下划线可以作为数字分隔符（`1_000_000`），但只能出现在两个数字之间
+    /// 5. 数字开头但不是合法字面量时（如 `1abc`、`3.4.5`），报告具体的原因
+    fn parse_number(&self, text: &str, start: usize, end: usize) -> Result<Expr> {
+        let original = text;
+        let cleaned = self.strip_digit_separators(text)?;
+        let text = cleaned.as_str();
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (radix, digits) = match unsigned.get(..2) {
+            Some("0x") => (16, &unsigned[2..]),
+            Some("0o") => (8, &unsigned[2..]),
+            Some("0b") => (2, &unsigned[2..]),
+            _ => (10, unsigned),
+        };
+
+        // 数字全部合法时再按i128解析，以便区分"数字格式错误"和"超出i64范围"
+        if !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix)) {
+            let value = i128::from_str_radix(digits, radix)
+                .ok()
+                .map(|v| if negative { -v } else { v })
+                .and_then(|v| i64::try_from(v).ok());
+            return match value {
+                Some(v) => Ok(Expr::IntLit(v, self.span(start, end))),
+                None => Err(self.int_overflow(original, start, end).into()),
+            };
         }
+        if radix == 10
+            && (text.contains('.') || text.contains(['e', 'E']))
+            && let Ok(v) = text.parse::<f64>()
+        {
+            if v.is_infinite() {
+                return Err(self.float_overflow(original, start, end).into());
+            }
+            return Ok(Expr::FloatLit(v, self.span(start, end)));
+        }
+        self.malformed_number(original, radix, digits, start)
+    }
+
+    /// 生成数字开头但不是合法数字字面量时的错误（如 `1abc`、`3.4.5`、`0x1g`）
+    /// 
+    /// # 参数
+    /// * `text` - 源码中的字面量文本（可带负号和进制前缀）
+    /// * `radix` - 由前缀确定的进制
+    /// * `digits` - 去除下划线后进制前缀之后的部分
+    /// * `start` - 起始列号
+    fn malformed_number<T>(&self, text: &str, radix: u32, digits: &str, start: usize) -> Result<T> {
+        let radix_name = match radix {
+            16 => "十六进制",
+            8 => "八进制",
+            2 => "二进制",
+            _ => "十进制",
+        };
+        let reason = if digits.is_empty() {
+            format!("进制前缀 `{}` 之后缺少数字", text.trim_start_matches('-'))
+        } else if radix == 10 && digits.matches('.').count() > 1 {
+            "数字中最多只能有一个小数点".to_string()
+        } else {
+            let bad = digits.chars().find(|c| *c != '.' && !c.is_digit(radix)).unwrap_or('.');
+            if radix == 10 && bad.is_ascii_alphabetic() {
+                format!("`{bad}` 不是{radix_name}数字（变量名不能以数字开头）")
+            } else {
+                format!("`{bad}` 不是{radix_name}数字")
+            }
+        };
+        let message = format!("语法错误：`{text}` 不是合法的数字字面量，{reason}（第 {} 行，第 {start} 列）", self.line_no);
+        Err(SyntaxError::new(self.line_no, Some(start), message).into())
+    }
+
+    /// 生成整数字面量超出int（i64）范围时的错误
+    /// 
+    /// # 参数
+    /// * `text` - 源码中的字面量文本（可带负号和进制前缀）
+    /// * `start` - 起始列号
+    /// * `end` - 结束列号（不包含）
+    fn int_overflow(&self, text: &str, start: usize, end: usize) -> SyntaxError {
+        let message = format!(
+            "语法错误：整数字面量 `{text}` 超出了 int 的范围（{} 到 {}）（第 {} 行）",
+            i64::MIN,
+            i64::MAX,
+            self.line_no
+        );
+        let size = if text.starts_with('-') { "太小" } else { "太大" };
+        let summary = format!("整数 `{text}` {size}了，超出了 int 能表示的范围");
+        let suggestions = format!(
+            "   - int 的范围是 {} 到 {}\n   - 请使用更小的数值，或者把它拆成几个较小的数分别计算\n   - 如果不需要精确的整数，可以写成浮点数（如 `1.0e20`）",
+            i64::MIN,
+            i64::MAX
+        );
+        SyntaxError::new(self.line_no, Some(start), message).with_help(summary, end, suggestions)
+    }
+
+    /// 生成比较运算连用（`a < b < c`）时的错误
+    /// 
+    /// # 参数
+    /// * `lhs` - 左侧已经解析的比较（`a < b`）
+    /// * `op` - 连用的比较运算符
+    /// * `rhs` - 右操作数（`c`）
+    /// 
+    /// # 说明
+    /// 修复建议把连用的比较改写为 `a < b and b < c`，其中的操作数用格式化工具重新输出（见`formatter::format_expr`）
+    fn chained_comparison(&self, lhs: &Expr, op: CompareOp, rhs: &Expr) -> SyntaxError {
+        let Expr::Compare(_, _, middle, _) = lhs else {
+            unreachable!("chained comparison starts with a comparison");
+        };
+        // 字面量按源码位置取出原文：补齐表达式之前的行和列，使位置与源文件一致
+        let source = format!("{}{}{}", "\n".repeat(self.line_no - 1), " ".repeat(self.col_offset), self.raw);
+        let rewrite = format!(
+            "{} and {} {} {}",
+            format_expr(lhs, &source),
+            format_expr(middle, &source),
+            op.symbol(),
+            format_expr(rhs, &source)
+        );
+
+        let message = format!(
+            "语法错误：比较运算不能连用，请拆成两个比较（第 {} 行）：`{}`",
+            self.line_no, self.source
+        );
+        let summary = "比较运算不能连用".to_string();
+        let suggestions = format!(
+            "   - 比较的结果是 bool，不能再与 `{}` 比较\n   - 请拆成两个比较：`{rewrite}`",
+            format_expr(rhs, &source)
+        );
+        let (start, end) = (lhs.span().start.col, rhs.span().end.col);
+        SyntaxError::new(self.line_no, Some(start), message).with_help(summary, end, suggestions)
+    }
+
+    /// 生成浮点数字面量超出float（f64）范围时的错误
+    /// 
+    /// # 参数
+    /// 与`int_overflow`相同
+    fn float_overflow(&self, text: &str, start: usize, end: usize) -> SyntaxError {
+        let message = format!(
+            "语法错误：浮点数字面量 `{text}` 超出了 float 的范围（绝对值最大约为 {:e}）（第 {} 行）",
+            f64::MAX,
+            self.line_no
+        );
+        let summary = format!("浮点数 `{text}` 的绝对值太大了，超出了 float 能表示的范围");
+        let suggestions = format!("   - float 的绝对值最大约为 {:e}\n   - 请减小指数部分", f64::MAX);
+        SyntaxError::new(self.line_no, Some(start), message).with_help(summary, end, suggestions)
+    }
+
+    /// 校验并去除数字字面量中的下划线分隔符
+    /// 
+    /// # 参数
+    /// * `text` - 数字字面量文本（可带负号和进制前缀）
+    /// 
+    /// # 返回值
+    /// * `Result<String>` - 去除下划线后的文本；下划线位置不正确时返回错误
+    /// 
+    /// # 规则
+    /// 下划线左右两侧都必须是数字（十六进制时包括a-f），
+    /// 因此 `1__0`、`1_`、`0x_FF`、`1_.5` 都会报错
+    fn strip_digit_separators(&self, text: &str) -> Result<String> {
+        if !text.contains('_') {
+            return Ok(text.to_string());
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        // 进制前缀（如 `0x`）中的字母不算数字
+        let digits_start = match text.trim_start_matches('-').get(..2) {
+            Some("0x" | "0o" | "0b") => text.len() - text.trim_start_matches('-').len() + 2,
+            _ => 0,
+        };
+        let is_digit_at = |i: usize| i >= digits_start && chars.get(i).is_some_and(|c| c.is_ascii_hexdigit());
+
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' && (i == 0 || !is_digit_at(i - 1) || !is_digit_at(i + 1)) {
+                syntax_bail!(
+                    self.line_no,
+                    "语法错误：数字字面量 `{text}` 中的下划线位置不正确，下划线只能出现在两个数字之间，如 `1_000`（第 {} 行）",
+                    self.line_no
+                );
+            }
+        }
+        Ok(text.replace('_', ""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 取出标识符的名称
+    fn ident(expr: &Expr) -> &str {
+        match expr {
+            Expr::Ident(name, _) => name,
+            other => panic!("expected identifier, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn subtraction_is_left_associative() {
+        let Expr::BinarySub(lhs, c, _) = parse_expr("a - b - c", 1, 0).unwrap() else {
+            panic!("expected subtraction");
+        };
+        let Expr::BinarySub(a, b, _) = *lhs else {
+            panic!("expected `a - b` on the left");
+        };
+        assert_eq!((ident(&a), ident(&b), ident(&c)), ("a", "b", "c"));
+    }
+
+    #[test]
+    fn multiplication_binds_tighter_than_addition() {
+        let Expr::BinaryAdd(a, rhs, _) = parse_expr("a + b * c", 1, 0).unwrap() else {
+            panic!("expected addition");
+        };
+        let Expr::BinaryMul(b, c, _) = *rhs else {
+            panic!("expected `b * c` on the right");
+        };
+        assert_eq!((ident(&a), ident(&b), ident(&c)), ("a", "b", "c"));
+    }
+
+    #[test]
+    fn exponent_works_with_and_without_decimal_point() {
+        for (text, expected) in [("1.5e3", 1500.0), ("1e308", 1e308), ("2E3", 2000.0), ("2e-3", 0.002), ("1.0e+2", 100.0)] {
+            match parse_expr(text, 1, 0) {
+                Ok(Expr::FloatLit(v, _)) => assert_eq!(v, expected, "{text}"),
+                other => panic!("`{text}` should be a float literal, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn infinite_float_literal_is_an_error() {
+        let err = parse_expr("1e400", 1, 0).unwrap_err();
+        assert!(err.to_string().contains("超出了 float 的范围"), "{err}");
+    }
+
+    #[test]
+    fn chained_comparison_suggests_rewrite() {
+        let err = parse_expr("a<0xFF <= b+1", 2, 10).unwrap_err();
+        let help = err.downcast_ref::<SyntaxError>().and_then(|e| e.help.clone()).expect("error should have help");
+        assert!(help.suggestions.ends_with("请拆成两个比较：`a < 0xFF and 0xFF <= b + 1`"), "{}", help.suggestions);
+        assert_eq!(err.downcast_ref::<SyntaxError>().unwrap().col, Some(11));
+        assert_eq!(help.end_col, 24);
+    }
+
+    #[test]
+    fn double_negation_nests() {
+        let Expr::Neg(inner, span) = parse_expr("- -5", 1, 0).unwrap() else {
+            panic!("expected negation");
+        };
+        assert_eq!((span.start.col, span.end.col), (1, 5));
+        // 紧跟数字的负号属于数字字面量，外层的负号是取负运算
+        assert!(matches!(*inner, Expr::IntLit(-5, _)), "{inner:?}");
+    }
+
+    #[test]
+    fn negation_of_parenthesized_sum() {
+        let Expr::Neg(inner, _) = parse_expr("-(a + b)", 1, 0).unwrap() else {
+            panic!("expected negation");
+        };
+        let Expr::Group(sum, _) = *inner else {
+            panic!("expected a parenthesized group");
+        };
+        let Expr::BinaryAdd(a, b, _) = *sum else {
+            panic!("expected `a + b` inside the parentheses");
+        };
+        assert_eq!((ident(&a), ident(&b)), ("a", "b"));
+    }
+
+    #[test]
+    fn underscores_separate_digits() {
+        assert!(matches!(parse_expr("1_000", 1, 0), Ok(Expr::IntLit(1000, _))));
+        assert!(matches!(parse_expr("0xFF_FF", 1, 0), Ok(Expr::IntLit(0xFFFF, _))));
+        match parse_expr("1_000.5", 1, 0) {
+            Ok(Expr::FloatLit(v, _)) => assert_eq!(v, 1000.5),
+            other => panic!("expected a float literal, got {other:?}"),
+        }
+        for text in ["1__0", "1_", "1_.5"] {
+            let err = parse_expr(text, 1, 0).expect_err(text);
+            assert!(err.to_string().contains("下划线位置不正确"), "{text}: {err}");
+        }
+        // 以下划线开头的是标识符而不是数字
+        assert_eq!(ident(&parse_expr("_1", 1, 0).unwrap()), "_1");
+    }
+
+    #[test]
+    fn malformed_numbers_get_a_number_specific_error() {
+        for (text, reason) in [
+            ("1abc", "`a` 不是十进制数字"),
+            ("3.4.5", "数字中最多只能有一个小数点"),
+            ("0x1g", "`g` 不是十六进制数字"),
+        ] {
+            let err = parse_expr(text, 1, 4).expect_err(text);
+            let message = err.to_string();
+            assert!(message.contains(&format!("`{text}` 不是合法的数字字面量，{reason}")), "{message}");
+            assert!(message.contains("第 5 列"), "{message}");
+        }
+        assert!(matches!(parse_expr("123", 1, 0), Ok(Expr::IntLit(123, _))));
+    }
+
+    #[test]
+    fn overflowing_integer_literal_is_an_error() {
+        let err = parse_expr("99999999999999999999 + 1", 1, 4).unwrap_err();
+        let syntax = err.downcast_ref::<SyntaxError>().expect("syntax error");
+        let help = syntax.help.as_ref().expect("error should have help");
+        assert_eq!(help.summary, "整数 `99999999999999999999` 太大了，超出了 int 能表示的范围");
+        // 插入符号标记整个字面量
+        assert_eq!((syntax.col, help.end_col), (Some(5), 25));
+        assert!(!err.to_string().contains("未定义"), "{err}");
+
+        let help = parse_expr("-9999999999999999999", 1, 0).unwrap_err().downcast_ref::<SyntaxError>().and_then(|e| e.help.clone());
+        assert!(help.is_some_and(|h| h.summary.contains("太小了")));
+        assert!(matches!(parse_expr("9223372036854775807", 1, 0), Ok(Expr::IntLit(i64::MAX, _))));
     }
-    true
 }
@@ -0,0 +1,491 @@
+use anyhow::Result;
+
+use crate::compiler::ast::CompareOp;
+use super::error::{syntax_bail, SyntaxError};
+
+/// 词法单元类型
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// 数字字面量（保留原始文本，由语法分析阶段转换为数值）
+    Number(String),
+    /// 字符串字面量（不包含引号）
+    Str(String),
+    /// 标识符
+    Ident(String),
+    /// `+`
+    Plus,
+    /// `-`
+    Minus,
+    /// `*`
+    Star,
+    /// `/`
+    Slash,
+    /// `%`
+    Percent,
+    /// `(`
+    LParen,
+    /// `)`
+    RParen,
+    /// `,`（分隔函数调用的实参）
+    Comma,
+    /// 比较运算符：`<` `<=` `>` `>=` `==` `!=`
+    Compare(CompareOp),
+}
+
+/// 词法单元
+/// 
+/// # 字段
+/// * `kind` - 词法单元类型
+/// * `start` - 起始列号（从1开始）
+/// * `end` - 结束列号（从1开始，不包含）
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 转义序列错误
+/// 
+/// # 字段
+/// * `index` - 反斜杠在字符串内容中的字符下标（从0开始）
+/// * `escape` - 反斜杠后的字符（字符串以反斜杠结尾时为None）
+#[derive(Debug)]
+pub(crate) struct EscapeError {
+    pub index: usize,
+    pub escape: Option<char>,
+}
+
+impl EscapeError {
+    /// 生成错误描述（不含位置）
+    pub fn message(&self) -> String {
+        match self.escape {
+            Some(c) => format!("未知的转义字符 `\\{c}`，支持的转义字符有 \\n \\t \\r \\\\ \\\" \\0"),
+            None => "字符串以单独的反斜杠结尾，如需输出反斜杠请写 `\\\\`".to_string(),
+        }
+    }
+}
+
+/// 解码字符串字面量中的转义序列
+/// 
+/// # 参数
+/// * `raw` - 字符串字面量的原始内容（不包含引号）
+/// 
+/// # 返回值
+/// * `Result<String, EscapeError>` - 解码后的字符串；遇到未知的转义序列时返回错误
+/// 
+/// # 支持的转义序列
+/// `\n` 换行、`\t` 制表符、`\r` 回车、`\\` 反斜杠、`\"` 引号、`\0` 空字符
+pub(crate) fn unescape(raw: &str) -> Result<String, EscapeError> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().enumerate();
+    while let Some((index, c)) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let decoded = match chars.next() {
+            Some((_, 'n')) => '\n',
+            Some((_, 't')) => '\t',
+            Some((_, 'r')) => '\r',
+            Some((_, '\\')) => '\\',
+            Some((_, '"')) => '"',
+            Some((_, '0')) => '\0',
+            Some((_, other)) => return Err(EscapeError { index, escape: Some(other) }),
+            None => return Err(EscapeError { index, escape: None }),
+        };
+        out.push(decoded);
+    }
+    Ok(out)
+}
+
+/// 检查一行代码中的字符串字面量是否都已闭合
+/// 
+/// # 参数
+/// * `line` - 已去除注释的源码行
+/// * `line_no` - 行号（用于错误报告）
+/// 
+/// # 返回值
+/// * `Result<()>` - 都已闭合时返回Ok；否则返回指向未闭合字符串左引号的语法错误
+/// 
+/// # 说明
+/// 在按分号和参数切分之前检查，避免未闭合的字符串吞掉之后的 `)` 或 `;` 而报告含糊的错误
+pub(crate) fn check_strings_closed(line: &str, line_no: usize) -> Result<()> {
+    let mut open_quote = None;
+    let mut chars = line.chars().enumerate();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if open_quote.is_some() => {
+                chars.next();
+            }
+            '"' if open_quote.is_some() => open_quote = None,
+            '"' => open_quote = Some(index),
+            _ => {}
+        }
+    }
+    match open_quote {
+        Some(index) => Err(unclosed_string(line_no, index + 1).into()),
+        None => Ok(()),
+    }
+}
+
+/// 生成字符串字面量未闭合的语法错误
+/// 
+/// # 参数
+/// * `line_no` - 行号
+/// * `col` - 左引号的列号（从1开始）
+fn unclosed_string(line_no: usize, col: usize) -> SyntaxError {
+    let message = format!("语法错误：字符串字面量未闭合（第 {line_no} 行，第 {col} 列）");
+    let summary = "字符串字面量未闭合，缺少右引号 `\"`".to_string();
+    let suggestions = "   - 字符串必须在同一行内用一对双引号括起来，请在字符串的末尾补上 `\"`\n   - 如果字符串本身包含双引号，请写成 `\\\"`，如：\n        print(\"他说：\\\"你好\\\"\")".to_string();
+    SyntaxError::new(line_no, Some(col), message).with_help(summary, col + 1, suggestions)
+}
+
+/// 把源码行切分为代码和行尾注释
+/// 
+/// # 参数
+/// * `line` - 源码行
+/// 
+/// # 返回值
+/// * `(&str, Option<&str>)` - 注释之前的代码（保留行首空白以保持列号不变，去除行尾空白）
+///   和从 `//` 开始的注释；没有注释时返回原行和None
+/// 
+/// # 说明
+/// 字符串字面量中的 `//`（如 `print("http://x")`）不是注释，转义的引号（`\"`）不结束字符串
+pub(crate) fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut in_string = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek().is_some_and(|&(_, next)| next == '/') => {
+                return (line[..index].trim_end(), Some(&line[index..]));
+            }
+            _ => {}
+        }
+    }
+    (line, None)
+}
+
+/// 把一行代码按分号切分为多条语句
+/// 
+/// # 参数
+/// * `line` - 已去除注释的源码行
+/// 
+/// # 返回值
+/// * `Vec<String>` - 各条语句的文本，其前面的部分（包括之前的语句和分号）替换为空格以保留列号；
+///   空语句（如 `x = 1;` 末尾分号之后的部分）不包含在内
+/// 
+/// # 说明
+/// 只在顶层切分：字符串字面量中的分号（如 `print("a;b")`）和花括号中的分号
+/// （写在同一行的函数体，如 `fun f() { x = 1; return x }`）不是语句的分隔符
+pub(crate) fn split_statements(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut separators = Vec::new();
+    let (mut depth, mut in_string) = (0usize, false);
+    let mut i = 0usize;
+    while i < chars.len() {
+        match chars[i] {
+            // 跳过转义字符（包括 `\"`）
+            '\\' if in_string => i += 1,
+            '"' => in_string = !in_string,
+            '{' if !in_string => depth += 1,
+            '}' if !in_string => depth = depth.saturating_sub(1),
+            ';' if !in_string && depth == 0 => separators.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    let mut segments = Vec::new();
+    let mut start = 0usize;
+    for end in separators.into_iter().chain([chars.len()]) {
+        let segment: String = chars[start..end].iter().collect();
+        if !segment.trim().is_empty() {
+            segments.push(format!("{}{}", " ".repeat(start), segment));
+        }
+        start = end + 1;
+    }
+    segments
+}
+
+/// 块注释替换为空白之后的源代码
+/// 
+/// # 字段
+/// * `text` - 块注释（包括 `/*` 和 `*/`）的每个字符替换为空格、换行保留原样后的源代码，
+///   因此行数和每行的字符数都不变，诊断信息中的行号和列号仍然对应原始源码
+/// * `continued_lines` - 从块注释内部开始的行号（多行块注释的第二行及之后各行，按行号排序）
+#[derive(Debug, Clone)]
+pub(crate) struct MaskedSource {
+    pub text: String,
+    pub continued_lines: Vec<usize>,
+}
+
+/// 把源代码中的块注释（`/* ... */`）替换为空白
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// 
+/// # 返回值
+/// * `Result<MaskedSource>` - 替换后的源代码；块注释没有闭合或嵌套时返回语法错误
+/// 
+/// # 规则
+/// 1. 块注释可以跨越多行，从 `/*` 开始到之后第一个 `*/` 为止
+/// 2. 字符串字面量和行注释（`//`，包括首行的shebang）中的 `/*` 不是块注释的开始
+/// 3. 不支持嵌套：块注释中再次出现 `/*` 时报告错误，而不是在第一个 `*/` 处结束后把剩余部分当作代码
+pub(crate) fn mask_block_comments(source: &str) -> Result<MaskedSource> {
+    let mut text = String::with_capacity(source.len());
+    let mut continued_lines = Vec::new();
+    let mut chars = source.chars().peekable();
+    let (mut line_no, mut col) = (1usize, 1usize);
+    let mut in_string = false;
+    let mut in_line_comment = source.starts_with("#!");
+    // 正在读取的块注释的起始位置（行号，列号）
+    let mut block_start: Option<(usize, usize)> = None;
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            text.push('\n');
+            line_no += 1;
+            col = 1;
+            // 字符串字面量和行注释都不会跨行
+            in_string = false;
+            in_line_comment = false;
+            if block_start.is_some() {
+                continued_lines.push(line_no);
+            }
+            continue;
+        }
+
+        if let Some((start_line, start_col)) = block_start {
+            let next = chars.peek().copied();
+            if c == '*' && next == Some('/') {
+                chars.next();
+                text.push_str("  ");
+                col += 2;
+                block_start = None;
+                continue;
+            }
+            if c == '/' && next == Some('*') {
+                let message = format!(
+                    "语法错误：块注释不能嵌套，第 {start_line} 行第 {start_col} 列开始的块注释中又出现了 `/*`（第 {line_no} 行，第 {col} 列）"
+                );
+                return Err(SyntaxError::new(line_no, Some(col), message).into());
+            }
+            text.push(' ');
+            col += 1;
+            continue;
+        }
+
+        match c {
+            '"' if !in_line_comment => in_string = !in_string,
+            '\\' if in_string && chars.peek().is_some_and(|&next| next != '\n') => {
+                // 转义字符（包括 `\"`）原样保留，不影响字符串的边界
+                text.push(c);
+                text.extend(chars.next());
+                col += 2;
+                continue;
+            }
+            '/' if !in_string && !in_line_comment => match chars.peek() {
+                Some('/') => in_line_comment = true,
+                Some('*') => {
+                    chars.next();
+                    text.push_str("  ");
+                    block_start = Some((line_no, col));
+                    col += 2;
+                    continue;
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        text.push(c);
+        col += 1;
+    }
+
+    if let Some((start_line, start_col)) = block_start {
+        let message = format!("语法错误：块注释 `/*` 没有对应的 `*/`（第 {start_line} 行，第 {start_col} 列）");
+        return Err(SyntaxError::new(start_line, Some(start_col), message).into());
+    }
+    Ok(MaskedSource { text, continued_lines })
+}
+
+/// 将表达式字符串切分为词法单元
+/// 
+/// # 参数
+/// * `s` - 要切分的表达式字符串
+/// * `line_no` - 行号（用于错误报告）
+/// * `col_offset` - `s`在源码行中之前的字符数（`s`从行首开始时为0）
+/// 
+/// # 返回值
+/// * `Result<Vec<Token>>` - 切分成功返回词法单元列表，失败返回错误信息
+/// 
+/// # 说明
+/// 列号按字符计算，加上`col_offset`后即为在源码行中的列号。
+/// 空白字符只用于分隔词法单元，因此 `a+b` 与 `a + b` 等价，所有运算符都是如此（如 `a*b`、`a==b`）。
+/// 除了数字指数部分的符号（`2e-3`）外，`-` 总是切分为单独的词法单元，由语法分析阶段根据位置区分减法和取负：
+/// `a-b` 是减法，`a- -b` 和 `a*-b` 中运算符之后的 `-` 是取负
+pub(crate) fn tokenize(s: &str, line_no: usize, col_offset: usize) -> Result<Vec<Token>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0usize;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        // 跳过空白
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let kind = match c {
+            '+' => { i += 1; TokenKind::Plus }
+            '-' => { i += 1; TokenKind::Minus }
+            '*' => { i += 1; TokenKind::Star }
+            '/' => { i += 1; TokenKind::Slash }
+            '%' => { i += 1; TokenKind::Percent }
+            '(' => { i += 1; TokenKind::LParen }
+            ')' => { i += 1; TokenKind::RParen }
+            ',' => { i += 1; TokenKind::Comma }
+            '<' | '>' | '=' | '!' => {
+                // 比较运算符：单字符或后跟 `=` 的双字符形式
+                let followed_by_eq = chars.get(i + 1) == Some(&'=');
+                let op = match (c, followed_by_eq) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    ('>', false) => CompareOp::Gt,
+                    ('>', true) => CompareOp::Ge,
+                    ('=', true) => CompareOp::Eq,
+                    ('!', true) => CompareOp::Ne,
+                    _ => syntax_bail!(line_no, "语法错误：无法识别的字符 `{c}`（第 {line_no} 行）"),
+                };
+                i += if followed_by_eq { 2 } else { 1 };
+                TokenKind::Compare(op)
+            }
+            '"' => {
+                // 字符串字面量：读取到下一个未转义的引号
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                if i >= chars.len() {
+                    return Err(unclosed_string(line_no, col_offset + start + 1).into());
+                }
+                i += 1;
+                let raw: String = chars[start + 1..i - 1].iter().collect();
+                match unescape(&raw) {
+                    Ok(s) => TokenKind::Str(s),
+                    Err(e) => {
+                        // 字符串内容从左引号之后开始
+                        let col = col_offset + start + 2 + e.index;
+                        let message = format!("语法错误：{}（第 {line_no} 行，第 {col} 列）", e.message());
+                        return Err(SyntaxError::new(line_no, Some(col), message).into());
+                    }
+                }
+            }
+            c if c.is_ascii_digit() => {
+                // 数字字面量：读取紧随其后的字母数字和小数点（小数点后必须是数字），
+                // 以及十进制数指数部分的正负号（如 `2e-3`，符号后必须是数字），交给语法分析阶段校验
+                let decimal = !(c == '0' && matches!(chars.get(i + 1), Some('x' | 'o' | 'b')));
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric()
+                        || chars[i] == '_'
+                        || (chars[i] == '.' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()))
+                        || (decimal
+                            && matches!(chars[i], '+' | '-')
+                            && matches!(chars[i - 1], 'e' | 'E')
+                            && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit())))
+                {
+                    i += 1;
+                }
+                TokenKind::Number(chars[start..i].iter().collect())
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                // 标识符：字母或下划线开头，后续为字母、数字或下划线
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                TokenKind::Ident(chars[start..i].iter().collect())
+            }
+            _ => syntax_bail!(line_no, "语法错误：无法识别的字符 `{c}`（第 {line_no} 行）"),
+        };
+
+        tokens.push(Token { kind, start: col_offset + start + 1, end: col_offset + i + 1 });
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_trailing_comment() {
+        assert_eq!(split_comment("x = 1 // c"), ("x = 1", Some("// c")));
+    }
+
+    #[test]
+    fn slashes_inside_string_are_not_a_comment() {
+        assert_eq!(split_comment("print(\"http://x\")"), ("print(\"http://x\")", None));
+    }
+
+    /// 切分表达式，返回各词法单元的类型
+    fn kinds(s: &str) -> Vec<TokenKind> {
+        tokenize(s, 1, 0).expect("expression should tokenize").into_iter().map(|t| t.kind).collect()
+    }
+
+    #[test]
+    fn operators_tokenize_regardless_of_whitespace() {
+        let ident = |name: &str| TokenKind::Ident(name.to_string());
+        let operators = [
+            ("+", TokenKind::Plus),
+            ("-", TokenKind::Minus),
+            ("*", TokenKind::Star),
+            ("/", TokenKind::Slash),
+            ("%", TokenKind::Percent),
+            ("<", TokenKind::Compare(CompareOp::Lt)),
+            ("<=", TokenKind::Compare(CompareOp::Le)),
+            (">", TokenKind::Compare(CompareOp::Gt)),
+            (">=", TokenKind::Compare(CompareOp::Ge)),
+            ("==", TokenKind::Compare(CompareOp::Eq)),
+            ("!=", TokenKind::Compare(CompareOp::Ne)),
+        ];
+        for (symbol, kind) in operators {
+            let expected = vec![ident("a"), kind, ident("b")];
+            for text in [format!("a{symbol}b"), format!("a {symbol} b"), format!("a  {symbol}\tb")] {
+                assert_eq!(kinds(&text), expected, "`{text}`");
+            }
+        }
+    }
+
+    #[test]
+    fn minus_after_an_operator_is_a_separate_token() {
+        // `a-b` 是减法；运算符之后的 `-` 由语法分析当作取负
+        assert_eq!(kinds("a-b"), kinds("a - b"));
+        assert_eq!(
+            kinds("a*-b"),
+            vec![TokenKind::Ident("a".to_string()), TokenKind::Star, TokenKind::Minus, TokenKind::Ident("b".to_string())]
+        );
+        assert_eq!(kinds("2e-3"), vec![TokenKind::Number("2e-3".to_string())]);
+    }
+
+    #[test]
+    fn masks_block_comments_but_not_strings() {
+        let masked = mask_block_comments("x = 1 /* a\n b */ + 2\nprint(\"/* not */\") // /* line\ny = 3 /**/\n").unwrap();
+        assert_eq!(masked.text, "x = 1     \n      + 2\nprint(\"/* not */\") // /* line\ny = 3     \n");
+        assert_eq!(masked.continued_lines, vec![2]);
+    }
+
+    #[test]
+    fn rejects_nested_and_unclosed_block_comments() {
+        let nested = mask_block_comments("/* a /* b */ */\n").unwrap_err();
+        assert!(nested.to_string().contains("块注释不能嵌套"), "{nested}");
+        assert!(mask_block_comments("x = 1 /* a\nprint(x)\n").is_err());
+    }
+}
@@ -1,3 +1,7 @@
+/// 词法分析模块
+/// 负责将表达式切分为词法单元
+mod lexer;
+
 /// 表达式解析模块
 /// 负责解析各种表达式，如字面量、标识符、二元运算等
 pub mod expr;
@@ -6,6 +10,14 @@ pub mod expr;
 /// 负责解析各种语句，如打印语句、赋值语句等
 pub mod stmt;
 
+/// 编译指示解析模块
+/// 负责解析源文件顶部的 `//!kairo ...` 编译选项
+pub mod pragma;
+
+/// 语法错误模块
+/// 定义带出错位置的语法错误类型
+pub mod error;
+
 /// 解析器驱动模块
 /// 协调各个解析模块，将源代码解析为抽象语法树
 mod driver;
@@ -13,3 +25,12 @@ mod driver;
 /// 导出解析函数
 /// 这是解析器模块的主要入口点
 pub use driver::parse as parse;
+
+/// 导出带错误恢复的解析函数（收集全部语法错误）
+pub use driver::parse_recovering;
+
+/// 导出编译指示解析函数
+pub use pragma::parse_pragma;
+
+/// 导出注释处理和语句切分函数（格式化时保留注释并找到函数的右花括号）
+pub(crate) use lexer::{mask_block_comments, split_comment, split_statements};
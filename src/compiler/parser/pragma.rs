@@ -0,0 +1,96 @@
+use anyhow::Result;
+
+use super::error::syntax_bail;
+use crate::compiler::codegen::rust::Overflow;
+
+/// 文件级编译指示
+/// 
+/// 通过源文件顶部的 `//!kairo ...` 注释行为单个文件设置编译选项，
+/// 例如 `//!kairo release` 或 `//!kairo overflow=wrap release`。
+/// 
+/// # 优先级
+/// 命令行参数 > 编译指示 > 默认值
+#[derive(Debug, Clone, Default)]
+pub struct Pragma {
+    /// 是否使用发布模式（None表示未在编译指示中指定）
+    pub release: Option<bool>,
+    /// 整数溢出的处理方式（None表示未在编译指示中指定）
+    pub overflow: Option<Overflow>,
+}
+
+/// 编译指示行的前缀
+const PRAGMA_PREFIX: &str = "//!kairo";
+
+/// 从源代码顶部解析编译指示
+/// 
+/// # 参数
+/// * `source` - 源代码字符串
+/// 
+/// # 返回值
+/// * `Result<Pragma>` - 解析成功返回编译指示设置，遇到未知选项返回错误
+/// 
+/// # 规则
+/// 1. 只识别第一条语句之前的行（空行、注释行和shebang行）
+/// 2. 一个文件可以有多行编译指示，后出现的选项覆盖先出现的
+/// 3. 支持的选项：`release`、`debug`、`overflow=check|wrap|saturate`
+pub fn parse_pragma(source: &str) -> Result<Pragma> {
+    let mut pragma = Pragma::default();
+
+    for (i, raw_line) in source.lines().enumerate() {
+        let line_no = i + 1;
+        let line_trim = raw_line.trim();
+
+        // shebang行和空行不影响编译指示的识别
+        if line_trim.is_empty() || (line_no == 1 && line_trim.starts_with("#!")) {
+            continue;
+        }
+
+        // 遇到第一条语句后停止识别
+        if !line_trim.starts_with("//") {
+            break;
+        }
+
+        let Some(rest) = line_trim.strip_prefix(PRAGMA_PREFIX) else {
+            continue;
+        };
+
+        // `//!kairox` 之类的注释不是编译指示
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            continue;
+        }
+
+        for option in rest.split_whitespace() {
+            match option {
+                "release" => pragma.release = Some(true),
+                "debug" => pragma.release = Some(false),
+                _ if option.starts_with("overflow=") => match option["overflow=".len()..].parse() {
+                    Ok(overflow) => pragma.overflow = Some(overflow),
+                    Err(_) => syntax_bail!(
+                        line_no,
+                        "编译指示错误：未知的溢出处理方式 `{option}`，可用的有 check、wrap、saturate（第 {line_no} 行）"
+                    ),
+                },
+                _ => syntax_bail!(line_no, "编译指示错误：未知的选项 `{option}`（第 {line_no} 行）"),
+            }
+        }
+    }
+
+    Ok(pragma)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_overflow_mode() {
+        let pragma = parse_pragma("//!kairo overflow=wrap release\nprint(1)\n").unwrap();
+        assert_eq!(pragma.overflow, Some(Overflow::Wrap));
+        assert_eq!(pragma.release, Some(true));
+    }
+
+    #[test]
+    fn rejects_unknown_overflow_mode() {
+        assert!(parse_pragma("//!kairo overflow=panic\n").is_err());
+    }
+}
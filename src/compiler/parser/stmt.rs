@@ -1,42 +1,115 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
 
-use crate::compiler::ast::{SourceSpan, Stmt};
+use crate::compiler::ast::{Expr, Param, SourceSpan, Stmt};
 
+use super::error::syntax_bail;
 use super::expr;
 
 /// 解析打印语句
 /// 
 /// # 参数
-/// * `line` - 要解析的行（已去除首尾空格）
+/// * `raw` - 原始行内容（用于计算各参数的列号）
 /// * `line_no` - 行号（用于错误报告）
 /// 
 /// # 返回值
 /// * `Result<Option<Stmt>>` - 如果是打印语句返回Some(Stmt::Print)，否则返回None
 /// 
 /// # 语法格式
-/// print("字符串内容")
+/// * `print()` - 输出空行（`print` 与括号之间、括号内侧可以有空白，如 `print ( "x" )`）
+/// * `print("字符串内容")` - 字符串中可以用 `{变量名}` 输出变量的值
+/// * `print(表达式, 表达式, ...)` - 依次输出各参数，以空格分隔
+/// * `print("x = {}, y = {}", x, y)` - 第一个参数含 `{}` 时作为格式字符串
 /// 
-/// # 限制
-/// 目前仅支持简单的字符串字面量，不支持转义字符
-pub(crate) fn parse_print(line: &str, line_no: usize) -> Result<Option<Stmt>> {
-    // 检查是否为print语句格式
-    if !line.starts_with("print(") || !line.ends_with(")") { 
-        return Ok(None); 
+/// # 转义字符
+/// 字符串中的 `\n` `\t` `\r` `\\` `\"` `\0` 在解析时解码为对应的字符
+pub(crate) fn parse_print(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
+    let line = raw.trim();
+
+    // 检查是否为print语句格式：`print` 与括号之间、括号与参数之间可以有空白
+    let Some(rest) = line.strip_prefix("print") else {
+        return Ok(None);
+    };
+    let Some(after_paren) = rest.trim_start().strip_prefix('(') else {
+        return Ok(None);
+    };
+    let Some(inner) = after_paren.strip_suffix(')') else {
+        return Ok(None);
+    };
+
+    // 计算括号内的内容在原始行中之前的字符数
+    let indent = raw.chars().count() - raw.trim_start().chars().count();
+    let inner_offset = indent + line.chars().count() - after_paren.chars().count();
+
+    // 按顶层逗号拆分参数，逐个解析为表达式
+    let mut args = Vec::new();
+    if !inner.trim().is_empty() {
+        for (piece_offset, piece) in split_top_level_commas(inner) {
+            if piece.trim().is_empty() {
+                syntax_bail!(line_no, "语法错误：print(...) 的参数不能为空（第 {line_no} 行）：{line}");
+            }
+            let lead = piece.chars().count() - piece.trim_start().chars().count();
+            args.push(expr::parse_expr(piece.trim(), line_no, inner_offset + piece_offset + lead)?);
+        }
     }
-    
-    // 提取括号内的内容
-    let inner = &line[6..line.len()-1];
-    let inner = inner.trim();
-    
-    // 仅支持简单的字符串字面量："..."
-    if !(inner.starts_with('"') && inner.ends_with('"') && inner.len() >= 2) {
-        bail!("语法错误：print(...) 仅支持字符串字面量，第 {line_no} 行");
+
+    let span = line_span(raw, line_no);
+    Ok(Some(Stmt::Print { args, span }))
+}
+
+/// 按顶层逗号拆分参数列表
+/// 
+/// # 参数
+/// * `s` - 参数列表文本（不包含外层括号）
+/// 
+/// # 返回值
+/// * `Vec<(usize, &str)>` - 各参数在`s`中的字符偏移及其文本（未去除空白）
+/// 
+/// # 说明
+/// 字符串字面量和括号内的逗号不作为分隔符，如 `"a, b", (1)` 拆分为两个参数
+fn split_top_level_commas(s: &str) -> Vec<(usize, &str)> {
+    let mut pieces = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut piece_start = (0usize, 0usize); // (字节偏移, 字符偏移)
+
+    for (char_idx, (byte_idx, c)) in s.char_indices().enumerate() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                pieces.push((piece_start.1, &s[piece_start.0..byte_idx]));
+                piece_start = (byte_idx + 1, char_idx + 1);
+            }
+            _ => {}
+        }
     }
-    
-    // TODO: 支持转义字符
-    let content = inner[1..inner.len()-1].to_string(); // 去掉首尾引号
-    let _span = SourceSpan::single_line(line_no, 1, line.len());
-    Ok(Some(Stmt::Print { content, _span }))
+    pieces.push((piece_start.1, &s[piece_start.0..]));
+    pieces
+}
+
+/// 计算一行语句的源码范围（不包含首尾空白）
+/// 
+/// # 参数
+/// * `raw` - 原始行内容
+/// * `line_no` - 行号
+/// 
+/// # 返回值
+/// * `SourceSpan` - 从第一个非空白字符到最后一个非空白字符之后的列范围
+fn line_span(raw: &str, line_no: usize) -> SourceSpan {
+    let start = raw.chars().count() - raw.trim_start().chars().count() + 1;
+    let end = raw.trim_end().chars().count() + 1;
+    SourceSpan::single_line(line_no, start, end)
 }
 
 /// 解析赋值语句
@@ -52,6 +125,7 @@ pub(crate) fn parse_print(line: &str, line_no: usize) -> Result<Option<Stmt>> {
 /// 变量名 = 表达式        // 不可变变量赋值
 /// $变量名 = 表达式       // 可变变量声明和赋值
 /// 变量名 = 表达式        // 已存在变量的重新赋值
+/// const 变量名 = 表达式  // 编译期常量声明（返回Stmt::Const）
 pub(crate) fn parse_assign(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
     // 快速路径：如果没有=号，则不是赋值语句
     let Some((lhs_raw, rhs_raw)) = raw.split_once('=') else { 
@@ -67,9 +141,23 @@ pub(crate) fn parse_assign(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
         i += 1; 
     }
     
+    // 检查是否有const关键字（常量声明）
+    let mut decl_const = false;
+    if lhs_raw[i..].starts_with("const") && lhs_raw[i + 5..].starts_with(|c: char| c.is_ascii_whitespace()) {
+        decl_const = true;
+        i += 5;
+        // 跳过const后的空格
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() { 
+            i += 1; 
+        }
+    }
+
     // 检查是否有$前缀（可变声明）
     let mut decl_mut = false;
     if i < bytes.len() && bytes[i] == b'$' {
+        if decl_const {
+            syntax_bail!(line_no, "语法错误：常量不能声明为可变（第 {line_no} 行）");
+        }
         decl_mut = true;
         i += 1;
         // 跳过$后的空格
@@ -105,20 +193,32 @@ pub(crate) fn parse_assign(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
     }
     
     let name = &lhs_raw[name_start..j];
+
+    // 布尔字面量不能用作变量名
+    if name == "true" || name == "false" {
+        syntax_bail!(line_no, "语法错误：`{name}` 是布尔字面量，不能用作变量名（第 {line_no} 行）");
+    }
     
     // 确保左值剩余部分只有空格
     let rest = &lhs_raw[j..];
     if rest.trim() != "" {
-        bail!("语法错误：无效的左值 `{}`（第 {line_no} 行）", lhs_raw.trim());
+        syntax_bail!(line_no, "语法错误：无效的左值 `{}`（第 {line_no} 行）", lhs_raw.trim());
     }
 
-    // 解析右值表达式
-    let expr = expr::parse_expr(rhs_raw.trim(), line_no)?;
-    let span = SourceSpan::single_line(line_no, 1, raw.len());
+    // 解析右值表达式（列偏移 = 等号及其之前的字符数 + 等号后的空格数）
+    let rhs = rhs_raw.trim();
+    let rhs_offset = raw.chars().count() - rhs_raw.trim_start().chars().count();
+    let expr = expr::parse_expr(rhs, line_no, rhs_offset)?;
+    let span = line_span(raw, line_no);
     
-    // 列号从1开始；长度为字节数（简化处理，假设ASCII）
-    let name_span = SourceSpan::single_line(line_no, name_start + 1, name_start + name.len() + 1);
+    // 列号从1开始，按Unicode字符计算（与表达式的列号一致）
+    let name_col = lhs_raw[..name_start].chars().count() + 1;
+    let name_span = SourceSpan::single_line(line_no, name_col, name_col + name.chars().count());
     
+    if decl_const {
+        return Ok(Some(Stmt::Const { name: name.to_string(), expr, span, name_span }));
+    }
+
     Ok(Some(Stmt::Assign { 
         name: name.to_string(), 
         decl_mut, 
@@ -127,3 +227,244 @@ pub(crate) fn parse_assign(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
         name_span 
     }))
 }
+
+/// 解析返回语句
+/// 
+/// # 参数
+/// * `raw` - 原始行内容
+/// * `line_no` - 行号（用于错误报告）
+/// 
+/// # 返回值
+/// * `Result<Option<Stmt>>` - 如果是返回语句返回Some(Stmt::Return)，否则返回None
+/// 
+/// # 语法格式
+/// * `return 表达式` - 返回表达式的值
+/// * `return` - 不返回值，直接结束函数
+pub(crate) fn parse_return(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
+    let line = raw.trim();
+    let span = line_span(raw, line_no);
+    if line == "return" {
+        return Ok(Some(Stmt::Return { expr: None, span }));
+    }
+    let Some(rest) = line.strip_prefix("return").filter(|rest| rest.starts_with(char::is_whitespace)) else {
+        return Ok(None);
+    };
+
+    let rhs = rest.trim();
+    let rhs_offset = raw.trim_end().chars().count() - rhs.chars().count();
+    let expr = expr::parse_expr(rhs, line_no, rhs_offset)?;
+    Ok(Some(Stmt::Return { expr: Some(expr), span }))
+}
+
+/// 解析函数调用语句
+/// 
+/// # 参数
+/// * `raw` - 原始行内容
+/// * `line_no` - 行号（用于错误报告）
+/// 
+/// # 返回值
+/// * `Result<Option<Stmt>>` - 如果是单独一行的函数调用返回Some(Stmt::Expr)，否则返回None
+/// 
+/// # 语法格式
+/// 函数名(实参, ...)     // 返回值（如果有）被丢弃
+pub(crate) fn parse_call(raw: &str, line_no: usize) -> Result<Option<Stmt>> {
+    let line = raw.trim();
+    let after_name = line.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let looks_like_call = line.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && after_name.trim_start().starts_with('(')
+        && line.ends_with(')');
+    if !looks_like_call {
+        return Ok(None);
+    }
+
+    let indent = raw.chars().count() - raw.trim_start().chars().count();
+    let expr = expr::parse_expr(line, line_no, indent)?;
+    // 单独一行的 `read()` 跳过一行输入
+    if !matches!(expr, Expr::Call(..) | Expr::Read(..)) {
+        syntax_bail!(line_no, "语法错误：表达式的值没有被使用，单独一行只能写函数调用（第 {line_no} 行）：{line}");
+    }
+    Ok(Some(Stmt::Expr { expr, span: line_span(raw, line_no) }))
+}
+
+/// 为无法解析的语句生成占位的语句节点（用于错误恢复）
+/// 
+/// # 参数
+/// * `raw` - 原始行内容
+/// * `line_no` - 行号
+/// 
+/// # 返回值
+/// * `Stmt` - `Stmt::Invalid`；语句以 `变量名 =`、`$变量名 =` 或 `const 变量名 =` 开头时记录其变量名
+pub(crate) fn invalid_stmt(raw: &str, line_no: usize) -> Stmt {
+    let line = raw.trim();
+    let line = line.strip_prefix("const").filter(|rest| rest.starts_with(char::is_whitespace)).unwrap_or(line);
+    let line = line.trim_start();
+    let line = line.strip_prefix('$').unwrap_or(line).trim_start();
+    let after_name = line.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_');
+    let name = &line[..line.len() - after_name.len()];
+    let after_name = after_name.trim_start();
+    let is_assign = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && !matches!(name, "true" | "false")
+        && after_name.starts_with('=')
+        && !after_name.starts_with("==");
+    Stmt::Invalid { name: is_assign.then(|| name.to_string()), span: line_span(raw, line_no) }
+}
+
+/// 函数头的解析结果
+/// 
+/// # 字段
+/// * `name` - 函数名
+/// * `params` - 参数列表
+/// * `ret` - 返回值类型注解
+/// * `span` - 函数头所在行的源码位置
+/// * `name_span` - 函数名的源码位置
+/// * `inline_body` - 与函数头写在同一行的函数体：`{` 之前的部分替换为空格（以保留列号），
+///   不包含右花括号；函数体从下一行开始时为None
+pub(crate) struct FnHeader {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub ret: Option<String>,
+    pub span: SourceSpan,
+    pub name_span: SourceSpan,
+    pub inline_body: Option<String>,
+}
+
+impl FnHeader {
+    /// 与函数体组合为函数定义语句
+    pub fn into_stmt(self, body: Vec<Stmt>) -> Stmt {
+        Stmt::FnDef {
+            name: self.name,
+            params: self.params,
+            ret: self.ret,
+            body,
+            span: self.span,
+            name_span: self.name_span,
+        }
+    }
+}
+
+/// 解析函数头
+/// 
+/// # 参数
+/// * `raw` - 原始行内容
+/// * `line_no` - 行号（用于错误报告）
+/// 
+/// # 返回值
+/// * `Result<Option<FnHeader>>` - 如果是函数定义返回Some(FnHeader)，否则返回None
+/// 
+/// # 语法格式
+/// fun 函数名(参数, 参数: 类型, ...) -> 返回值类型 {     // 函数体从下一行开始，以单独一行的 `}` 结束
+/// fun 函数名(参数, ...) { 语句; 语句 }                  // 函数体较短时可以写在同一行，多条语句以分号分隔
+/// 
+/// `fun` 也可以写成 `fn`；参数的类型注解和返回值类型注解都可以省略
+pub(crate) fn parse_fn_header(raw: &str, line_no: usize) -> Result<Option<FnHeader>> {
+    let chars: Vec<char> = raw.chars().collect();
+    let skip_ws = |i: &mut usize| {
+        while *i < chars.len() && chars[*i].is_whitespace() {
+            *i += 1;
+        }
+    };
+    let read_ident = |i: &mut usize| -> Option<(String, usize)> {
+        let start = *i;
+        if !chars.get(start).is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') {
+            return None;
+        }
+        while *i < chars.len() && (chars[*i].is_ascii_alphanumeric() || chars[*i] == '_') {
+            *i += 1;
+        }
+        Some((chars[start..*i].iter().collect(), start))
+    };
+
+    // 关键字、函数名和左括号都匹配时才是函数定义（`fun = 1` 仍是赋值语句）
+    let mut i = 0usize;
+    skip_ws(&mut i);
+    let Some((keyword, _)) = read_ident(&mut i) else {
+        return Ok(None);
+    };
+    if keyword != "fun" && keyword != "fn" {
+        return Ok(None);
+    }
+    skip_ws(&mut i);
+    let Some((name, name_start)) = read_ident(&mut i) else {
+        return Ok(None);
+    };
+    let name_span = SourceSpan::single_line(line_no, name_start + 1, i + 1);
+    skip_ws(&mut i);
+    if chars.get(i) != Some(&'(') {
+        return Ok(None);
+    }
+    i += 1;
+
+    if name == "true" || name == "false" {
+        syntax_bail!(line_no, "语法错误：`{name}` 是布尔字面量，不能用作函数名（第 {line_no} 行）");
+    }
+
+    // 参数列表：参数名 或 参数名: 类型，以逗号分隔
+    let mut params: Vec<Param> = Vec::new();
+    skip_ws(&mut i);
+    if chars.get(i) == Some(&')') {
+        i += 1;
+    } else {
+        loop {
+            skip_ws(&mut i);
+            let Some((param, param_start)) = read_ident(&mut i) else {
+                syntax_bail!(line_no, "语法错误：函数 `{name}` 的参数列表中应为参数名（第 {line_no} 行）");
+            };
+            let mut param_end = i;
+            skip_ws(&mut i);
+            let mut ty = None;
+            if chars.get(i) == Some(&':') {
+                i += 1;
+                skip_ws(&mut i);
+                let Some((ty_name, _)) = read_ident(&mut i) else {
+                    syntax_bail!(line_no, "语法错误：参数 `{param}` 的 `:` 之后应为类型名（第 {line_no} 行）");
+                };
+                ty = Some(ty_name);
+                param_end = i;
+                skip_ws(&mut i);
+            }
+            if params.iter().any(|p| p.name == param) {
+                syntax_bail!(line_no, "语法错误：函数 `{name}` 有多个名为 `{param}` 的参数（第 {line_no} 行）");
+            }
+            params.push(Param { name: param, ty, span: SourceSpan::single_line(line_no, param_start + 1, param_end + 1) });
+
+            match chars.get(i) {
+                Some(',') => i += 1,
+                Some(')') => {
+                    i += 1;
+                    break;
+                }
+                _ => syntax_bail!(line_no, "语法错误：函数 `{name}` 的参数之间应以逗号分隔，并以 `)` 结束（第 {line_no} 行）"),
+            }
+        }
+    }
+
+    // 返回值类型注解：-> 类型
+    skip_ws(&mut i);
+    let mut ret = None;
+    if chars.get(i) == Some(&'-') && chars.get(i + 1) == Some(&'>') {
+        i += 2;
+        skip_ws(&mut i);
+        let Some((ty_name, _)) = read_ident(&mut i) else {
+            syntax_bail!(line_no, "语法错误：函数 `{name}` 的 `->` 之后应为返回值类型（第 {line_no} 行）");
+        };
+        ret = Some(ty_name);
+        skip_ws(&mut i);
+    }
+
+    if chars.get(i) != Some(&'{') {
+        syntax_bail!(line_no, "语法错误：函数 `{name}` 的函数体应以 `{{` 开始（第 {line_no} 行）");
+    }
+    i += 1;
+
+    // 左花括号之后还有内容时，函数体必须在同一行以右花括号结束
+    let rest: String = chars[i..].iter().collect();
+    let inline_body = if rest.trim().is_empty() {
+        None
+    } else if let Some(body) = rest.trim_end().strip_suffix('}') {
+        Some(format!("{}{}", " ".repeat(i), body))
+    } else {
+        syntax_bail!(line_no, "语法错误：函数 `{name}` 的函数体写在同一行时应以 `}}` 结束，否则请从下一行开始写函数体（第 {line_no} 行）");
+    };
+
+    Ok(Some(FnHeader { name, params, ret, span: line_span(raw, line_no), name_span, inline_body }))
+}
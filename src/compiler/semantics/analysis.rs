@@ -1,30 +1,71 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 
-use crate::compiler::ast::{Expr, Program, SourceSpan, Stmt};
-use super::diagnostics::{caret_line, get_line, render_error};
+use crate::compiler::ast::{Expr, Param, Program, SourceSpan, Stmt};
+use super::diagnostics::{get_line, Diagnostic, Diagnostics, Severity};
+use super::fold::fold_const;
+use super::format::{is_format_print, parse_placeholders, Placeholder};
+use super::scope::ScopeStack;
+use super::types::{infer_type, FnSig, Type};
 
 /// 变量的可变性类型
 /// 
 /// # 变体
 /// * `Immutable` - 不可变变量（默认）
 /// * `Mutable` - 可变变量（使用$前缀声明）
+/// * `Const` - 编译期常量（使用const关键字声明）
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mutability { 
     /// 不可变变量
     Immutable, 
     /// 可变变量
-    Mutable 
+    Mutable,
+    /// 编译期常量
+    Const,
 }
 
 /// 语义分析信息
-/// 包含程序中的所有变量及其可变性信息
+/// 包含程序中的所有变量及其可变性、类型信息
 #[derive(Debug, Default)]
 pub struct SemanticInfo {
     /// 变量名到可变性的映射表
     pub vars: HashMap<String, Mutability>,
+    /// 变量名到类型的映射表（由首次赋值的表达式推断）
+    pub types: HashMap<String, Type>,
+    /// 常量名到初始值表达式的映射表（用于在使用处内联）
+    pub consts: HashMap<String, Expr>,
+    /// 不影响编译的警告信息
+    pub warnings: Vec<Diagnostic>,
+    /// 函数名到函数签名的映射表
+    pub functions: HashMap<String, FnSig>,
+    /// 函数名到函数体符号表的映射表（函数体中的参数和局部变量与顶层变量相互独立）
+    pub bodies: HashMap<String, SemanticInfo>,
+}
+
+/// 语义检查选项
+#[derive(Debug, Clone, Default)]
+pub struct SemanticOptions {
+    /// 宽松模式：重新赋值不可变变量只报告警告，
+    /// 生成代码时用新的let绑定遮蔽原变量（便于快速原型开发）
+    pub lenient: bool,
+    /// 对没有任何效果的语句（如把变量赋值给它自己）报告警告
+    pub warn_no_effect: bool,
+    /// 对与顶层常量同名（遮蔽了常量）的函数参数报告警告
+    /// （函数体中的局部变量不能与顶层常量同名，总是报告错误）
+    pub warn_shadow: bool,
+    /// 不对声明后从未使用的变量报告警告（如REPL中变量通常在之后的输入中才使用）
+    pub allow_unused: bool,
+    /// 把所有警告当作错误报告（语义检查因此失败）
+    pub deny_warnings: bool,
+}
+
+impl SemanticInfo {
+    /// 推断表达式的类型（基于已记录的变量类型）
+    pub fn type_of(&self, expr: &Expr) -> Option<Type> {
+        infer_type(expr, &self.types, &self.functions)
+    }
 }
 
 /// 执行语义检查（不可变性规则）并构建符号表
@@ -33,6 +74,7 @@ pub struct SemanticInfo {
 /// * `program` - 程序的抽象语法树
 /// * `file` - 源文件路径（用于错误报告）
 /// * `source` - 源代码字符串（用于错误报告）
+/// * `options` - 语义检查选项
 /// 
 /// # 返回值
 /// * `Result<SemanticInfo>` - 语义分析成功返回符号表，失败返回错误信息
@@ -42,22 +84,323 @@ pub struct SemanticInfo {
 ///    - $变量名 = 值：声明可变变量，不能重复声明
 ///    - 变量名 = 值：声明不可变变量或重新赋值
 /// 2. 不可变性规则：
-///    - 不可变变量不能重新赋值
+///    - 不可变变量不能重新赋值（宽松模式下只报告警告）
 ///    - 可变变量可以重新赋值
 /// 3. 未定义变量检查：
 ///    - 表达式中使用的变量必须已声明
-pub fn check_semantics(program: &Program, file: &Path, source: &str) -> Result<SemanticInfo> {
+///    - 不能除以编译期就能确定为0的值（如字面量0）
+///    - print字符串中的 `{变量名}` 必须引用已声明的变量，
+///      格式字符串中 `{}` 的个数必须与其余参数的个数一致
+///    - 布尔值不能参与算术运算
+///    - typeof(x) 中表达式的类型必须能在编译期确定
+///    - 二元运算两侧的类型必须兼容（如字符串只能与字符串相加，不会自动把数字转换为字符串）
+///    - 重新赋值时新值的类型必须与变量首次赋值时的类型相同
+///    - 带多余前导零的整数字面量（如 `007`）报告警告
+///    - 声明后从未被读取的变量报告警告（以 `_` 开头的变量名除外）
+/// 4. 常量规则：
+///    - const 变量名 = 值：初始值只能由字面量和之前声明的常量组成
+///    - 常量不能重新赋值或重复声明
+/// 5. 函数规则：
+///    - 函数不能重复定义，变量和常量不能与函数同名
+///    - 调用的函数必须已定义，实参的个数和类型必须与参数一致
+///    - 没有返回值的函数只能单独一行调用，不能用在表达式中
+///    - 函数体中只能使用参数、局部变量和顶层常量，不能使用顶层的变量
+///    - return的值必须与返回值类型一致，有返回值的函数必须以return语句结束
+///    - 无法推断返回值类型的函数（如只返回递归调用的结果）必须注明返回值类型
+/// 
+/// 指定了`deny_warnings`时，所有警告都改为错误
+pub fn check_semantics(program: &Program, file: &Path, source: &str, options: &SemanticOptions) -> Result<SemanticInfo> {
     let mut info = SemanticInfo::default();
-    let mut errors: Vec<String> = Vec::new();
+    let mut errors: Vec<Diagnostic> = Vec::new();
+
+    // 第一遍：收集函数签名（函数可以在定义之前调用），并推断省略的返回值类型
+    collect_functions(program, &mut info, file, source, &mut errors);
+    infer_return_types(program, &mut info, file, source, options);
+
+    // 第二遍：处理声明和可变性规则（每个函数体有独立的符号表）
+    declare_block(&program.statements, &mut info, file, source, options, &mut errors);
+    for stmt in &program.statements {
+        if let Stmt::FnDef { name, params, body, .. } = stmt
+            && let Some(sig) = info.functions.get(name)
+        {
+            if options.warn_shadow {
+                check_shadowed_consts(program, params, file, source, &mut info.warnings);
+            }
+            let mut local = body_table(sig, &info);
+            declare_block(body, &mut local, file, source, options, &mut errors);
+            info.warnings.append(&mut local.warnings);
+            info.bodies.insert(name.clone(), local);
+        }
+    }
+
+    // 第三遍：检查表达式中未定义的变量（按语句顺序在作用域栈中登记声明）
+    let mut scopes = ScopeStack::default();
+    let mut warnings = Vec::new();
+    check_block(&program.statements, &info, &info, &mut scopes, None, file, source, &mut errors, &mut warnings);
+    info.warnings.append(&mut warnings);
+
+    // 警告当作错误时，改变其严重程度后与错误一起报告
+    if options.deny_warnings {
+        errors.extend(info.warnings.drain(..).map(|mut warning| {
+            warning.severity = Severity::Error;
+            warning
+        }));
+    }
+
+    // 如果有错误，返回所有错误信息
+    if !errors.is_empty() {
+        return Err(Diagnostics(errors).into());
+    }
+
+    Ok(info)
+}
+
+/// 收集程序中定义的函数签名
+/// 
+/// # 参数
+/// * `program` - 程序的抽象语法树
+/// * `info` - 语义分析信息（函数签名写入`functions`）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 检查规则
+/// 1. 函数不能重复定义，也不能使用保留的名称（如 `main`、`print`）
+/// 2. 参数和返回值的类型注解必须是 int、float、string、bool 之一
+fn collect_functions(program: &Program, info: &mut SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    for stmt in &program.statements {
+        let Stmt::FnDef { name, params, ret, body, span, name_span } = stmt else {
+            continue;
+        };
 
-    // 第一遍：处理声明和可变性规则
+        if RESERVED_FN_NAMES.contains(&name.as_str()) {
+            errors.push(friendly_error_reserved_fn(file, source, name, *name_span));
+            continue;
+        }
+        if info.functions.contains_key(name) {
+            errors.push(friendly_error_redefine_fn(file, source, name, *name_span));
+            continue;
+        }
+
+        let mut resolve = |ty: &Option<String>, span: SourceSpan| match ty {
+            None => None,
+            Some(ty_name) => {
+                let ty = Type::from_name(ty_name);
+                if ty.is_none() {
+                    errors.push(friendly_error_unknown_type(file, source, ty_name, span));
+                }
+                ty
+            }
+        };
+        let param_types: Vec<(String, Type)> = params
+            .iter()
+            .map(|p| (p.name.clone(), resolve(&p.ty, p.span).unwrap_or(Type::Int)))
+            .collect();
+        let ret = resolve(ret, *span);
+        // 函数体中有无法解析的语句时（可能是带值的return），按有返回值处理，避免连带的错误
+        let returns_value = ret.is_some()
+            || body.iter().any(|stmt| matches!(stmt, Stmt::Return { expr: Some(_), .. } | Stmt::Invalid { .. }));
+        info.functions.insert(name.clone(), FnSig { params: param_types, ret, returns_value });
+    }
+}
+
+/// 函数名不能使用的保留名称（入口函数和内置语句）
+const RESERVED_FN_NAMES: [&str; 9] = ["main", "print", "typeof", "read", "read_int", "return", "const", "fun", "fn"];
+
+/// 推断省略了返回值类型注解的函数的返回值类型
+/// 
+/// # 参数
+/// * `program` - 程序的抽象语法树
+/// * `info` - 语义分析信息（推断出的类型写入`functions`中的函数签名）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `options` - 语义检查选项
+/// 
+/// # 推断规则
+/// 返回值类型为函数体中第一个能确定类型的 `return 表达式` 的类型；
+/// 没有带值的return语句时函数没有返回值。
+/// 函数之间可能互相调用，因此重复推断直到没有新的返回值类型被确定。
+/// 这里只做推断，错误在之后的检查中报告
+fn infer_return_types(program: &Program, info: &mut SemanticInfo, file: &Path, source: &str, options: &SemanticOptions) {
+    // 函数体中可以使用顶层常量，先确定它们的类型
+    let mut consts = SemanticInfo { functions: info.functions.clone(), ..Default::default() };
     for stmt in &program.statements {
+        if let Stmt::Const { name, expr, .. } = stmt
+            && !consts.vars.contains_key(name)
+        {
+            if let Some(ty) = consts.type_of(expr) {
+                consts.types.insert(name.clone(), ty);
+            }
+            consts.vars.insert(name.clone(), Mutability::Const);
+            consts.consts.insert(name.clone(), expr.clone());
+        }
+    }
+
+    loop {
+        let mut changed = false;
+        for stmt in &program.statements {
+            let Stmt::FnDef { name, ret: None, body, .. } = stmt else {
+                continue;
+            };
+            let Some(sig) = info.functions.get(name).filter(|sig| sig.ret.is_none()) else {
+                continue;
+            };
+
+            consts.functions = info.functions.clone();
+            let mut local = body_table(sig, &consts);
+            declare_block(body, &mut local, file, source, options, &mut Vec::new());
+            let ret = body.iter().find_map(|stmt| match stmt {
+                Stmt::Return { expr: Some(expr), .. } => local.type_of(expr),
+                _ => None,
+            });
+
+            if let Some(ty) = ret
+                && let Some(sig) = info.functions.get_mut(name)
+            {
+                sig.ret = Some(ty);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// 创建函数体的符号表
+/// 
+/// # 参数
+/// * `sig` - 函数签名
+/// * `info` - 顶层的语义分析信息
+/// 
+/// # 返回值
+/// * `SemanticInfo` - 包含参数（不可变）、顶层常量和所有函数签名的符号表；
+///   函数体看不到顶层的变量
+fn body_table(sig: &FnSig, info: &SemanticInfo) -> SemanticInfo {
+    let mut local = SemanticInfo {
+        functions: info.functions.clone(),
+        consts: info.consts.clone(),
+        ..Default::default()
+    };
+    for name in info.consts.keys() {
+        local.vars.insert(name.clone(), Mutability::Const);
+        if let Some(ty) = info.types.get(name) {
+            local.types.insert(name.clone(), *ty);
+        }
+    }
+    for (param, ty) in &sig.params {
+        local.vars.insert(param.clone(), Mutability::Immutable);
+        local.types.insert(param.clone(), *ty);
+    }
+    local
+}
+
+/// 检查函数参数是否遮蔽了顶层常量
+/// 
+/// # 参数
+/// * `program` - 程序的抽象语法树（用于找到常量的声明位置）
+/// * `params` - 函数的参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `warnings` - 警告信息列表（用于收集警告）
+/// 
+/// # 说明
+/// 函数体只能看到外层的常量，因此只有参数会遮蔽外层的声明；
+/// 函数体中与顶层常量同名的局部变量不是遮蔽，而是错误（见`friendly_error_local_named_const`）
+fn check_shadowed_consts(program: &Program, params: &[Param], file: &Path, source: &str, warnings: &mut Vec<Diagnostic>) {
+    for param in params {
+        let declaration = program.statements.iter().find_map(|stmt| match stmt {
+            Stmt::Const { name, name_span, .. } if *name == param.name => Some(*name_span),
+            _ => None,
+        });
+        if let Some(const_span) = declaration {
+            warnings.push(friendly_warning_shadow(file, source, param, const_span));
+        }
+    }
+}
+
+/// 处理语句列表中的声明和可变性规则，构建符号表
+/// 
+/// # 参数
+/// * `stmts` - 顶层或函数体中的语句
+/// * `info` - 当前代码块的符号表
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `options` - 语义检查选项
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 说明
+/// 函数定义在这里跳过，函数体由调用者用独立的符号表处理。
+/// 同时记录每条语句读取的变量，最后对声明后从未被读取的变量报告警告
+fn declare_block(
+    stmts: &[Stmt],
+    info: &mut SemanticInfo,
+    file: &Path,
+    source: &str,
+    options: &SemanticOptions,
+    errors: &mut Vec<Diagnostic>,
+) {
+    // 处理函数体时，符号表中已有的常量都来自顶层
+    let outer_consts: HashSet<String> = info.consts.keys().cloned().collect();
+
+    // 声明的变量（按首次声明的顺序）和被读取过的名称
+    let mut declared: Vec<(&str, SourceSpan)> = Vec::new();
+    let mut read: HashSet<String> = HashSet::new();
+
+    for stmt in stmts {
+        collect_reads(stmt, &mut read);
+        if let Stmt::Assign { name, name_span, .. } = stmt
+            && !info.vars.contains_key(name)
+            && !info.functions.contains_key(name)
+        {
+            declared.push((name, *name_span));
+        }
+
         match stmt {
-            Stmt::Print { .. } => {
-                // 打印语句不需要语义检查
+            Stmt::Print { .. } | Stmt::Return { .. } | Stmt::Expr { .. } | Stmt::FnDef { .. } => {
+                // 这些语句不声明变量
             }
-            Stmt::Assign { name, decl_mut, span: _span, name_span, .. } => {
+            Stmt::Invalid { name: Some(name), .. } => {
+                // 无法解析的赋值：当作类型未知的可变变量，之后的使用和赋值不再报告连带的错误
+                info.vars.entry(name.clone()).or_insert(Mutability::Mutable);
+            }
+            Stmt::Invalid { name: None, .. } => {}
+            Stmt::Assign { name, decl_mut, expr, span, name_span } => {
+                // 变量不能与函数同名
+                if info.functions.contains_key(name) {
+                    errors.push(friendly_error_name_is_fn(file, source, name, *name_span));
+                    continue;
+                }
+
                 let existed = info.vars.get(name).cloned();
+
+                // 把已存在的变量赋值给它自己（`x = x`）不会有任何效果
+                // （不可变变量在非宽松模式下已报告重新赋值的错误）
+                if options.warn_no_effect
+                    && !*decl_mut
+                    && matches!(expr, Expr::Ident(rhs, _) if rhs == name)
+                    && (existed == Some(Mutability::Mutable)
+                        || (existed == Some(Mutability::Immutable) && options.lenient))
+                {
+                    info.warnings.push(friendly_warning_self_assign(file, source, name, *span));
+                }
+
+                // 新变量的类型由首次赋值的表达式推断；重新赋值时类型必须相同
+                match (existed, info.types.get(name).copied(), info.type_of(expr)) {
+                    (None, _, Some(ty)) => {
+                        info.types.insert(name.clone(), ty);
+                    }
+                    // 不可变变量只有宽松模式下才允许重新赋值，否则已报告不可变错误
+                    (Some(mutability), Some(expected), Some(found))
+                        if !*decl_mut
+                            && expected != found
+                            && (mutability == Mutability::Mutable
+                                || (mutability == Mutability::Immutable && options.lenient)) =>
+                    {
+                        errors.push(friendly_error_type_mismatch(file, source, name, expected, found, expr.span()));
+                    }
+                    _ => {}
+                }
                 
                 if *decl_mut {
                     // 处理可变变量声明（$前缀）
@@ -66,6 +409,10 @@ pub fn check_semantics(program: &Program, file: &Path, source: &str) -> Result<S
                             // 新声明，添加到符号表
                             info.vars.insert(name.clone(), Mutability::Mutable); 
                         }
+                        Some(Mutability::Const) if outer_consts.contains(name) => {
+                            // 函数体中声明与顶层常量同名的局部变量，报告错误
+                            errors.push(friendly_error_local_named_const(file, source, name, *name_span));
+                        }
                         Some(_) => {
                             // 重复声明，报告错误
                             errors.push(friendly_error_redeclare(file, source, name, *name_span));
@@ -78,6 +425,10 @@ pub fn check_semantics(program: &Program, file: &Path, source: &str) -> Result<S
                             // 新声明，添加到符号表
                             info.vars.insert(name.clone(), Mutability::Immutable); 
                         }
+                        Some(Mutability::Immutable) if options.lenient => {
+                            // 宽松模式：允许重新赋值（生成代码时遮蔽原变量），只报告警告
+                            info.warnings.push(friendly_warning_assign_immutable(file, source, name, *name_span));
+                        }
                         Some(Mutability::Immutable) => {
                             // 试图修改不可变变量，报告错误
                             errors.push(friendly_error_assign_immutable(file, source, name, *name_span));
@@ -85,39 +436,230 @@ pub fn check_semantics(program: &Program, file: &Path, source: &str) -> Result<S
                         Some(Mutability::Mutable) => { 
                             // 修改可变变量，允许
                         }
+                        Some(Mutability::Const) if outer_consts.contains(name) => {
+                            // 函数体中声明与顶层常量同名的局部变量，报告错误
+                            errors.push(friendly_error_local_named_const(file, source, name, *name_span));
+                        }
+                        Some(Mutability::Const) => {
+                            // 试图修改常量，报告错误
+                            errors.push(friendly_error_assign_const(file, source, name, *name_span));
+                        }
+                    }
+                }
+            }
+            Stmt::Const { name, expr, name_span, .. } => {
+                if info.functions.contains_key(name) {
+                    // 常量不能与函数同名
+                    errors.push(friendly_error_name_is_fn(file, source, name, *name_span));
+                } else if info.vars.contains_key(name) {
+                    // 重复声明，报告错误
+                    errors.push(friendly_error_redeclare(file, source, name, *name_span));
+                } else {
+                    if let Some(ty) = info.type_of(expr) {
+                        info.types.insert(name.clone(), ty);
                     }
+                    info.vars.insert(name.clone(), Mutability::Const);
+                    info.consts.insert(name.clone(), expr.clone());
                 }
             }
         }
     }
 
-    // 第二遍：检查表达式中未定义的变量
-    let mut declared: HashMap<&str, Mutability> = HashMap::new();
-    for stmt in &program.statements {
+    if !options.allow_unused {
+        for (name, name_span) in declared {
+            if !name.starts_with('_') && !read.contains(name) {
+                info.warnings.push(friendly_warning_unused(file, source, name, name_span));
+            }
+        }
+    }
+}
+
+/// 记录语句中读取的全部名称（函数体由其自己的`declare_block`处理）
+/// 
+/// # 说明
+/// 表达式中的标识符和print格式字符串中的 `{变量名}` 都算作读取
+fn collect_reads(stmt: &Stmt, read: &mut HashSet<String>) {
+    match stmt {
+        Stmt::Print { args, .. } => {
+            let format_print = is_format_print(args);
+            for (i, arg) in args.iter().enumerate() {
+                // 与`check_print_format`相同：格式字符串之后的参数按普通值输出
+                if let Expr::StringLit(content, _) = arg
+                    && (i == 0 || !format_print)
+                    && let Ok(placeholders) = parse_placeholders(content)
+                {
+                    read.extend(placeholders.into_iter().filter_map(|placeholder| match placeholder {
+                        Placeholder::Named(name) => Some(name),
+                        Placeholder::Positional => None,
+                    }));
+                }
+                collect_expr_reads(arg, read);
+            }
+        }
+        Stmt::Assign { expr, .. } | Stmt::Const { expr, .. } | Stmt::Expr { expr, .. } => collect_expr_reads(expr, read),
+        Stmt::Return { expr: Some(expr), .. } => collect_expr_reads(expr, read),
+        Stmt::Return { expr: None, .. } | Stmt::FnDef { .. } | Stmt::Invalid { .. } => {}
+    }
+}
+
+/// 递归记录表达式中读取的标识符
+fn collect_expr_reads(expr: &Expr, read: &mut HashSet<String>) {
+    match expr {
+        Expr::Ident(name, _) => {
+            read.insert(name.clone());
+        }
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            collect_expr_reads(a, read);
+            collect_expr_reads(b, read);
+        }
+        Expr::Group(inner, _) | Expr::TypeOf(inner, _) | Expr::Neg(inner, _) => collect_expr_reads(inner, read),
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                collect_expr_reads(arg, read);
+            }
+        }
+        Expr::StringLit(..) | Expr::IntLit(..) | Expr::FloatLit(..) | Expr::BoolLit(..) | Expr::Read(..) => {}
+    }
+}
+
+/// 检查语句列表中的表达式
+/// 
+/// # 参数
+/// * `stmts` - 顶层或函数体中的语句
+/// * `program_info` - 顶层的语义分析信息（用于找到各函数体的符号表）
+/// * `info` - 当前代码块的符号表
+/// * `scopes` - 作用域栈（按语句顺序登记声明）
+/// * `current_fn` - 当前所在的函数名（顶层为None）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// * `warnings` - 警告信息列表（用于收集警告）
+#[allow(clippy::too_many_arguments)]
+fn check_block<'a>(
+    stmts: &'a [Stmt],
+    program_info: &SemanticInfo,
+    info: &SemanticInfo,
+    scopes: &mut ScopeStack<'a>,
+    current_fn: Option<&str>,
+    file: &Path,
+    source: &str,
+    errors: &mut Vec<Diagnostic>,
+    warnings: &mut Vec<Diagnostic>,
+) {
+    // 对表达式执行的各项检查
+    // `declaring`为正在首次声明的变量名（表达式是它的初始值）
+    let check_expr = |expr: &Expr,
+                      scopes: &ScopeStack,
+                      value_needed: bool,
+                      declaring: Option<&str>,
+                      errors: &mut Vec<Diagnostic>,
+                      warnings: &mut Vec<Diagnostic>| {
+        collect_undefined_idents(expr, scopes, declaring, file, source, errors);
+        check_calls(expr, value_needed, info, file, source, errors);
+        check_division_by_zero(expr, info, file, source, errors);
+        check_bool_arith(expr, info, file, source, errors);
+        check_typeof(expr, info, file, source, errors);
+        check_operand_types(expr, info, file, source, errors);
+        check_leading_zeros(expr, file, source, warnings);
+    };
+
+    for stmt in stmts {
         match stmt {
-            Stmt::Print { .. } => {
-                // 打印语句不需要检查
+            Stmt::Print { args, .. } => {
+                let format_print = is_format_print(args);
+                for (i, arg) in args.iter().enumerate() {
+                    // 检查字符串参数中的格式占位符（格式字符串之后的参数按普通值输出，不检查）
+                    if let Expr::StringLit(content, lit_span) = arg
+                        && (i == 0 || !format_print)
+                    {
+                        let positional_args = (i == 0 && format_print).then(|| args.len() - 1);
+                        check_print_format(content, *lit_span, positional_args, scopes, file, source, errors);
+                    }
+                    check_expr(arg, scopes, true, None, errors, warnings);
+                }
             }
             Stmt::Assign { name, decl_mut, expr, name_span: _name_span, .. } => {
                 // 检查表达式中使用的变量是否已声明
-                collect_undefined_idents(expr, &declared, file, source, &mut errors);
+                let declaring = (!scopes.contains(name.as_str())).then_some(name.as_str());
+                check_expr(expr, scopes, true, declaring, errors, warnings);
                 
                 // 更新已声明变量列表
+                let ty = info.types.get(name).copied();
                 if *decl_mut {
-                    declared.insert(name.as_str(), Mutability::Mutable);
-                } else if !declared.contains_key(name.as_str()) {
-                    declared.insert(name.as_str(), Mutability::Immutable);
+                    scopes.declare(name.as_str(), Mutability::Mutable, ty);
+                } else if !scopes.contains(name.as_str()) {
+                    scopes.declare(name.as_str(), Mutability::Immutable, ty);
                 }
             }
-        }
-    }
+            Stmt::Const { name, expr, .. } => {
+                // 检查表达式中使用的变量是否已声明，且都是常量
+                check_expr(expr, scopes, true, None, errors, warnings);
+                collect_non_const_idents(expr, scopes, name, file, source, errors);
 
-    // 如果有错误，返回所有错误信息
-    if !errors.is_empty() {
-        return Err(anyhow!(errors.join("\n")));
-    }
+                if !scopes.contains(name.as_str()) {
+                    scopes.declare(name.as_str(), Mutability::Const, info.types.get(name).copied());
+                }
+            }
+            Stmt::Expr { expr, .. } => {
+                // 单独一行的函数调用，返回值被丢弃
+                check_expr(expr, scopes, false, None, errors, warnings);
+            }
+            Stmt::Invalid { name, .. } => {
+                // 语法错误已经报告；赋值的变量与`declare_block`中一样当作已声明
+                if let Some(name) = name
+                    && !scopes.contains(name.as_str())
+                {
+                    scopes.declare(name.as_str(), Mutability::Mutable, None);
+                }
+            }
+            Stmt::Return { expr, span } => {
+                if let Some(expr) = expr {
+                    check_expr(expr, scopes, true, None, errors, warnings);
+                }
+                if let Some(fn_name) = current_fn
+                    && let Some(sig) = info.functions.get(fn_name)
+                {
+                    check_return(fn_name, sig, expr.as_ref(), *span, info, file, source, errors);
+                }
+            }
+            Stmt::FnDef { name, params, body, span, name_span, .. } => {
+                let (Some(sig), Some(local)) = (program_info.functions.get(name), program_info.bodies.get(name)) else {
+                    continue;
+                };
+                let errors_before = errors.len();
 
-    Ok(info)
+                // 函数体有自己的作用域，其中只能看到参数、局部变量和外层的常量
+                scopes.push_function();
+                for (param, (_, ty)) in params.iter().zip(&sig.params) {
+                    scopes.declare(param.name.as_str(), Mutability::Immutable, Some(*ty));
+                }
+                check_block(body, program_info, local, scopes, Some(name), file, source, errors, warnings);
+                scopes.pop();
+
+                // 有返回值的函数必须以return语句结束（最后一条语句无法解析时不检查）
+                if let Some(ret) = sig.ret
+                    && !matches!(body.last(), Some(Stmt::Return { .. } | Stmt::Invalid { .. }))
+                {
+                    errors.push(friendly_error_missing_return(file, source, name, ret, *span));
+                }
+
+                // 有带值的return却推断不出返回值类型（如只返回对自身的递归调用），
+                // 生成的Rust函数没有返回值类型；函数体中已有错误时不再报告
+                if sig.returns_value
+                    && sig.ret.is_none()
+                    && errors.len() == errors_before
+                    && !body.iter().any(|stmt| matches!(stmt, Stmt::Invalid { .. }))
+                {
+                    errors.push(friendly_error_uninferred_return(file, source, name, *name_span));
+                }
+            }
+        }
+    }
 }
 
 /// 生成修改不可变变量的友好错误信息
@@ -129,23 +671,133 @@ pub fn check_semantics(program: &Program, file: &Path, source: &str) -> Result<S
 /// * `name_span` - 变量名的源码位置
 /// 
 /// # 返回值
-/// * `String` - 格式化的错误信息
+/// * `Diagnostic` - 错误诊断
 fn friendly_error_assign_immutable(
     file: &Path,
     source: &str,
     name: &str,
     name_span: SourceSpan,
-) -> String {
+) -> Diagnostic {
     let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
     let line_no = name_span.start.line;
-    let col = name_span.start.col;
     let line_text = get_line(source, line_no);
-    let caret = caret_line(name_span);
     let summary = format!("你试图修改不可变变量 `{name}`");
     let suggestions = format!(
         "   - 如果你想让它可变，请在首次赋值时加 `$`：\n        ${name} = 0   ← 这样声明\n        {name} = {name} + 1   ← 这样修改\n   - 或者，你是否想创建一个新变量？\n        new_{name} = {name} + 1",
     );
-    render_error(&summary, filename, line_no, col, &line_text, &caret, &suggestions)
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成宽松模式下重新赋值不可变变量的警告信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `name_span` - 变量名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 警告诊断
+fn friendly_warning_assign_immutable(
+    file: &Path,
+    source: &str,
+    name: &str,
+    name_span: SourceSpan,
+) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("重新赋值了不可变变量 `{name}`（宽松模式下允许，这会创建一个同名的新变量）");
+    let suggestions = format!(
+        "   - 如果它本来就需要修改，请在首次赋值时加 `$`：\n        ${name} = 0\n   - 去掉 --lenient 后这里会报错",
+    );
+    Diagnostic::warning(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成把变量赋值给它自己的警告信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `span` - 赋值语句的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 警告诊断
+fn friendly_warning_self_assign(file: &Path, source: &str, name: &str, span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("赋值语句 `{name} = {name}` 没有任何效果");
+    let suggestions = "   - 把变量赋值给它自己不会改变它的值，可以删除这一行\n   - 如果是想赋值为另一个变量，请检查变量名是否写错".to_string();
+    Diagnostic::warning(&summary, filename, span, &line_text, &suggestions)
+}
+
+/// 生成在声明变量的同时使用它自身的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `span` - 初始值中该变量的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_self_reference(file: &Path, source: &str, name: &str, span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("你在声明 `{name}` 的同时使用了它自身");
+    let suggestions = format!(
+        "   - 这是 `{name}` 第一次出现，此时它还没有值\n   - 请先用初始值声明它，再修改它的值：\n        ${name} = 0\n        {name} = {name} + 1"
+    );
+    Diagnostic::error(&summary, filename, span, &line_text, &suggestions)
+}
+
+/// 生成变量声明后从未使用的警告信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `name_span` - 首次声明时变量名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 警告诊断
+fn friendly_warning_unused(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("变量 `{name}` 声明后从未被使用");
+    let suggestions = format!(
+        "   - 如果不再需要它，可以删除这个变量\n   - 如果是有意不使用，请在变量名前加 `_` 消除这条警告：\n        _{name} = ..."
+    );
+    Diagnostic::warning(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成函数参数遮蔽顶层常量的友好警告信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `param` - 遮蔽常量的参数
+/// * `const_span` - 被遮蔽的常量名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 警告诊断（标记参数，并在修复建议中给出常量的声明位置）
+fn friendly_warning_shadow(file: &Path, source: &str, param: &Param, const_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let name = &param.name;
+    let line_no = param.span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("参数 `{name}` 遮蔽了同名的顶层常量");
+    let const_line = const_span.start.line;
+    let suggestions = format!(
+        "   - 常量 `{name}` 声明于 {filename}:{const_line}:{}：\n        {}\n   - 函数体中的 `{name}` 指的是参数而不是这个常量，如果不是有意为之，请给参数改名",
+        const_span.start.col,
+        get_line(source, const_line).trim(),
+    );
+    Diagnostic::warning(&summary, filename, param.span, &line_text, &suggestions)
 }
 
 /// 生成重复声明变量的友好错误信息
@@ -157,71 +809,1007 @@ fn friendly_error_assign_immutable(
 /// * `name_span` - 变量名的源码位置
 /// 
 /// # 返回值
-/// * `String` - 格式化的错误信息
-fn friendly_error_redeclare(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> String {
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_redeclare(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
     let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
     let line_no = name_span.start.line;
-    let col = name_span.start.col;
     let line_text = get_line(source, line_no);
-    let caret = caret_line(name_span);
     let summary = format!("变量 `{name}` 已在之前声明，不能重复声明");
     let suggestions = format!(
         "   - 如需重新赋值，请直接写：\n        {name} = ...\n   - 如需新变量，请改用不同的名称：\n        {name}_2 = ...",
     );
-    render_error(&summary, filename, line_no, col, &line_text, &caret, &suggestions)
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
 }
 
-/// 递归收集表达式中未定义的标识符
+/// 生成修改常量的友好错误信息
 /// 
 /// # 参数
-/// * `expr` - 要检查的表达式
-/// * `declared` - 已声明的变量映射表
 /// * `file` - 源文件路径
 /// * `source` - 源代码字符串
-/// * `errors` - 错误信息列表（用于收集错误）
+/// * `name` - 常量名
+/// * `name_span` - 常量名的源码位置
 /// 
-/// # 功能
-/// 遍历表达式树，检查所有标识符是否已在之前声明
-/// 对于未定义的变量，生成友好的错误信息并添加到错误列表
-fn collect_undefined_idents(
-    expr: &Expr,
-    declared: &HashMap<&str, Mutability>,
-    file: &Path,
-    source: &str,
-    errors: &mut Vec<String>,
-) {
-    match expr {
-        Expr::Ident(name, span) => {
-            // 检查标识符是否已声明
-            if !declared.contains_key(name.as_str()) {
-                let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
-                let line_no = span.start.line;
-                let line_text = get_line(source, line_no);
-                
-                // 尝试在行中定位标识符以获得更准确的列位置
-                let (col, span_for_caret) = if let Some(idx) = line_text.find(name) {
-                    let start_col = idx + 1; // 转换为1基索引
-                    let end_col = start_col + name.len();
-                    (start_col, SourceSpan::single_line(line_no, start_col, end_col))
-                } else {
-                    (span.start.col, *span)
-                };
-                
-                let caret = caret_line(span_for_caret);
-                let summary = format!("使用了未定义的变量 `{name}`");
-                let suggestions = format!(
-                    "   - 请先声明变量：\n        {name} = ...    // 不可变\n        ${name} = ...   // 可变",
-                );
-                errors.push(render_error(&summary, filename, line_no, col, &line_text, &caret, &suggestions));
-            }
-        }
-        Expr::BinaryAdd(a, b, _) => {
-            // 递归检查二元加法表达式的左右操作数
-            collect_undefined_idents(a, declared, file, source, errors);
-            collect_undefined_idents(b, declared, file, source, errors);
-        }
-        _ => {
-            // 其他表达式类型（字面量等）不需要检查
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_assign_const(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("你试图修改常量 `{name}`");
+    let suggestions = format!(
+        "   - 常量的值在编译期确定，不能修改\n   - 如果需要修改，请改为可变变量：\n        ${name} = ...",
+    );
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成函数体中的局部变量与顶层常量同名时的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名（同时也是顶层常量的名称）
+/// * `name_span` - 变量名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_local_named_const(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("局部变量 `{name}` 与顶层常量同名");
+    let suggestions = format!(
+        "   - 函数体中可以读取顶层常量 `{name}`，但不能声明同名的局部变量，也不能修改这个常量\n   - 请给局部变量改名，如：\n        local_{name} = ...",
+    );
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 递归检查常量初始值中引用的非常量标识符
+/// 
+/// # 参数
+/// * `expr` - 常量的初始值表达式
+/// * `scopes` - 当前可见的作用域栈
+/// * `const_name` - 正在声明的常量名
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 功能
+/// 常量必须在编译期求值，因此其初始值只能引用之前声明的常量。
+/// 未定义的标识符已由`collect_undefined_idents`报告，这里跳过
+fn collect_non_const_idents(
+    expr: &Expr,
+    scopes: &ScopeStack,
+    const_name: &str,
+    file: &Path,
+    source: &str,
+    errors: &mut Vec<Diagnostic>,
+) {
+    match expr {
+        Expr::Ident(name, span) => {
+            if matches!(scopes.lookup(name.as_str()), Some((Mutability::Immutable | Mutability::Mutable, _))) {
+                let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+                let line_no = span.start.line;
+                let line_text = get_line(source, line_no);
+
+                let summary = format!("常量 `{const_name}` 的初始值必须是常量表达式，但使用了变量 `{name}`");
+                let suggestions = format!(
+                    "   - 如果 `{name}` 的值不会改变，请把它也声明为常量：\n        const {name} = ...\n   - 或者，把 `{const_name}` 改为普通变量：\n        {const_name} = ...",
+                );
+                errors.push(Diagnostic::error(&summary, filename, *span, &line_text, &suggestions));
+            }
+        }
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            collect_non_const_idents(a, scopes, const_name, file, source, errors);
+            collect_non_const_idents(b, scopes, const_name, file, source, errors);
+        }
+        Expr::Group(inner, _) | Expr::Neg(inner, _) => {
+            collect_non_const_idents(inner, scopes, const_name, file, source, errors);
+        }
+        Expr::TypeOf(..) => {
+            // typeof在编译期求值，本身就是常量表达式
+        }
+        Expr::Call(fn_name, _, span) => {
+            let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+            let line_no = span.start.line;
+            let line_text = get_line(source, line_no);
+            let summary = format!("常量 `{const_name}` 的初始值必须是常量表达式，但调用了函数 `{fn_name}`");
+            let suggestions = format!(
+                "   - 函数在运行时才会被调用，请把 `{const_name}` 改为普通变量：\n        {const_name} = ...",
+            );
+            errors.push(Diagnostic::error(&summary, filename, *span, &line_text, &suggestions));
         }
+        Expr::Read(kind, span) => {
+            let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+            let line_text = get_line(source, span.start.line);
+            let summary = format!("常量 `{const_name}` 的初始值必须是常量表达式，但读取了输入（`{}()`）", kind.name());
+            let suggestions = format!(
+                "   - 输入在运行时才能读取，请把 `{const_name}` 改为普通变量：\n        {const_name} = {}()",
+                kind.name()
+            );
+            errors.push(Diagnostic::error(&summary, filename, *span, &line_text, &suggestions));
+        }
+        _ => {}
+    }
+}
+
+/// 递归收集表达式中未定义的标识符
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `scopes` - 当前可见的作用域栈
+/// * `declaring` - 表达式是某个变量首次声明时的初始值时，为该变量名
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 功能
+/// 遍历表达式树，检查所有标识符是否已在之前声明，且在当前作用域栈中可见
+/// 对于未定义的变量，生成友好的错误信息并添加到错误列表；
+/// 初始值中使用了正在声明的变量自身时（如首次出现的 `total = total + 1`），报告专门的错误
+fn collect_undefined_idents(
+    expr: &Expr,
+    scopes: &ScopeStack,
+    declaring: Option<&str>,
+    file: &Path,
+    source: &str,
+    errors: &mut Vec<Diagnostic>,
+) {
+    match expr {
+        // 在声明变量的同时使用了它自身
+        Expr::Ident(name, span) if declaring == Some(name.as_str()) => {
+            errors.push(friendly_error_self_reference(file, source, name, *span));
+        }
+        // 检查标识符是否已声明
+        Expr::Ident(name, span) if !scopes.contains(name.as_str()) => {
+            let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+            let line_no = span.start.line;
+            let line_text = get_line(source, line_no);
+            
+            // 插入符号标记在标识符本身下方（同名变量在一行中出现多次时也能准确定位）
+            let summary = format!("使用了未定义的变量 `{name}`");
+            let suggestions = format!(
+                "   - 请先声明变量：\n        {name} = ...    // 不可变\n        ${name} = ...   // 可变",
+            );
+            errors.push(Diagnostic::error(&summary, filename, *span, &line_text, &suggestions));
+        }
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            // 递归检查二元表达式的左右操作数
+            collect_undefined_idents(a, scopes, declaring, file, source, errors);
+            collect_undefined_idents(b, scopes, declaring, file, source, errors);
+        }
+        Expr::Group(inner, _) | Expr::TypeOf(inner, _) | Expr::Neg(inner, _) => {
+            // 递归检查括号内的表达式
+            collect_undefined_idents(inner, scopes, declaring, file, source, errors);
+        }
+        Expr::Call(_, args, _) => {
+            // 函数名由check_calls检查，这里只检查实参
+            for arg in args {
+                collect_undefined_idents(arg, scopes, declaring, file, source, errors);
+            }
+        }
+        _ => {
+            // 其他表达式类型（字面量等）不需要检查
+        }
+    }
+}
+
+/// 递归检查表达式中除以0的情况
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `info` - 当前代码块的符号表（用于计算常量的值）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 功能
+/// 除以0或对0取余会在运行时panic，对于编译期就能确定为0的除数（字面量0、
+/// 只由字面量和常量组成且值为0的表达式）直接报告，常量折叠因此不会遇到除以0
+fn check_division_by_zero(expr: &Expr, info: &SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    match expr {
+        Expr::BinaryDiv(a, b, span) | Expr::BinaryMod(a, b, span) => {
+            if is_const_zero(b, info) {
+                let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+                let line_no = span.start.line;
+                let line_text = get_line(source, line_no);
+                let summary = match expr {
+                    Expr::BinaryMod(..) => "不能对0取余".to_string(),
+                    _ => "不能除以0".to_string(),
+                };
+
+                // 插入符号从运算符（左操作数之后第一个非空白字符）标记到除数末尾
+                let col = operator_col(&line_text, a.span());
+                let suggestions = "   - 除数为0会导致程序在运行时崩溃\n   - 请检查除数是否写错，或改用非0的值".to_string();
+                errors.push(Diagnostic::error(&summary, filename, SourceSpan::single_line(line_no, col, b.span().end.col), &line_text, &suggestions));
+            }
+            check_division_by_zero(a, info, file, source, errors);
+            check_division_by_zero(b, info, file, source, errors);
+        }
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            check_division_by_zero(a, info, file, source, errors);
+            check_division_by_zero(b, info, file, source, errors);
+        }
+        Expr::Group(inner, _) | Expr::Neg(inner, _) => {
+            check_division_by_zero(inner, info, file, source, errors);
+        }
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                check_division_by_zero(arg, info, file, source, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 判断表达式在编译期的值是否为整数0（如 `0`、`(0)`、`2 - 2` 或值为0的常量，见`fold_const`）
+fn is_const_zero(expr: &Expr, info: &SemanticInfo) -> bool {
+    matches!(fold_const(expr, info), Some(Expr::IntLit(0, _)))
+}
+
+/// 计算二元运算符所在的列
+/// 
+/// # 参数
+/// * `line_text` - 源码行
+/// * `lhs` - 左操作数的源码位置
+/// 
+/// # 返回值
+/// * `usize` - 左操作数之后第一个非空白字符的列（1基索引）
+fn operator_col(line_text: &str, lhs: SourceSpan) -> usize {
+    let skipped = line_text
+        .chars()
+        .skip(lhs.end.col.saturating_sub(1))
+        .take_while(|c| c.is_whitespace())
+        .count();
+    lhs.end.col + skipped
+}
+
+/// 检查print字符串参数中的格式占位符
+/// 
+/// # 参数
+/// * `content` - 字符串参数的内容
+/// * `span` - 字符串字面量的源码位置
+/// * `positional_args` - 作为格式字符串时可填入 `{}` 的参数个数；不是格式字符串时为None
+/// * `scopes` - 当前可见的作用域栈
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 检查规则
+/// 1. 花括号必须配对（`{{` `}}` 表示花括号本身）
+/// 2. `{变量名}` 引用的变量必须已声明
+/// 3. 格式字符串中 `{}` 的个数必须与其余参数的个数一致
+/// 4. 只有第一个参数可以作为格式字符串使用 `{}`
+fn check_print_format(
+    content: &str,
+    span: SourceSpan,
+    positional_args: Option<usize>,
+    scopes: &ScopeStack,
+    file: &Path,
+    source: &str,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+
+    // 插入符号标记在字符串字面量下方
+    let mut report = |summary: String, suggestions: String| {
+        errors.push(Diagnostic::error(&summary, filename, span, &line_text, &suggestions));
+    };
+
+    let placeholders = match parse_placeholders(content) {
+        Ok(placeholders) => placeholders,
+        Err(message) => {
+            report(message, "   - 用 `{变量名}` 输出变量的值，例如：\n        print(\"x = {x}\")".to_string());
+            return;
+        }
+    };
+
+    // 命名占位符引用的变量必须已声明
+    for placeholder in &placeholders {
+        if let Placeholder::Named(name) = placeholder
+            && !scopes.contains(name.as_str())
+        {
+            report(
+                format!("格式字符串中使用了未定义的变量 `{name}`"),
+                format!("   - 请先声明变量：\n        {name} = ...\n   - 如需输出花括号本身，请写 `{{{{{name}}}}}`"),
+            );
+        }
+    }
+
+    // 位置占位符的个数必须与参数个数一致
+    let positional = placeholders.iter().filter(|p| **p == Placeholder::Positional).count();
+    match positional_args {
+        Some(args) if positional != args => report(
+            format!("格式字符串有 {positional} 个 `{{}}` 占位符，但提供了 {args} 个参数"),
+            "   - 每个 `{}` 对应格式字符串之后的一个参数，例如：\n        print(\"x = {}\", x)\n   - 如需输出花括号本身，请写 `{{}}`".to_string(),
+        ),
+        None if positional > 0 => report(
+            "只有 print 的第一个参数可以作为格式字符串使用 `{}` 占位符".to_string(),
+            "   - 把格式字符串放在第一个参数：\n        print(\"x = {}\", x)\n   - 或者用 `{变量名}` 直接输出变量的值：\n        print(\"x = {x}\")".to_string(),
+        ),
+        _ => {}
+    }
+}
+
+/// 递归检查表达式中参与算术运算的布尔值
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `info` - 语义分析信息（用于推断操作数类型）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+fn check_bool_arith(expr: &Expr, info: &SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    match expr {
+        Expr::BinaryAdd(a, b, span)
+        | Expr::BinarySub(a, b, span)
+        | Expr::BinaryMul(a, b, span)
+        | Expr::BinaryDiv(a, b, span)
+        | Expr::BinaryMod(a, b, span) => {
+            if info.type_of(a) == Some(Type::Bool) || info.type_of(b) == Some(Type::Bool) {
+                errors.push(friendly_error_bool_arith(*span, file, source));
+                return;
+            }
+            check_bool_arith(a, info, file, source, errors);
+            check_bool_arith(b, info, file, source, errors);
+        }
+        Expr::Neg(inner, span) => {
+            if info.type_of(inner) == Some(Type::Bool) {
+                errors.push(friendly_error_bool_arith(*span, file, source));
+                return;
+            }
+            check_bool_arith(inner, info, file, source, errors);
+        }
+        Expr::Compare(_, a, b, _) => {
+            check_bool_arith(a, info, file, source, errors);
+            check_bool_arith(b, info, file, source, errors);
+        }
+        Expr::Group(inner, _) => check_bool_arith(inner, info, file, source, errors),
+        Expr::TypeOf(inner, _) => check_bool_arith(inner, info, file, source, errors),
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                check_bool_arith(arg, info, file, source, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 生成布尔值参与算术运算的友好错误信息
+/// 
+/// # 参数
+/// * `span` - 算术表达式的源码位置
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_bool_arith(span: SourceSpan, file: &Path, source: &str) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+
+    // 插入符号标记在整个算术表达式下方
+    let summary = "布尔值（true/false）不能参与算术运算".to_string();
+    let suggestions = "   - 算术运算（+ - * / % 和取负）只能用于整数和浮点数\n   - 请检查是否用错了变量".to_string();
+    Diagnostic::error(&summary, filename, span, &line_text, &suggestions)
+}
+
+/// 递归检查表达式中typeof的类型能否在编译期确定
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `info` - 语义分析信息（用于推断类型）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+fn check_typeof(expr: &Expr, info: &SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    let (inner, span) = match expr {
+        Expr::TypeOf(inner, span) => (inner, *span),
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => {
+            check_typeof(a, info, file, source, errors);
+            check_typeof(b, info, file, source, errors);
+            return;
+        }
+        Expr::Group(inner, _) | Expr::Neg(inner, _) => return check_typeof(inner, info, file, source, errors),
+        Expr::Call(_, args, _) => {
+            for arg in args {
+                check_typeof(arg, info, file, source, errors);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    // 未定义的变量已由collect_undefined_idents报告，这里不重复报告
+    if info.type_of(inner).is_some() || contains_ident_without_type(inner, info) {
+        return;
+    }
+
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+
+    // 插入符号标记在typeof关键字下方
+    let col = span.start.col;
+    let summary = "无法确定 typeof 中表达式的类型".to_string();
+    let suggestions = "   - 请检查表达式两侧的类型是否一致，例如整数和字符串不能相加".to_string();
+    errors.push(Diagnostic::error(&summary, filename, SourceSpan::single_line(line_no, col, col + "typeof".len()), &line_text, &suggestions));
+}
+
+/// 递归检查二元运算两侧的类型是否兼容
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `info` - 语义分析信息（用于推断操作数类型）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 检查规则
+/// 1. `+`：字符串只能与字符串相加，`"count: " + 1` 是类型错误而不是自动拼接为 "count: 1"
+/// 2. `-` `*` `/` `%`：不能用于字符串
+/// 3. 比较运算：两侧类型必须相同（整数和浮点数可以互相比较）
+/// 
+/// 布尔值参与算术运算已由`check_bool_arith`报告，这里不重复报告；
+/// 类型未知的操作数（如未定义的变量）也跳过
+fn check_operand_types(expr: &Expr, info: &SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    let numeric = |t: Type| matches!(t, Type::Int | Type::Float);
+    let mismatch = match expr {
+        Expr::BinaryAdd(a, b, span) => match (info.type_of(a), info.type_of(b)) {
+            (Some(Type::Str), Some(t)) | (Some(t), Some(Type::Str)) if numeric(t) => Some((
+                *span,
+                format!("字符串不能和 {t} 相加"),
+                format!("   - `+` 只能拼接两个字符串，{t} 不会自动转换为字符串\n   - 如果是想输出它们，请把它们作为 print 的参数：\n        print(\"count:\", count)"),
+            )),
+            _ => None,
+        },
+        Expr::BinarySub(a, b, span)
+        | Expr::BinaryMul(a, b, span)
+        | Expr::BinaryDiv(a, b, span)
+        | Expr::BinaryMod(a, b, span) => {
+            let op = match expr {
+                Expr::BinarySub(..) => "-",
+                Expr::BinaryMul(..) => "*",
+                Expr::BinaryDiv(..) => "/",
+                _ => "%",
+            };
+            let has_str = info.type_of(a) == Some(Type::Str) || info.type_of(b) == Some(Type::Str);
+            let has_bool = info.type_of(a) == Some(Type::Bool) || info.type_of(b) == Some(Type::Bool);
+            (has_str && !has_bool).then(|| (
+                *span,
+                format!("字符串不支持 `{op}` 运算"),
+                "   - 字符串只支持用 `+` 拼接和比较运算\n   - 请检查是否用错了变量".to_string(),
+            ))
+        }
+        Expr::Compare(op, a, b, span) => match (info.type_of(a), info.type_of(b)) {
+            (Some(ta), Some(tb)) if ta != tb && !(numeric(ta) && numeric(tb)) => Some((
+                *span,
+                format!("不能比较 {ta} 和 {tb}"),
+                format!("   - `{}` 两侧的值必须是相同的类型（整数和浮点数可以互相比较）\n   - 请检查是否用错了变量", op.symbol()),
+            )),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    if let Some((span, summary, suggestions)) = mismatch {
+        let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+        let line_no = span.start.line;
+        let line_text = get_line(source, line_no);
+        errors.push(Diagnostic::error(&summary, filename, span, &line_text, &suggestions));
+        return;
+    }
+
+    for child in expr.children() {
+        check_operand_types(child, info, file, source, errors);
+    }
+}
+
+/// 生成重新赋值时类型不匹配的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `expected` - 变量首次赋值时确定的类型
+/// * `found` - 新值的类型
+/// * `expr_span` - 新值表达式的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_type_mismatch(
+    file: &Path,
+    source: &str,
+    name: &str,
+    expected: Type,
+    found: Type,
+    expr_span: SourceSpan,
+) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = expr_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("类型不匹配：变量 `{name}` 的类型是 {expected}，不能赋值为 {found}");
+    let hint = match (expected, found) {
+        (Type::Float, Type::Int) => "\n   - 如果想赋值为浮点数，请写成带小数点的形式，如 `2.0`".to_string(),
+        _ => String::new(),
+    };
+    let suggestions = format!(
+        "   - 变量的类型由首次赋值决定，之后只能赋值为相同类型的值{hint}\n   - 或者，用一个新变量保存它：\n        {name}_{found} = ...",
+    );
+    Diagnostic::error(&summary, filename, expr_span, &line_text, &suggestions)
+}
+
+/// 递归检查表达式中带多余前导零的十进制整数字面量
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串（用于取得字面量的原始写法）
+/// * `warnings` - 警告信息列表（用于收集警告）
+/// 
+/// # 功能
+/// `007` 会被解析为7，但在不少语言中前导零表示八进制，容易引起误解
+fn check_leading_zeros(expr: &Expr, file: &Path, source: &str, warnings: &mut Vec<Diagnostic>) {
+    if let Expr::IntLit(value, span) = expr {
+        let line_no = span.start.line;
+        let line_text = get_line(source, line_no);
+        let text: String = line_text
+            .chars()
+            .skip(span.start.col.saturating_sub(1))
+            .take(span.end.col.saturating_sub(span.start.col))
+            .collect();
+        let digits = text.trim_start_matches('-');
+
+        if digits.len() > 1 && digits.starts_with('0') && digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+            let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+            let summary = format!("整数字面量 `{text}` 带有多余的前导零");
+            let sign = if text.starts_with('-') { "-" } else { "" };
+            let octal_digits = match digits.trim_start_matches(['0', '_']) {
+                "" => "0",
+                rest => rest,
+            };
+            let suggestions = format!(
+                "   - 它的值是十进制的 {value}，请去掉前导零：\n        {value}\n   - 如果你想写八进制数，请使用 `0o` 前缀：\n        {sign}0o{octal_digits}",
+            );
+            warnings.push(Diagnostic::warning(&summary, filename, *span, &line_text, &suggestions));
+        }
+    }
+
+    for child in expr.children() {
+        check_leading_zeros(child, file, source, warnings);
+    }
+}
+
+/// 检查表达式中是否有类型未知的标识符（通常是未定义的变量）
+fn contains_ident_without_type(expr: &Expr, info: &SemanticInfo) -> bool {
+    match expr {
+        Expr::Ident(name, _) => !info.types.contains_key(name),
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _)
+        | Expr::Compare(_, a, b, _) => contains_ident_without_type(a, info) || contains_ident_without_type(b, info),
+        Expr::Group(inner, _) | Expr::Neg(inner, _) => contains_ident_without_type(inner, info),
+        Expr::Call(name, args, _) => {
+            !info.functions.contains_key(name) || args.iter().any(|arg| contains_ident_without_type(arg, info))
+        }
+        _ => false,
+    }
+}
+
+/// 递归检查表达式中的函数调用
+/// 
+/// # 参数
+/// * `expr` - 要检查的表达式
+/// * `value_needed` - 是否需要使用`expr`的值（单独一行的函数调用为false）
+/// * `info` - 当前代码块的符号表（包含函数签名）
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+/// 
+/// # 检查规则
+/// 1. 调用的函数必须已定义
+/// 2. 实参的个数必须与参数的个数一致，类型已知的实参必须与参数的类型相同
+/// 3. 没有返回值的函数不能用在需要值的地方
+fn check_calls(expr: &Expr, value_needed: bool, info: &SemanticInfo, file: &Path, source: &str, errors: &mut Vec<Diagnostic>) {
+    if let Expr::Call(name, args, span) = expr {
+        let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+        let line_no = span.start.line;
+        let line_text = get_line(source, line_no);
+        let mut report = |span: SourceSpan, summary: String, suggestions: String| {
+            errors.push(Diagnostic::error(&summary, filename, span, &line_text, &suggestions));
+        };
+
+        match info.functions.get(name) {
+            None => {
+                // 函数名位于调用表达式的开头
+                let name_span = SourceSpan::single_line(line_no, span.start.col, span.start.col + name.chars().count());
+                report(
+                    name_span,
+                    format!("调用了未定义的函数 `{name}`"),
+                    format!("   - 请先定义函数：\n        fun {name}(...) {{\n            ...\n        }}\n   - 或者检查函数名是否拼写正确"),
+                );
+            }
+            Some(sig) if sig.params.len() != args.len() => {
+                let params: Vec<&str> = sig.params.iter().map(|(p, _)| p.as_str()).collect();
+                report(
+                    *span,
+                    format!("函数 `{name}` 需要 {} 个参数，但提供了 {} 个", sig.params.len(), args.len()),
+                    format!("   - 函数的定义是：\n        fun {name}({})", params.join(", ")),
+                );
+            }
+            Some(sig) => {
+                for (i, (arg, (param, expected))) in args.iter().zip(&sig.params).enumerate() {
+                    if let Some(found) = info.type_of(arg)
+                        && found != *expected
+                    {
+                        report(
+                            arg.span(),
+                            format!("函数 `{name}` 的第 {} 个参数 `{param}` 的类型是 {expected}，但传入了 {found}", i + 1),
+                            "   - 参数的类型由函数定义中的类型注解决定（省略时为int）\n   - 请检查是否传错了参数".to_string(),
+                        );
+                    }
+                }
+                // 返回值类型无法推断时（如return中使用了未定义的变量），错误已在函数体中报告
+                if value_needed && !sig.returns_value {
+                    report(
+                        *span,
+                        format!("函数 `{name}` 没有返回值，不能用在表达式中"),
+                        format!("   - 单独一行调用它：\n        {name}(...)\n   - 或者在函数体中用 `return 值` 返回一个值"),
+                    );
+                }
+            }
+        }
+    }
+
+    for child in expr.children() {
+        check_calls(child, true, info, file, source, errors);
+    }
+}
+
+/// 检查return语句的值与函数的返回值类型是否一致
+/// 
+/// # 参数
+/// * `fn_name` - 所在的函数名
+/// * `sig` - 所在函数的签名
+/// * `expr` - return的值（没有值时为None）
+/// * `span` - return语句的源码位置
+/// * `info` - 函数体的符号表
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `errors` - 错误信息列表（用于收集错误）
+#[allow(clippy::too_many_arguments)]
+fn check_return(
+    fn_name: &str,
+    sig: &FnSig,
+    expr: Option<&Expr>,
+    span: SourceSpan,
+    info: &SemanticInfo,
+    file: &Path,
+    source: &str,
+    errors: &mut Vec<Diagnostic>,
+) {
+    let (summary, suggestions, span) = match (sig.ret, expr) {
+        (Some(expected), Some(expr)) => match info.type_of(expr) {
+            Some(found) if found != expected => (
+                format!("函数 `{fn_name}` 的返回值类型是 {expected}，但返回了 {found}"),
+                "   - 同一个函数的所有return语句必须返回相同类型的值\n   - 请检查返回值类型注解或返回的表达式".to_string(),
+                expr.span(),
+            ),
+            _ => return,
+        },
+        (Some(expected), None) => (
+            format!("函数 `{fn_name}` 的返回值类型是 {expected}，return 必须带有返回值"),
+            "   - 请写成：\n        return 值".to_string(),
+            span,
+        ),
+        (None, _) => return,
+    };
+
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+    errors.push(Diagnostic::error(&summary, filename, span, &line_text, &suggestions));
+}
+
+/// 生成函数缺少return语句的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 函数名
+/// * `ret` - 函数的返回值类型
+/// * `span` - 函数头的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_missing_return(file: &Path, source: &str, name: &str, ret: Type, span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("函数 `{name}` 的返回值类型是 {ret}，但函数体没有以 return 语句结束");
+    let suggestions = "   - 请在函数体的最后返回一个值：\n        return 值".to_string();
+    Diagnostic::error(&summary, filename, span, &line_text, &suggestions)
+}
+
+/// 生成无法推断函数返回值类型的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 函数名
+/// * `name_span` - 函数名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_uninferred_return(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("无法推断函数 `{name}` 的返回值类型");
+    let suggestions = format!(
+        "   - 返回值只来自递归调用时无法确定其类型，请注明返回值类型，例如：\n        fun {name}(...) -> int {{ ... }}"
+    );
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成重复定义函数的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 函数名
+/// * `name_span` - 函数名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_redefine_fn(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("函数 `{name}` 已在之前定义，不能重复定义");
+    let suggestions = format!("   - 请改用不同的函数名：\n        fun {name}_2(...) {{ ... }}");
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成使用保留名称作为函数名的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 函数名
+/// * `name_span` - 函数名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_reserved_fn(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("`{name}` 是保留的名称，不能用作函数名");
+    let suggestions = format!(
+        "   - 顶层的语句就是程序的入口，不需要定义 main 函数\n   - 请改用其他函数名，如：\n        fun my_{name}(...) {{ ... }}",
+    );
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+/// 生成未知类型注解的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `ty_name` - 类型注解中的名称
+/// * `span` - 类型注解所在的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_unknown_type(file: &Path, source: &str, ty_name: &str, span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("未知的类型 `{ty_name}`");
+    let suggestions = "   - 可用的类型有 int、float、string、bool，例如：\n        fun add(a: int, b: int) -> int { ... }".to_string();
+    Diagnostic::error(&summary, filename, span, &line_text, &suggestions)
+}
+
+/// 生成变量与函数同名的友好错误信息
+/// 
+/// # 参数
+/// * `file` - 源文件路径
+/// * `source` - 源代码字符串
+/// * `name` - 变量名
+/// * `name_span` - 变量名的源码位置
+/// 
+/// # 返回值
+/// * `Diagnostic` - 错误诊断
+fn friendly_error_name_is_fn(file: &Path, source: &str, name: &str, name_span: SourceSpan) -> Diagnostic {
+    let filename = file.file_name().and_then(|s| s.to_str()).unwrap_or("<unknown>");
+    let line_no = name_span.start.line;
+    let line_text = get_line(source, line_no);
+    let summary = format!("`{name}` 已经是函数名，不能再用作变量名");
+    let suggestions = format!("   - 请改用不同的变量名：\n        {name}_value = ...");
+    Diagnostic::error(&summary, filename, name_span, &line_text, &suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser;
+
+    /// 解析并检查源代码，返回错误诊断
+    fn error_diagnostics_of(source: &str, options: &SemanticOptions) -> Vec<Diagnostic> {
+        let file = Path::new("test.kr");
+        let program = parser::parse(source, file).expect("source should parse");
+        match check_semantics(&program, file, source, options) {
+            Ok(_) => Vec::new(),
+            Err(err) => err.downcast::<Diagnostics>().expect("semantic errors are diagnostics").0,
+        }
+    }
+
+    /// 解析并检查源代码（源代码必须没有错误），返回警告诊断
+    fn warnings_of(source: &str, options: &SemanticOptions) -> Vec<Diagnostic> {
+        let file = Path::new("test.kr");
+        let program = parser::parse(source, file).expect("source should parse");
+        check_semantics(&program, file, source, options).expect("source should pass the check").warnings
+    }
+
+    /// 解析并检查源代码，返回错误诊断的摘要
+    fn errors_of(source: &str, options: &SemanticOptions) -> Vec<String> {
+        error_diagnostics_of(source, options).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn recursive_function_without_return_type_is_an_error() {
+        let errors = errors_of("fun fact(n) {\n    return n * fact(n - 1)\n}\nprint(fact(3))\n", &SemanticOptions::default());
+        assert_eq!(errors, vec!["无法推断函数 `fact` 的返回值类型".to_string()]);
+    }
+
+    #[test]
+    fn recursive_function_with_return_type_passes() {
+        let errors = errors_of("fun fact(n) -> int {\n    return n * fact(n - 1)\n}\nprint(fact(3))\n", &SemanticOptions::default());
+        assert!(errors.is_empty(), "{errors:?}");
+    }
+
+    #[test]
+    fn unused_variable_warning_fails_only_with_deny_warnings() {
+        let source = "x = 1\nprint(2)\n";
+        let file = Path::new("test.kr");
+        let program = parser::parse(source, file).unwrap();
+        let info = check_semantics(&program, file, source, &SemanticOptions::default()).expect("warnings do not fail");
+        assert_eq!(info.warnings.len(), 1);
+
+        let deny = SemanticOptions { deny_warnings: true, ..Default::default() };
+        let errors = errors_of(source, &deny);
+        assert_eq!(errors.len(), 1, "{errors:?}");
+    }
+
+    #[test]
+    fn function_local_named_like_const_is_not_an_assignment() {
+        let source = "const n = 1\nfun f() {\n    n = 2\n    print(n)\n}\nfun g() {\n    $n = 3\n    print(n)\n}\nf()\ng()\n";
+        let errors = errors_of(source, &SemanticOptions::default());
+        assert_eq!(errors, vec!["局部变量 `n` 与顶层常量同名".to_string(); 2]);
+        // 顶层修改常量仍然报告为修改常量
+        assert_eq!(errors_of("const n = 1\nn = 2\n", &SemanticOptions::default()), vec!["你试图修改常量 `n`".to_string()]);
+    }
+
+    #[test]
+    fn warn_shadow_reports_parameters_named_like_consts() {
+        let file = Path::new("test.kr");
+        let source = "const n = 1\nfun f(n) {\n    return n\n}\nprint(f(2))\n";
+        let program = parser::parse(source, file).expect("source should parse");
+        let options = SemanticOptions { warn_shadow: true, ..Default::default() };
+        let info = check_semantics(&program, file, source, &options).expect("shadowing is only a warning");
+        let warning = info.warnings.iter().find(|w| w.message == "参数 `n` 遮蔽了同名的顶层常量").expect("shadow warning");
+        assert!(warning.to_string().contains("常量 `n` 声明于 test.kr:1:7"), "{warning}");
+    }
+
+    #[test]
+    fn format_placeholders_must_match_argument_count() {
+        let options = SemanticOptions::default();
+        let too_few = error_diagnostics_of("x = 1\nprint(\"{} {}\", x)\n", &options);
+        assert_eq!(too_few.len(), 1);
+        assert_eq!(too_few[0].message, "格式字符串有 2 个 `{}` 占位符，但提供了 1 个参数");
+        // 插入符号标记在格式字符串下方
+        let span = too_few[0].span.expect("error should have a span");
+        assert_eq!((span.start.line, span.start.col, span.end.col), (2, 7, 14));
+
+        let too_many = errors_of("x = 1\nprint(\"{}\", x, x)\n", &options);
+        assert_eq!(too_many, vec!["格式字符串有 1 个 `{}` 占位符，但提供了 2 个参数".to_string()]);
+
+        // 命名占位符不需要对应的参数
+        assert!(errors_of("x = 1\nprint(\"{x} {}\", x)\n", &options).is_empty());
+    }
+
+    #[test]
+    fn leading_zeros_warn_except_for_zero() {
+        let warnings = warnings_of("x = 007\nprint(x, 0, 0.5, 0x0F)\n", &SemanticOptions::default());
+        let messages: Vec<&str> = warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["整数字面量 `007` 带有多余的前导零"]);
+        assert!(warnings[0].suggestions.contains("0o"), "{}", warnings[0].suggestions);
+        assert!(warnings_of("x = 0\nprint(x)\n", &SemanticOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn caret_column_counts_characters_not_bytes() {
+        let errors = error_diagnostics_of("x = \"你好\" + y // 注释\nprint(x)\n", &SemanticOptions::default());
+        assert_eq!(errors.len(), 1);
+        let span = errors[0].span.expect("error should have a span");
+        assert_eq!((span.start.line, span.start.col, span.end.col), (1, 12, 13));
+        // 中文字符占两列，插入符号对齐到 `y` 下方
+        let rendered = errors[0].to_string();
+        assert!(rendered.contains(" 1 | x = \"你好\" + y // 注释\n   |              ^\n"), "{rendered}");
+
+        // 同一行中文字符之后的第二条语句
+        let errors = error_diagnostics_of("s = \"甲\"; s = \"乙\"\nprint(s)\n", &SemanticOptions::default());
+        let span = errors[0].span.expect("error should have a span");
+        assert_eq!((span.start.col, span.end.col), (10, 11), "{}", errors[0]);
+    }
+
+    #[test]
+    fn self_assignment_warns_under_warn_no_effect() {
+        let source = "$x = 1\nx = x\nprint(x)\n";
+        let options = SemanticOptions { warn_no_effect: true, ..Default::default() };
+        let warnings = warnings_of(source, &options);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0].message, "赋值语句 `x = x` 没有任何效果");
+        let span = warnings[0].span.expect("warning should have a span");
+        assert_eq!((span.start.line, span.start.col), (2, 1));
+        assert!(warnings_of(source, &SemanticOptions::default()).is_empty());
+        // 右侧不只是同名变量时不报告
+        assert!(warnings_of("$x = 1\nx = x + 0\nprint(x)\n", &options).is_empty());
+    }
+
+    #[test]
+    fn caret_points_at_each_occurrence_of_a_name() {
+        let source = "missing2 = 1\nz = missing2 + missing\nw = q + q\nprint(z, w)\n";
+        let columns: Vec<(usize, usize, usize)> = error_diagnostics_of(source, &SemanticOptions::default())
+            .iter()
+            .map(|d| d.span.expect("error should have a span"))
+            .map(|span| (span.start.line, span.start.col, span.end.col))
+            .collect();
+        // `missing` 不会被当成前面的 `missing2`；同一行出现两次的 `q` 各自报告
+        assert_eq!(columns, vec![(2, 16, 23), (3, 5, 6), (3, 9, 10)]);
+    }
+
+    #[test]
+    fn unused_variable_suggests_underscore_prefix() {
+        let warnings = warnings_of("count = 1\n_ignored = 2\nused = 3\nprint(used)\n", &SemanticOptions::default());
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert_eq!(warnings[0].message, "变量 `count` 声明后从未被使用");
+        assert_eq!(warnings[0].severity, Severity::Warning);
+        assert!(warnings[0].suggestions.contains("请在变量名前加 `_` 消除这条警告：\n        _count = ..."), "{}", warnings[0].suggestions);
+    }
+
+    #[test]
+    fn self_reference_in_first_declaration() {
+        let errors = error_diagnostics_of("total = total + 1\nprint(total)\n", &SemanticOptions::default());
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].message, "你在声明 `total` 的同时使用了它自身");
+        let span = errors[0].span.expect("error should have a span");
+        assert_eq!((span.start.col, span.end.col), (9, 14));
+        assert!(errors[0].suggestions.contains("$total = 0"), "{}", errors[0].suggestions);
+        // 之前已声明时是普通的重新赋值
+        assert!(errors_of("$total = 0\ntotal = total + 1\nprint(total)\n", &SemanticOptions::default()).is_empty());
     }
 }
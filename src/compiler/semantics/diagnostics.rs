@@ -1,4 +1,8 @@
+use std::cell::RefCell;
 use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::str::FromStr;
 
 use crate::compiler::ast::SourceSpan;
 
@@ -13,15 +17,100 @@ use crate::compiler::ast::SourceSpan;
 /// * 暗淡色 (dim) - 用于行号
 /// * 重置色 (reset) - 重置所有颜色
 /// 
-/// # 环境变量支持
-/// 如果设置了NO_COLOR环境变量，则返回空字符串（禁用颜色）
+/// # 参数
+/// * `color` - 是否使用颜色（见`RenderOptions::color`），不使用时返回空字符串
 #[inline]
-pub fn color_codes() -> (&'static str, &'static str, &'static str, &'static str, &'static str, &'static str) {
-    // 如果设置了NO_COLOR环境变量则禁用颜色 (https://no-color.org/)
-    if env::var("NO_COLOR").is_ok() {
-        ("", "", "", "", "", "")
-    } else {
+pub fn color_codes(color: bool) -> (&'static str, &'static str, &'static str, &'static str, &'static str, &'static str) {
+    if color {
         ("\x1b[1;31m", "\x1b[31m", "\x1b[1;34m", "\x1b[1;33m", "\x1b[2m", "\x1b[0m")
+    } else {
+        ("", "", "", "", "", "")
+    }
+}
+
+/// 诊断信息的颜色设置（对应命令行参数--color）
+/// 
+/// # 变体
+/// * `Auto` - 标准错误是终端且没有设置NO_COLOR环境变量时使用颜色
+/// * `Always` - 总是使用颜色（输出被重定向时也是，供能够解析ANSI颜色代码的工具使用）
+/// * `Never` - 从不使用颜色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// 按输出目标自动决定
+    Auto,
+    /// 总是使用颜色
+    Always,
+    /// 从不使用颜色
+    Never,
+}
+
+impl ColorChoice {
+    /// 判断该设置下是否使用颜色
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => env::var("NO_COLOR").is_err() && io::stderr().is_terminal(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        })
+    }
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(format!("unknown color choice `{s}` (expected: auto, always, never)")),
+        }
+    }
+}
+
+/// 诊断信息的显示选项
+/// 
+/// # 字段
+/// * `color` - 是否使用ANSI颜色
+/// * `caret_char` - 标记出错位置的字符
+/// 
+/// # 说明
+/// 调用方（命令行程序、编辑器插件等）通过`Diagnostic::render`显式传入，不存在进程级的全局设置；
+/// 默认值为不带颜色、使用 `^`，`Display`使用默认值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderOptions {
+    pub color: bool,
+    pub caret_char: char,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions { color: false, caret_char: '^' }
+    }
+}
+
+/// 校验插入符号字符
+/// 
+/// # 参数
+/// * `s` - 用户指定的字符串
+/// 
+/// # 返回值
+/// * `Result<char, String>` - 是单个可见字符时返回该字符，否则返回错误信息
+pub fn parse_caret_char(s: &str) -> Result<char, String> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if !c.is_whitespace() && !c.is_control() => Ok(c),
+        _ => Err(format!("`{s}` is not a single visible character")),
     }
 }
 
@@ -38,51 +127,154 @@ pub fn get_line(source: &str, line_no: usize) -> String {
     source.lines().nth(line_no - 1).unwrap_or("").to_string()
 }
 
+/// 获取源码范围所跨越的各行内容
+/// 
+/// # 参数
+/// * `source` - 完整的源代码字符串
+/// * `span` - 源码范围
+/// 
+/// # 返回值
+/// * `String` - 从起始行到结束行的内容，以换行分隔（单行范围时与`get_line`相同）
+pub fn get_lines(source: &str, span: SourceSpan) -> String {
+    (span.start.line..=span.end.line.max(span.start.line))
+        .map(|line_no| get_line(source, line_no))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 获取字符在终端中的显示宽度
+/// 
+/// # 返回值
+/// * `usize` - 中日韩文字、全角符号和常见emoji占2列，其余字符占1列
+fn display_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
 /// 生成错误标记的插入符号字符串
 /// 
 /// # 参数
 /// * `span` - 源码范围，用于确定插入符号的位置和长度
+/// * `line_text` - 错误所在的源码行，用于保持插入符号前的缩进字符一致
+/// * `marker` - 插入符号使用的字符（见`RenderOptions::caret_char`）
 /// 
 /// # 返回值
 /// * `String` - 插入符号字符串，如 "   ^^^^^"
 /// 
 /// # 示例
 /// 如果span表示第5-10列，则返回 "    ^^^^^^"
+/// 
+/// # 制表符处理
+/// 插入符号前的前导部分会沿用源码行中对应位置的制表符，其余字符替换为空格，
+/// 这样无论终端的制表符宽度是多少，插入符号都能对齐到正确的位置
+/// 
+/// # 宽字符处理
+/// span的列号按Unicode字符计算，而中文等宽字符在终端中占两列，
+/// 因此前导空格和插入符号的个数按字符的显示宽度计算
+/// 
+/// # 标记字符
+/// 通常为 `^`，也可以替换为其他字符（如 `~`）
 #[inline]
-pub fn caret_line(span: SourceSpan) -> String {
+pub fn caret_line(span: SourceSpan, line_text: &str, marker: char) -> String {
     let start = span.start.col.saturating_sub(1); // 转换为0基索引
-    let width = span.end.col.saturating_sub(span.start.col).max(1); // 确保至少1个字符宽度
+    let len = span.end.col.saturating_sub(span.start.col);
     let mut s = String::new();
     
-    // 添加前导空格
+    // 添加前导空白（保留源码行中的制表符）
+    let mut chars = line_text.chars();
     for _ in 0..start { 
-        s.push(' '); 
+        match chars.next() {
+            Some('\t') => s.push('\t'),
+            Some(c) => s.push_str(&" ".repeat(display_width(c))),
+            None => s.push(' '),
+        }
     }
     
-    // 添加插入符号
+    // 添加插入符号（超出行尾的部分每列按1个宽度计算），确保至少1个字符宽度
+    let width: usize = (0..len).map(|_| chars.next().map_or(1, display_width)).sum::<usize>().max(1);
     for _ in 0..width { 
-        s.push('^'); 
+        s.push(marker); 
     }
     
     s
 }
 
-/// 渲染标准化的Rust风格诊断块（带颜色）
+/// 生成诊断块中的源码行和插入符号行
+/// 
+/// # 参数
+/// * `span` - 插入符号标记的源码范围
+/// * `code` - 范围所跨越的源码行（以换行分隔，见`get_lines`）
+/// * `color` - 插入符号的颜色代码
+/// * `options` - 显示选项（行号的颜色和插入符号的字符）
+/// 
+/// # 返回值
+/// * `String` - 不带末尾换行的多行文本
+/// 
+/// # 规则
+/// 1. 单行范围：输出该行和其下方的插入符号，如 ` 3 | x = 1` 和 `   |     ^`
+/// 2. 多行范围：依次输出每一行，每行下方的插入符号连续地标记范围在该行中的部分：
+///    第一行从起始列到行尾，中间各行从第一个非空白字符到行尾，最后一行从第一个非空白字符到结束列；
+///    空行不输出插入符号，行号右对齐到结束行号的宽度
+/// 3. `code`的行数少于范围跨越的行数时（只提供了起始行），只标记起始行中从起始列到行尾的部分
+fn source_block(span: SourceSpan, code: &str, color: &str, options: &RenderOptions) -> String {
+    let (_, _, _, _, dim, reset) = color_codes(options.color);
+    let lines: Vec<&str> = code.split('\n').collect();
+    let line_count = span.end.line.saturating_sub(span.start.line) + 1;
+    if line_count == 1 || lines.len() < line_count {
+        let line_text = lines[0];
+        let span = if line_count == 1 {
+            span
+        } else {
+            SourceSpan::single_line(span.start.line, span.start.col, line_text.chars().count() + 1)
+        };
+        let caret = caret_line(span, line_text, options.caret_char);
+        return format!(" {dim}{}{reset} | {line_text}\n   | {color}{caret}{reset}", span.start.line);
+    }
+
+    let width = span.end.line.to_string().len();
+    let mut rows = Vec::new();
+    for (i, line_text) in lines.iter().take(line_count).enumerate() {
+        let line_no = span.start.line + i;
+        rows.push(format!(" {dim}{line_no:>width$}{reset} | {line_text}"));
+        if line_text.trim().is_empty() {
+            continue;
+        }
+        let indent = line_text.chars().count() - line_text.trim_start().chars().count();
+        let start_col = if i == 0 { span.start.col } else { indent + 1 };
+        let end_col = if line_no == span.end.line { span.end.col } else { line_text.chars().count() + 1 };
+        let caret = caret_line(SourceSpan::single_line(line_no, start_col, end_col), line_text, options.caret_char);
+        rows.push(format!(" {:width$} | {color}{caret}{reset}", ""));
+    }
+    rows.join("\n")
+}
+
+/// 渲染标准化的Rust风格错误诊断块（带颜色）
 /// 
 /// # 参数
-/// * `summary` - 错误摘要（第一行，不包含颜色代码）
-/// * `filename` - 文件名（显示在头部）
-/// * `line_no` - 行号（1基索引）
-/// * `col` - 列号（1基索引）
-/// * `code_line` - 完整的源码行文本
-/// * `caret` - 预构建的插入符号字符串（如 "   ^^^^^"）
-/// * `suggestions` - 多行建议文本（已组合好）
+/// * `diagnostic` - 要渲染的诊断信息（头部显示其文件名和起始位置，插入符号标记其范围）
+/// * `options` - 显示选项
 /// 
 /// # 返回值
-/// * `String` - 格式化的错误诊断信息
+/// * `String` - 格式化的错误诊断信息；不带源码行的诊断（见`Diagnostic::plain`）只返回`message`；
+///   跨越多行的范围逐行输出源码和插入符号（见`source_block`）
 /// 
 /// # 格式示例
-/// ```
+/// ```text
 /// ❌ 错误：你试图修改不可变变量 `x`
 ///   --> file.kr:3:5
 ///    |
@@ -92,22 +284,338 @@ pub fn caret_line(span: SourceSpan) -> String {
 ///    - 如果你想让它可变，请在首次赋值时加 `$`：
 ///        $x = 0   ← 这样声明
 /// ```
-pub fn render_error(summary: &str, filename: &str, line_no: usize, col: usize, code_line: &str, caret: &str, suggestions: &str) -> String {
-    let (bred, red, bblue, byellow, dim, reset) = color_codes();
+pub fn render_error(diagnostic: &Diagnostic, options: &RenderOptions) -> String {
+    let (bred, red, bblue, byellow, _, reset) = color_codes(options.color);
+    let Some(span) = diagnostic.span.filter(|_| diagnostic.friendly) else {
+        return diagnostic.message.clone();
+    };
     format!(
-        "\n{bred}❌ 错误：{summary}{reset}\n  {bblue}--> {filename}:{line_no}:{col}{reset}\n   |\n {dim}{line_no}{reset} | {line}\n   | {red}{caret}{reset}\n{byellow}💡 修复建议：{reset}\n{suggestions}\n",
-        summary = summary,
-        filename = filename,
-        line_no = line_no,
-        col = col,
-        line = code_line,
-        caret = caret,
-        suggestions = suggestions,
+        "\n{bred}❌ 错误：{summary}{reset}\n  {bblue}--> {filename}:{line_no}:{col}{reset}\n   |\n{block}\n{byellow}💡 修复建议：{reset}\n{suggestions}\n",
+        summary = diagnostic.message,
+        filename = diagnostic.file,
+        line_no = span.start.line,
+        col = span.start.col,
+        block = source_block(span, &diagnostic.code_line, red, options),
+        suggestions = diagnostic.suggestions,
         bred = bred,
-        red = red,
         bblue = bblue,
         byellow = byellow,
-        dim = dim,
         reset = reset,
     )
 }
+
+/// 渲染标准化的Rust风格警告块（带颜色）
+/// 
+/// # 参数
+/// 与`render_error`相同
+/// 
+/// # 返回值
+/// * `String` - 格式化的警告诊断信息；不带源码行的诊断只返回`message`
+/// 
+/// # 说明
+/// 警告不会使编译失败，格式与错误相同，只是标题和插入符号使用黄色
+pub fn render_warning(diagnostic: &Diagnostic, options: &RenderOptions) -> String {
+    let (_, _, bblue, byellow, _, reset) = color_codes(options.color);
+    let Some(span) = diagnostic.span.filter(|_| diagnostic.friendly) else {
+        return diagnostic.message.clone();
+    };
+    let (summary, filename, suggestions) = (&diagnostic.message, &diagnostic.file, &diagnostic.suggestions);
+    let (line_no, col) = (span.start.line, span.start.col);
+    let block = source_block(span, &diagnostic.code_line, byellow, options);
+    format!(
+        "\n{byellow}⚠️ 警告：{summary}{reset}\n  {bblue}--> {filename}:{line_no}:{col}{reset}\n   |\n{block}\n{byellow}💡 修复建议：{reset}\n{suggestions}\n",
+    )
+}
+
+/// 渲染检查通过的提示信息（粗体绿色）
+/// 
+/// # 参数
+/// * `message` - 提示信息
+/// * `options` - 显示选项
+/// 
+/// # 返回值
+/// * `String` - 格式化的提示信息；不使用颜色时只有文字
+pub fn render_success(message: &str, options: &RenderOptions) -> String {
+    let (_, _, _, _, _, reset) = color_codes(options.color);
+    let bgreen = if reset.is_empty() { "" } else { "\x1b[1;32m" };
+    format!("{bgreen}✅ {message}{reset}")
+}
+
+/// 诊断信息的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 错误：编译失败
+    Error,
+    /// 警告：不影响编译
+    Warning,
+}
+
+impl Severity {
+    /// 获取严重程度的名称（用于JSON输出）
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// 一条诊断信息（与显示方式无关的数据）
+/// 
+/// # 字段
+/// * `severity` - 严重程度
+/// * `file` - 文件名（与终端输出中显示的相同）
+/// * `span` - 出错位置的源码范围（与具体位置无关的错误为None）
+/// * `message` - 错误摘要
+/// * `suggestions` - 多行修复建议（可以为空）
+/// * `code_line` - 出错位置所在的源码行（用于终端输出；范围跨越多行时为以换行分隔的各行）
+/// * `friendly` - 终端输出是否使用带源码行和修复建议的诊断块（否则只输出`message`）
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    pub span: Option<SourceSpan>,
+    pub message: String,
+    pub suggestions: String,
+    code_line: String,
+    friendly: bool,
+}
+
+impl Diagnostic {
+    /// 创建错误诊断（终端输出为`render_error`的诊断块）
+    /// 
+    /// # 参数
+    /// * `summary` - 错误摘要
+    /// * `filename` - 文件名
+    /// * `span` - 插入符号标记的源码范围（诊断块头部的列号为其起始列）
+    /// * `code_line` - 出错位置所在的源码行（范围跨越多行时可以用`get_lines`取出各行）
+    /// * `suggestions` - 多行建议文本（已组合好）
+    pub fn error(summary: &str, filename: &str, span: SourceSpan, code_line: &str, suggestions: &str) -> Self {
+        Self::friendly(Severity::Error, summary, filename, span, code_line, suggestions)
+    }
+
+    /// 创建警告诊断（终端输出为`render_warning`的诊断块），参数与`error`相同
+    pub fn warning(summary: &str, filename: &str, span: SourceSpan, code_line: &str, suggestions: &str) -> Self {
+        Self::friendly(Severity::Warning, summary, filename, span, code_line, suggestions)
+    }
+
+    fn friendly(severity: Severity, summary: &str, filename: &str, span: SourceSpan, code_line: &str, suggestions: &str) -> Self {
+        Diagnostic {
+            severity,
+            file: filename.to_string(),
+            span: Some(span),
+            message: summary.to_string(),
+            suggestions: suggestions.to_string(),
+            code_line: code_line.to_string(),
+            friendly: true,
+        }
+    }
+
+    /// 创建只有一行信息的诊断（如语法错误，终端输出只显示`message`）
+    /// 
+    /// # 参数
+    /// * `severity` - 严重程度
+    /// * `filename` - 文件名
+    /// * `span` - 出错位置（未知时为None）
+    /// * `message` - 完整的错误信息
+    pub fn plain(severity: Severity, filename: &str, span: Option<SourceSpan>, message: &str) -> Self {
+        Diagnostic {
+            severity,
+            file: filename.to_string(),
+            span,
+            message: message.to_string(),
+            suggestions: String::new(),
+            code_line: String::new(),
+            friendly: false,
+        }
+    }
+
+    /// 按指定的显示选项渲染为终端输出的文本
+    pub fn render(&self, options: &RenderOptions) -> String {
+        match self.severity {
+            Severity::Error => render_error(self, options),
+            Severity::Warning => render_warning(self, options),
+        }
+    }
+
+    /// 排序用的位置：按文件名、起始行号、起始列号排序，没有位置信息的排在同一文件的最后
+    pub fn sort_key(&self) -> (&str, bool, usize, usize) {
+        match self.span {
+            Some(span) => (&self.file, false, span.start.line, span.start.col),
+            None => (&self.file, true, 0, 0),
+        }
+    }
+
+    /// 渲染为JSON对象
+    /// 
+    /// # 格式
+    /// `{"severity":"error","file":"a.kr","line":3,"column":5,"end_line":3,"end_column":8,"message":"...","suggestions":"..."}`，
+    /// 行号和列号从1开始，`end_column`不包含在范围内；没有位置信息时行号和列号为null
+    pub fn to_json(&self) -> String {
+        let position = |value: Option<usize>| value.map_or("null".to_string(), |v| v.to_string());
+        format!(
+            "{{\"severity\":\"{}\",\"file\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{},\"message\":{},\"suggestions\":{}}}",
+            self.severity.name(),
+            json_string(&self.file),
+            position(self.span.map(|s| s.start.line)),
+            position(self.span.map(|s| s.start.col)),
+            position(self.span.map(|s| s.end.line)),
+            position(self.span.map(|s| s.end.col)),
+            json_string(&self.message),
+            json_string(&self.suggestions),
+        )
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions::default()))
+    }
+}
+
+/// 一组错误诊断（作为编译失败时的错误类型，调用方可以用`downcast_ref`取出诊断数据）
+/// 
+/// 显示为各诊断的终端输出（使用默认的显示选项），以换行分隔
+#[derive(Debug)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    /// 按指定的显示选项渲染全部诊断，以换行分隔
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let rendered: Vec<String> = self.0.iter().map(|diagnostic| diagnostic.render(options)).collect();
+        rendered.join("\n")
+    }
+}
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(&RenderOptions::default()))
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+/// 诊断信息的报告目标
+/// 
+/// # 字段
+/// * `echo` - 报告时立即输出到标准错误所用的显示选项（None表示不输出）
+/// * `collect` - 是否保存报告的诊断信息（之后用`take`取出）
+/// 
+/// # 说明
+/// 编译过程中不影响结果的诊断信息（如警告）通过调用方传入的`Reporter`报告，
+/// 由调用方决定立即输出还是保存起来统一处理（如以JSON输出）；导致编译失败的错误不经过这里，
+/// 而是作为`Diagnostics`错误返回。默认值既不输出也不保存
+#[derive(Debug, Default)]
+pub struct Reporter {
+    echo: Option<RenderOptions>,
+    collect: bool,
+    collected: RefCell<Vec<Diagnostic>>,
+}
+
+impl Reporter {
+    /// 创建报告目标，参数见类型的说明
+    pub fn new(echo: Option<RenderOptions>, collect: bool) -> Self {
+        Reporter { echo, collect, collected: RefCell::new(Vec::new()) }
+    }
+
+    /// 报告一条诊断信息
+    pub fn report(&self, diagnostic: &Diagnostic) {
+        if let Some(options) = &self.echo {
+            eprintln!("{}", diagnostic.render(options));
+        }
+        if self.collect {
+            self.collected.borrow_mut().push(diagnostic.clone());
+        }
+    }
+
+    /// 取出已保存的全部诊断信息（按报告顺序）
+    pub fn take(&self) -> Vec<Diagnostic> {
+        self.collected.take()
+    }
+}
+
+/// 把诊断信息列表渲染为JSON数组
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let items: Vec<String> = diagnostics.iter().map(Diagnostic::to_json).collect();
+    format!("[{}]", items.join(","))
+}
+
+/// 把字符串编码为JSON字符串字面量（包括两侧的引号）
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::ast::span::SourcePos;
+
+    #[test]
+    fn caret_line_keeps_leading_tabs() {
+        let line = "\t\tfoo = 1";
+        let caret = caret_line(SourceSpan::single_line(1, 3, 6), line, '^');
+        assert_eq!(caret, "\t\t^^^");
+        assert_eq!(caret_line(SourceSpan::single_line(1, 4, 5), "\t  x = 1", '^'), "\t  ^");
+    }
+
+    #[test]
+    fn render_uses_the_given_options() {
+        let diagnostic = Diagnostic::error("未定义的变量", "main.kr", SourceSpan::single_line(2, 5, 8), "x = foo", "");
+
+        let plain = diagnostic.render(&RenderOptions { color: false, caret_char: '~' });
+        assert!(plain.contains("~~~"), "{plain}");
+        assert!(!plain.contains('^'), "{plain}");
+        assert!(!plain.contains("\x1b["), "{plain}");
+
+        let colored = diagnostic.render(&RenderOptions { color: true, caret_char: '^' });
+        assert!(colored.contains("\x1b["), "{colored}");
+        assert!(colored.contains("^^^"), "{colored}");
+    }
+
+    #[test]
+    fn display_uses_default_options() {
+        let diagnostic = Diagnostic::error("未定义的变量", "main.kr", SourceSpan::single_line(2, 5, 8), "x = foo", "");
+        assert_eq!(diagnostic.to_string(), diagnostic.render(&RenderOptions::default()));
+        assert_eq!(Diagnostics(vec![diagnostic.clone()]).to_string(), diagnostic.render(&RenderOptions::default()));
+    }
+
+    #[test]
+    fn renders_single_line_span_unchanged() {
+        let diagnostic = Diagnostic::error("未定义的变量", "main.kr", SourceSpan::single_line(3, 5, 8), "x = foo", "   - 建议");
+        let expected = "\n❌ 错误：未定义的变量\n  --> main.kr:3:5\n   |\n 3 | x = foo\n   |     ^^^\n💡 修复建议：\n   - 建议\n";
+        assert_eq!(diagnostic.render(&RenderOptions::default()), expected);
+    }
+
+    #[test]
+    fn renders_each_line_of_a_multi_line_span() {
+        let source = "a\nb\nc\nd\ne\nf\ng\nh\nx = foo(1,\n\n        2,\n    3)\n";
+        let span = SourceSpan { start: SourcePos { line: 9, col: 5 }, end: SourcePos { line: 12, col: 7 } };
+        let diagnostic = Diagnostic::error("参数错误", "main.kr", span, &get_lines(source, span), "");
+        let rendered = diagnostic.render(&RenderOptions::default());
+        let block = [
+            "  9 | x = foo(1,",
+            "    |     ^^^^^^",
+            " 10 | ",
+            " 11 |         2,",
+            "    |         ^^",
+            " 12 |     3)",
+            "    |     ^^",
+        ]
+        .join("\n");
+        assert!(rendered.contains(&format!("  --> main.kr:9:5\n   |\n{block}\n💡")), "{rendered}");
+    }
+}
@@ -0,0 +1,139 @@
+use crate::compiler::ast::{CompareOp, Expr, SourceSpan};
+use super::analysis::{Mutability, SemanticInfo};
+
+/// 在编译期计算只由字面量和常量组成的表达式（常量折叠）
+/// 
+/// # 参数
+/// * `expr` - 要计算的表达式
+/// * `semantic` - 当前代码块的语义分析信息（用于取出常量的初始值）
+/// 
+/// # 返回值
+/// * `Option<Expr>` - 计算结果对应的字面量（源码位置为原表达式的位置）；
+///   表达式中有变量、函数调用、typeof或读取输入，或者无法在编译期安全地计算时返回None
+/// 
+/// # 计算规则
+/// 与生成的Rust代码在运行时的结果完全相同：
+/// 1. 整数运算为i64运算，除法向0取整；溢出或除以0时不折叠（保留给运行时按溢出处理方式处理）
+/// 2. 整数与浮点数混合运算时整数转换为f64；结果不是有限值（如 `1.0 / 0.0`）时不折叠
+/// 3. 两个字符串相加为拼接
+/// 4. 比较运算的两侧为同类值（整数与浮点数混合时同样提升为f64）
+pub fn fold_const(expr: &Expr, semantic: &SemanticInfo) -> Option<Expr> {
+    let span = expr.span();
+    let folded = match expr {
+        Expr::IntLit(..) | Expr::FloatLit(..) | Expr::StringLit(..) | Expr::BoolLit(..) => expr.clone(),
+        // 与常量同名的函数参数遮蔽常量，不能折叠
+        Expr::Ident(name, _) if semantic.vars.get(name) == Some(&Mutability::Const) => {
+            fold_const(semantic.consts.get(name)?, semantic)?
+        }
+        Expr::Ident(..) => return None,
+        Expr::Group(inner, _) => fold_const(inner, semantic)?,
+        Expr::Neg(inner, _) => match fold_const(inner, semantic)? {
+            Expr::IntLit(v, _) => Expr::IntLit(v.checked_neg()?, span),
+            Expr::FloatLit(v, _) => Expr::FloatLit(-v, span),
+            _ => return None,
+        },
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _) => {
+            let (lhs, rhs) = (fold_const(a, semantic)?, fold_const(b, semantic)?);
+            match (&lhs, &rhs) {
+                (Expr::IntLit(x, _), Expr::IntLit(y, _)) => {
+                    let (x, y) = (*x, *y);
+                    let value = match expr {
+                        Expr::BinaryAdd(..) => x.checked_add(y),
+                        Expr::BinarySub(..) => x.checked_sub(y),
+                        Expr::BinaryMul(..) => x.checked_mul(y),
+                        Expr::BinaryDiv(..) => x.checked_div(y),
+                        _ => x.checked_rem(y),
+                    };
+                    Expr::IntLit(value?, span)
+                }
+                (Expr::StringLit(x, _), Expr::StringLit(y, _)) if matches!(expr, Expr::BinaryAdd(..)) => {
+                    Expr::StringLit(format!("{x}{y}"), span)
+                }
+                _ => {
+                    let (x, y) = (as_float(&lhs)?, as_float(&rhs)?);
+                    let value = match expr {
+                        Expr::BinaryAdd(..) => x + y,
+                        Expr::BinarySub(..) => x - y,
+                        Expr::BinaryMul(..) => x * y,
+                        Expr::BinaryDiv(..) => x / y,
+                        _ => x % y,
+                    };
+                    if !value.is_finite() {
+                        return None;
+                    }
+                    Expr::FloatLit(value, span)
+                }
+            }
+        }
+        Expr::Compare(op, a, b, _) => {
+            let (lhs, rhs) = (fold_const(a, semantic)?, fold_const(b, semantic)?);
+            let result = match (&lhs, &rhs) {
+                (Expr::IntLit(x, _), Expr::IntLit(y, _)) => compare(*op, x, y),
+                (Expr::StringLit(x, _), Expr::StringLit(y, _)) => compare(*op, x, y),
+                (Expr::BoolLit(x, _), Expr::BoolLit(y, _)) => compare(*op, x, y),
+                _ => compare(*op, &as_float(&lhs)?, &as_float(&rhs)?),
+            };
+            Expr::BoolLit(result, span)
+        }
+        Expr::TypeOf(..) | Expr::Call(..) | Expr::Read(..) => return None,
+    };
+    Some(with_span(folded, span))
+}
+
+/// 获取数字字面量的值（整数转换为f64），其他字面量返回None
+fn as_float(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::IntLit(v, _) => Some(*v as f64),
+        Expr::FloatLit(v, _) => Some(*v),
+        _ => None,
+    }
+}
+
+/// 按比较运算符比较两个同类值
+fn compare<T: PartialOrd + ?Sized>(op: CompareOp, a: &T, b: &T) -> bool {
+    match op {
+        CompareOp::Lt => a < b,
+        CompareOp::Le => a <= b,
+        CompareOp::Gt => a > b,
+        CompareOp::Ge => a >= b,
+        CompareOp::Eq => a == b,
+        CompareOp::Ne => a != b,
+    }
+}
+
+/// 把字面量的源码位置替换为原表达式的位置
+fn with_span(literal: Expr, span: SourceSpan) -> Expr {
+    match literal {
+        Expr::IntLit(v, _) => Expr::IntLit(v, span),
+        Expr::FloatLit(v, _) => Expr::FloatLit(v, span),
+        Expr::StringLit(s, _) => Expr::StringLit(s, span),
+        Expr::BoolLit(v, _) => Expr::BoolLit(v, span),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::parser::expr::parse_expr;
+
+    /// 折叠表达式字符串（没有变量和常量）
+    fn fold(s: &str) -> Option<Expr> {
+        fold_const(&parse_expr(s, 1, 0).unwrap(), &SemanticInfo::default())
+    }
+
+    #[test]
+    fn folds_integer_arithmetic_with_precedence() {
+        assert!(matches!(fold("2 + 3 * 4"), Some(Expr::IntLit(14, _))));
+    }
+
+    #[test]
+    fn does_not_fold_division_by_zero() {
+        assert!(fold("1 / 0").is_none());
+        assert!(fold("1 % 0").is_none());
+    }
+}
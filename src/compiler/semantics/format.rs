@@ -0,0 +1,91 @@
+use crate::compiler::ast::Expr;
+
+/// 格式字符串中的占位符
+/// 
+/// # 变体
+/// * `Positional` - 位置占位符：`{}`（可带格式说明，如 `{:>5}`）
+/// * `Named` - 命名占位符：`{name}`（可带格式说明，如 `{name:>5}`），引用同名变量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placeholder {
+    /// 位置占位符
+    Positional,
+    /// 命名占位符
+    Named(String),
+}
+
+/// 解析格式字符串中的全部占位符
+/// 
+/// # 参数
+/// * `s` - 格式字符串（print的字符串内容）
+/// 
+/// # 返回值
+/// * `Result<Vec<Placeholder>, String>` - 按出现顺序返回占位符，格式错误时返回错误描述
+/// 
+/// # 规则
+/// 1. `{{` 和 `}}` 表示字面量花括号，不是占位符
+/// 2. `{}` 或 `{:说明}` 是位置占位符
+/// 3. `{变量名}` 或 `{变量名:说明}` 是命名占位符
+pub fn parse_placeholders(s: &str) -> Result<Vec<Placeholder>, String> {
+    let mut placeholders = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '{' => {
+                // 读取到对应的右花括号
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => return Err("格式字符串中的 `{` 没有对应的 `}`（如需输出花括号本身，请写 `{{`）".to_string()),
+                    }
+                }
+
+                let name = inner.split(':').next().unwrap_or("").trim();
+                if name.is_empty() {
+                    placeholders.push(Placeholder::Positional);
+                } else if is_placeholder_name(name) {
+                    placeholders.push(Placeholder::Named(name.to_string()));
+                } else {
+                    return Err(format!("格式字符串中的占位符 `{{{inner}}}` 无效，只支持 `{{}}` 或 `{{变量名}}`"));
+                }
+            }
+            '}' => {
+                return Err("格式字符串中的 `}` 没有对应的 `{`（如需输出花括号本身，请写 `}}`）".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(placeholders)
+}
+
+/// 检查占位符中的名称是否为有效的变量名
+fn is_placeholder_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 判断print语句是否为格式字符串形式
+/// 
+/// # 参数
+/// * `args` - print语句的参数
+/// 
+/// # 返回值
+/// * `bool` - 第一个参数是含 `{}` 位置占位符的字符串字面量时返回true，
+///   此时其余参数依次填入占位符；否则各参数以空格分隔输出
+pub fn is_format_print(args: &[Expr]) -> bool {
+    match args.first() {
+        Some(Expr::StringLit(content, _)) => parse_placeholders(content)
+            .is_ok_and(|placeholders| placeholders.contains(&Placeholder::Positional)),
+        _ => false,
+    }
+}
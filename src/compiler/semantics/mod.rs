@@ -6,5 +6,22 @@ pub mod diagnostics;
 /// 执行变量声明检查、不可变性规则验证等语义分析
 pub mod analysis;
 
+/// 类型模块
+/// 定义Kairo的类型以及表达式的类型推断
+pub mod types;
+
+/// 作用域模块
+/// 按代码块嵌套记录已声明的变量
+pub mod scope;
+
+/// 格式字符串模块
+/// 解析print字符串中的占位符
+pub mod format;
+
+/// 常量折叠模块
+/// 在编译期计算只由字面量和常量组成的表达式
+pub mod fold;
+
 /// 导出语义分析的主要类型和函数
-pub use analysis::{check_semantics, Mutability, SemanticInfo};
+pub use analysis::{check_semantics, Mutability, SemanticInfo, SemanticOptions};
+pub use types::{FnSig, Type};
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use super::analysis::Mutability;
+use super::types::Type;
+
+/// 作用域栈
+/// 
+/// 每个作用域记录其中声明的变量名及其可变性、类型；
+/// 进入代码块时压入新作用域，离开时弹出，查找变量时从最内层向外逐层查找
+/// 
+/// # 字段
+/// * `scopes` - 作用域列表，第一个为全局作用域，最后一个为当前作用域
+#[derive(Debug)]
+pub struct ScopeStack<'a> {
+    scopes: Vec<Scope<'a>>,
+}
+
+/// 单个作用域
+/// 
+/// # 字段
+/// * `vars` - 变量名到可变性和类型（无法推断时为None）的映射表
+/// * `function` - 是否为函数体的作用域：函数体看不到外层的变量，只能看到外层的常量
+#[derive(Debug, Default)]
+struct Scope<'a> {
+    vars: HashMap<&'a str, (Mutability, Option<Type>)>,
+    function: bool,
+}
+
+impl Default for ScopeStack<'_> {
+    fn default() -> Self {
+        ScopeStack { scopes: vec![Scope::default()] }
+    }
+}
+
+impl<'a> ScopeStack<'a> {
+    /// 进入代码块，压入新的作用域
+    pub fn push(&mut self) {
+        self.scopes.push(Scope::default());
+    }
+
+    /// 进入函数体，压入新的作用域（外层的变量在其中不可见）
+    pub fn push_function(&mut self) {
+        self.scopes.push(Scope { vars: HashMap::new(), function: true });
+    }
+
+    /// 离开代码块，弹出当前作用域（全局作用域不会被弹出）
+    pub fn pop(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// 在当前作用域中声明变量（同名变量会被覆盖）
+    /// 
+    /// # 参数
+    /// * `name` - 变量名
+    /// * `mutability` - 变量的可变性
+    /// * `ty` - 变量的类型（无法推断时为None）
+    pub fn declare(&mut self, name: &'a str, mutability: Mutability, ty: Option<Type>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.vars.insert(name, (mutability, ty));
+        }
+    }
+
+    /// 从当前作用域向外查找变量
+    /// 
+    /// # 返回值
+    /// * `Option<(Mutability, Option<Type>)>` - 找到时返回离当前作用域最近的声明；
+    ///   越过函数体的作用域之后只查找常量
+    pub fn lookup(&self, name: &str) -> Option<(Mutability, Option<Type>)> {
+        let mut outside_function = false;
+        for scope in self.scopes.iter().rev() {
+            if let Some(&(mutability, ty)) = scope.vars.get(name)
+                && (!outside_function || mutability == Mutability::Const)
+            {
+                return Some((mutability, ty));
+            }
+            outside_function |= scope.function;
+        }
+        None
+    }
+
+    /// 判断变量在当前可见的作用域中是否已声明
+    pub fn contains(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::compiler::ast::{Expr, ReadKind};
+
+/// 表达式和变量的类型
+/// 
+/// # 变体
+/// * `Int` - 整数（i64）
+/// * `Float` - 浮点数（f64）
+/// * `Str` - 字符串
+/// * `Bool` - 布尔值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    /// 整数
+    Int,
+    /// 浮点数
+    Float,
+    /// 字符串
+    Str,
+    /// 布尔值
+    Bool,
+}
+
+impl Type {
+    /// 根据类型注解中的名称获取类型
+    /// 
+    /// # 参数
+    /// * `name` - 类型名称（与typeof的结果一致：int、float、string、bool）
+    /// 
+    /// # 返回值
+    /// * `Option<Type>` - 名称有效时返回对应的类型
+    pub fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "float" => Some(Type::Float),
+            "string" => Some(Type::Str),
+            "bool" => Some(Type::Bool),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    /// 输出面向用户的类型名称（与typeof的结果一致）
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Str => "string",
+            Type::Bool => "bool",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 函数签名
+/// 
+/// # 字段
+/// * `params` - 各参数的名称和类型（省略类型注解的参数为int）
+/// * `ret` - 返回值类型（函数没有返回值或无法推断时为None）
+/// * `returns_value` - 函数体中是否有带值的return语句
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnSig {
+    pub params: Vec<(String, Type)>,
+    pub ret: Option<Type>,
+    pub returns_value: bool,
+}
+
+/// 推断表达式的类型
+/// 
+/// # 参数
+/// * `expr` - 要推断的表达式
+/// * `types` - 已知的变量类型映射表
+/// * `functions` - 已知的函数签名映射表
+/// 
+/// # 返回值
+/// * `Option<Type>` - 能确定类型时返回类型，否则返回None
+/// 
+/// # 推断规则
+/// 1. 字面量：由字面量本身决定
+/// 2. 标识符：查找变量类型表
+/// 3. 算术运算：两侧都是整数时为整数；
+///    整数与浮点数混合时，整数提升为浮点数，结果为浮点数
+/// 4. 字符串相加：结果为字符串
+/// 5. typeof(x)：结果为字符串
+/// 6. 比较运算：结果为布尔值
+/// 7. 取负：与操作数类型相同（只能用于整数和浮点数）
+/// 8. 函数调用：函数的返回值类型
+/// 9. 读取输入：read() 为字符串，read_int() 为整数
+pub fn infer_type(expr: &Expr, types: &HashMap<String, Type>, functions: &HashMap<String, FnSig>) -> Option<Type> {
+    match expr {
+        Expr::StringLit(..) => Some(Type::Str),
+        Expr::IntLit(..) => Some(Type::Int),
+        Expr::FloatLit(..) => Some(Type::Float),
+        Expr::BoolLit(..) => Some(Type::Bool),
+        Expr::TypeOf(..) => Some(Type::Str),
+        Expr::Compare(..) => Some(Type::Bool),
+        Expr::Ident(name, _) => types.get(name).copied(),
+        Expr::Call(name, _, _) => functions.get(name)?.ret,
+        Expr::Read(ReadKind::Line, _) => Some(Type::Str),
+        Expr::Read(ReadKind::Int, _) => Some(Type::Int),
+        Expr::Group(inner, _) => infer_type(inner, types, functions),
+        Expr::Neg(inner, _) => match infer_type(inner, types, functions)? {
+            ty @ (Type::Int | Type::Float) => Some(ty),
+            _ => None,
+        },
+        Expr::BinaryAdd(a, b, _)
+        | Expr::BinarySub(a, b, _)
+        | Expr::BinaryMul(a, b, _)
+        | Expr::BinaryDiv(a, b, _)
+        | Expr::BinaryMod(a, b, _) => {
+            match (infer_type(a, types, functions)?, infer_type(b, types, functions)?) {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                (Type::Int | Type::Float, Type::Int | Type::Float) => Some(Type::Float),
+                (Type::Str, Type::Str) if matches!(expr, Expr::BinaryAdd(..)) => Some(Type::Str),
+                _ => None,
+            }
+        }
+    }
+}
@@ -0,0 +1,4 @@
+/// Kairo编程语言编译器库
+/// 
+/// 供命令行工具以及编辑器、语言服务器等外部工具使用
+pub mod compiler;
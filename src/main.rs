@@ -1,22 +1,34 @@
 /// 命令行接口模块
 mod cli;
 
-/// 编译器模块
-mod compiler;
-
 /// Kairo编程语言编译器的主入口点
 /// 
 /// # 功能
 /// 1. 解析命令行参数
 /// 2. 执行相应的编译或运行操作
 /// 3. 处理错误并显示友好的错误信息
+/// 
+/// # 错误输出
+/// 默认只显示根本原因（通常是编译器构造的友好消息）；
+/// 指定--full-errors或KAIRO_FULL_ERROR=1时显示完整的错误链，
+/// 同时设置RUST_BACKTRACE=1时还会显示调用栈；
+/// 指定--diagnostics=json时错误只出现在JSON输出中；
+/// 指定--no-stderr-diagnostics时错误只写入--diagnostics-out指定的文件
 fn main() {
-    if let Err(e) = cli::run() {
-        // 优先显示根本原因（通常是我们编译器构造的友好消息）
-        if let Some(root) = e.chain().last() {
-            eprintln!("{}", root);
+    let cli = cli::parse_args();
+    let full_errors = cli.full_errors();
+    let quiet = cli.json_diagnostics() || !cli.stderr_diagnostics();
+    let render = cli.render_options();
+
+    if let Err(e) = cli::run(cli) {
+        if quiet {
+            // 错误已包含在JSON输出中
+        } else if full_errors {
+            // 完整的错误链（包含上下文和调用栈）
+            eprintln!("{:?}", e);
         } else {
-            eprintln!("{}", e);
+            // 优先显示根本原因（通常是我们编译器构造的友好消息）
+            cli::print_error(&e, &render);
         }
         std::process::exit(1);
     }
@@ -0,0 +1,331 @@
+//! 命令行接口的集成测试：运行编译出的kairo程序并检查其退出状态和输出
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+/// 创建测试专用的空临时目录（按测试名称和进程号区分，可以并行运行）
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("kairo-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 在指定目录中运行kairo（不带颜色）
+fn kairo(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_kairo"))
+        .args(args)
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("failed to run kairo")
+}
+
+/// 在指定目录中运行kairo，并把`input`写入其标准输入（写完后关闭，之后的读取遇到EOF）
+fn kairo_with_stdin(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kairo"))
+        .args(args)
+        .current_dir(dir)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run kairo");
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().expect("failed to wait for kairo")
+}
+
+/// 修改目录的只读权限
+fn set_read_only(dir: &Path, read_only: bool) {
+    let mut permissions = fs::metadata(dir).unwrap().permissions();
+    permissions.set_readonly(read_only);
+    fs::set_permissions(dir, permissions).unwrap();
+}
+
+#[test]
+fn check_fails_on_warnings_only_with_deny_warnings() {
+    let dir = temp_dir("deny-warnings");
+    fs::write(dir.join("unused.kr"), "x = 1\nprint(2)\n").unwrap();
+
+    let normal = kairo(&dir, &["check", "unused.kr"]);
+    assert!(normal.status.success(), "{normal:?}");
+    let denied = kairo(&dir, &["check", "--deny-warnings", "unused.kr"]);
+    assert!(!denied.status.success(), "{denied:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn interpret_runs_in_read_only_directory() {
+    let dir = temp_dir("read-only");
+    fs::write(dir.join("hello.kr"), "print(\"hi\")\n").unwrap();
+    set_read_only(&dir, true);
+    // 特权用户（如容器中的root）不受目录权限限制，改为用同名文件占住输出目录的位置
+    let probe = dir.join("probe");
+    if fs::write(&probe, "").is_ok() {
+        fs::remove_file(&probe).unwrap();
+        fs::write(dir.join("target"), "").unwrap();
+    }
+
+    let interpreted = kairo(&dir, &["run", "--interpret", "hello.kr"]);
+    let compiled = kairo(&dir, &["run", "hello.kr"]);
+    set_read_only(&dir, false);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(interpreted.status.success(), "{interpreted:?}");
+    assert_eq!(String::from_utf8_lossy(&interpreted.stdout), "hi\n");
+    assert!(!compiled.status.success(), "{compiled:?}");
+    let stderr = String::from_utf8_lossy(&compiled.stderr);
+    assert!(stderr.contains("cannot create output directory target/kairo_out"), "{stderr}");
+}
+
+#[test]
+fn build_emits_both_exe_and_rust() {
+    let dir = temp_dir("emit-exe-rust");
+    fs::write(dir.join("hello.kr"), "print(\"hi\")\n").unwrap();
+
+    let output = kairo(&dir, &["build", "--emit", "exe,rust", "hello.kr"]);
+    assert!(output.status.success(), "{output:?}");
+    let out_dir = dir.join("target").join("kairo_out");
+    let exe = out_dir.join(if cfg!(windows) { "hello.exe" } else { "hello" });
+    assert!(exe.is_file(), "missing {}", exe.display());
+    assert!(out_dir.join("hello.rs").is_file(), "missing hello.rs");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn build_continues_after_a_failing_file() {
+    let dir = temp_dir("build-many");
+    fs::write(dir.join("a.kr"), "print(\"a\")\n").unwrap();
+    fs::write(dir.join("b.kr"), "print(missing)\n").unwrap();
+    fs::write(dir.join("c.kr"), "print(\"c\")\n").unwrap();
+
+    let output = kairo(&dir, &["build", "a.kr", "b.kr", "c.kr"]);
+    assert!(!output.status.success(), "{output:?}");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed: b.kr"), "{stderr}");
+    assert!(stderr.contains("2 of 3 files built; failed: b.kr"), "{stderr}");
+    let out_dir = dir.join("target").join("kairo_out");
+    for name in ["a", "c"] {
+        let exe = out_dir.join(if cfg!(windows) { format!("{name}.exe") } else { name.to_string() });
+        assert!(exe.is_file(), "missing {}", exe.display());
+    }
+    assert!(!out_dir.join(if cfg!(windows) { "b.exe" } else { "b" }).exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dash_reads_source_from_stdin() {
+    let dir = temp_dir("stdin-source");
+
+    let ok = kairo_with_stdin(&dir, &["run", "--interpret", "-"], "print(\"from stdin\")\n");
+    assert!(ok.status.success(), "{ok:?}");
+    assert_eq!(String::from_utf8_lossy(&ok.stdout), "from stdin\n");
+    let bad = kairo_with_stdin(&dir, &["run", "--interpret", "-"], "print(missing)\n");
+    assert!(!bad.status.success(), "{bad:?}");
+    let stderr = String::from_utf8_lossy(&bad.stderr);
+    assert!(stderr.contains("--> <stdin>:1:7"), "{stderr}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn fmt_check_reports_unformatted_files() {
+    let dir = temp_dir("fmt-check");
+    fs::write(dir.join("messy.kr"), "x=1\nprint( x )\n").unwrap();
+    fs::write(dir.join("tidy.kr"), "x = 1\nprint(x)\n").unwrap();
+
+    let messy = kairo(&dir, &["fmt", "--check", "messy.kr"]);
+    assert!(!messy.status.success(), "{messy:?}");
+    assert!(String::from_utf8_lossy(&messy.stderr).contains("is not formatted"));
+    assert_eq!(fs::read_to_string(dir.join("messy.kr")).unwrap(), "x=1\nprint( x )\n");
+    let tidy = kairo(&dir, &["fmt", "--check", "tidy.kr"]);
+    assert!(tidy.status.success(), "{tidy:?}");
+
+    // 格式化之后再检查即可通过
+    assert!(kairo(&dir, &["fmt", "messy.kr"]).status.success());
+    assert!(kairo(&dir, &["fmt", "--check", "messy.kr"]).status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_expands_glob_patterns() {
+    let dir = temp_dir("glob");
+    fs::create_dir_all(dir.join("src/sub")).unwrap();
+    fs::write(dir.join("src/a.kr"), "print(\"a\")\n").unwrap();
+    fs::write(dir.join("src/sub/b.kr"), "print(\"b\")\n").unwrap();
+    fs::write(dir.join("src/notes.txt"), "not kairo\n").unwrap();
+
+    let all = kairo(&dir, &["run", "--interpret", "src/**/*.kr"]);
+    let top = kairo(&dir, &["run", "--interpret", "src/*.kr"]);
+    let literal = kairo(&dir, &["run", "--interpret", "src/sub/b.kr"]);
+    let unmatched = kairo(&dir, &["run", "--interpret", "lib/*.kr"]);
+    let no_glob = kairo(&dir, &["run", "--interpret", "--no-glob", "src/*.kr"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(all.status.success(), "{all:?}");
+    assert_eq!(String::from_utf8_lossy(&all.stdout), "a\nb\n2 of 2 files ran\n");
+    assert!(top.status.success(), "{top:?}");
+    assert_eq!(String::from_utf8_lossy(&top.stdout), "a\n");
+    assert!(literal.status.success(), "{literal:?}");
+    assert_eq!(String::from_utf8_lossy(&literal.stdout), "b\n");
+    assert!(!unmatched.status.success(), "{unmatched:?}");
+    assert!(String::from_utf8_lossy(&unmatched.stderr).contains("no files match `lib/*.kr`"), "{unmatched:?}");
+    assert!(!no_glob.status.success(), "{no_glob:?}");
+    assert!(String::from_utf8_lossy(&no_glob.stderr).contains("source file not found: src/*.kr"), "{no_glob:?}");
+}
+
+#[test]
+fn diagnostics_out_writes_json_to_file() {
+    let dir = temp_dir("diagnostics-out");
+    fs::write(dir.join("bad.kr"), "x = 1\nx = 2\nprint(x)\n").unwrap();
+
+    let output = kairo(&dir, &["check", "bad.kr", "--diagnostics-out", "reports/check.json", "--no-stderr-diagnostics"]);
+    assert!(!output.status.success(), "{output:?}");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json = fs::read_to_string(dir.join("reports/check.json")).unwrap();
+    assert!(json.starts_with("[{\"severity\":\"error\",\"file\":\"bad.kr\",\"line\":2,\"column\":1,"), "{json}");
+    assert!(json.contains("你试图修改不可变变量 `x`"), "{json}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn interpreter_reports_call_depth_and_step_limits() {
+    let dir = temp_dir("interp-limits");
+    fs::write(dir.join("rec.kr"), "fun f() { f() }\nf()\n").unwrap();
+
+    let default = kairo(&dir, &["run", "--interpret", "rec.kr"]);
+    assert!(!default.status.success(), "{default:?}");
+    assert!(String::from_utf8_lossy(&default.stderr).contains("函数调用嵌套超过了 1000 层的上限"), "{default:?}");
+
+    let depth = kairo(&dir, &["run", "--interpret", "--max-call-depth", "20", "rec.kr"]);
+    assert!(String::from_utf8_lossy(&depth.stderr).contains("函数调用嵌套超过了 20 层的上限"), "{depth:?}");
+
+    let steps = kairo(&dir, &["run", "--interpret", "--max-steps", "5", "rec.kr"]);
+    assert!(String::from_utf8_lossy(&steps.stderr).contains("执行的语句超过了 5 条的上限"), "{steps:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn check_dump_lowered_shows_folded_constants() {
+    let dir = temp_dir("dump-lowered");
+    fs::write(dir.join("fold.kr"), "x = 2 + 3 * 4\nprint(x)\n").unwrap();
+
+    let output = kairo(&dir, &["check", "--dump-lowered", "fold.kr"]);
+    assert!(output.status.success(), "{output:?}");
+    let dump = String::from_utf8_lossy(&output.stdout);
+    assert!(dump.contains("IntLit(\n                14,"), "{dump}");
+    assert!(!dump.contains("BinaryMul"), "{dump}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn compiled_program_reads_stdin() {
+    let dir = temp_dir("stdin");
+    fs::write(dir.join("double.kr"), "n = read_int()\nprint(n * 2)\n").unwrap();
+    fs::write(dir.join("line.kr"), "s = read()\nprint(\"[\" + s + \"]\")\n").unwrap();
+
+    let doubled = kairo_with_stdin(&dir, &["run", "double.kr"], "21\n");
+    assert!(doubled.status.success(), "{doubled:?}");
+    assert_eq!(String::from_utf8_lossy(&doubled.stdout), "42\n");
+
+    // 遇到EOF时read()返回空字符串，read_int()报告运行时错误
+    let empty = kairo_with_stdin(&dir, &["run", "line.kr"], "");
+    assert!(empty.status.success(), "{empty:?}");
+    assert_eq!(String::from_utf8_lossy(&empty.stdout), "[]\n");
+    let eof = kairo_with_stdin(&dir, &["run", "double.kr"], "");
+    assert!(!eof.status.success(), "{eof:?}");
+    assert!(String::from_utf8_lossy(&eof.stderr).contains("不是整数"), "{eof:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn full_errors_shows_the_whole_error_chain() {
+    let dir = temp_dir("full-errors");
+    fs::write(dir.join("bad.kr"), "print(y)\n").unwrap();
+
+    let friendly = kairo(&dir, &["build", "bad.kr"]);
+    let friendly_stderr = String::from_utf8_lossy(&friendly.stderr);
+    assert!(friendly_stderr.contains("使用了未定义的变量 `y`"), "{friendly_stderr}");
+    assert!(!friendly_stderr.contains("failed to compile"), "{friendly_stderr}");
+
+    let full = kairo(&dir, &["build", "--full-errors", "bad.kr"]);
+    let full_stderr = String::from_utf8_lossy(&full.stderr);
+    assert!(!full.status.success(), "{full:?}");
+    assert!(full_stderr.contains("failed to compile \"bad.kr\"\n\nCaused by:"), "{full_stderr}");
+    assert!(full_stderr.contains("使用了未定义的变量 `y`"), "{full_stderr}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_kairo"))
+        .args(["build", "bad.kr"])
+        .current_dir(&dir)
+        .env("NO_COLOR", "1")
+        .env("KAIRO_FULL_ERROR", "1")
+        .output()
+        .expect("failed to run kairo");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Caused by:"), "{output:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn escape_sequences_round_trip_through_compiled_program() {
+    let dir = temp_dir("escapes");
+    fs::write(dir.join("esc.kr"), "print(\"a\\tb\\nc\\\\d\\\"e\\r\\0!\")\nx = \"q\\n\"\nprint(x + \"z\")\n").unwrap();
+    fs::write(dir.join("bad.kr"), "print(\"a\\qb\")\n").unwrap();
+
+    let compiled = kairo(&dir, &["run", "esc.kr"]);
+    assert!(compiled.status.success(), "{compiled:?}");
+    assert_eq!(compiled.stdout, b"a\tb\nc\\d\"e\r\0!\nq\nz\n");
+    let interpreted = kairo(&dir, &["run", "--interpret", "esc.kr"]);
+    assert_eq!(interpreted.stdout, compiled.stdout);
+
+    let bad = kairo(&dir, &["check", "bad.kr"]);
+    assert!(!bad.status.success(), "{bad:?}");
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("未知的转义字符 `\\q`"), "{bad:?}");
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("第 1 行，第 9 列"), "{bad:?}");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn lenient_mode_compiles_immutable_reassignment() {
+    let dir = temp_dir("lenient");
+    fs::write(dir.join("reassign.kr"), "x = 1\nprint(x)\nx = x + 1\nprint(x)\n").unwrap();
+
+    let strict = kairo(&dir, &["run", "reassign.kr"]);
+    assert!(!strict.status.success(), "{strict:?}");
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("你试图修改不可变变量 `x`"), "{strict:?}");
+
+    let lenient = kairo(&dir, &["run", "--lenient", "reassign.kr"]);
+    assert!(lenient.status.success(), "{lenient:?}");
+    assert_eq!(String::from_utf8_lossy(&lenient.stdout), "1\n2\n");
+    let interpreted = kairo(&dir, &["run", "--interpret", "--lenient", "reassign.kr"]);
+    assert_eq!(String::from_utf8_lossy(&interpreted.stdout), "1\n2\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn print_rustc_command_does_not_run_rustc() {
+    let dir = temp_dir("print-rustc");
+    fs::write(dir.join("hello.kr"), "print(\"hi\")\n").unwrap();
+
+    let output = kairo(&dir, &["build", "--print-rustc-command", "-o", "out/app", "hello.kr"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let command = stdout.lines().find(|line| line.starts_with("rustc ")).expect("rustc command line");
+    assert!(command.contains("--edition=2024"), "{command}");
+    assert!(command.contains("-o out/app"), "{command}");
+    assert!(command.ends_with("hello.rs"), "{command}");
+    assert!(!dir.join("out/app").exists(), "rustc should not have run");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn run_accepts_program_arguments() {
+    let dir = temp_dir("run-args");
+    fs::write(dir.join("hello.kr"), "print(\"hi\")\n").unwrap();
+
+    let output = kairo(&dir, &["run", "hello.kr", "--", "foo", "--help", "bar"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "hi\n");
+    fs::remove_dir_all(&dir).unwrap();
+}